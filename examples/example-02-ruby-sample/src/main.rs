@@ -4,8 +4,7 @@ use libcnb::data::launch::{Launch, Process};
 use libcnb::data::{layer_name, process_type};
 use libcnb::detect::{DetectContext, DetectResult, DetectResultBuilder};
 use libcnb::generic::GenericPlatform;
-use libcnb::layer_env::TargetLifecycle;
-use libcnb::{buildpack_main, Buildpack, Env};
+use libcnb::{buildpack_main, Buildpack};
 
 use crate::util::{DownloadError, UntarError};
 use serde::Deserialize;
@@ -32,14 +31,10 @@ impl Buildpack for RubyBuildpack {
     fn build(&self, context: BuildContext<Self>) -> libcnb::Result<BuildResult, Self::Error> {
         println!("---> Ruby Buildpack");
 
-        let ruby_layer = context.handle_layer(layer_name!("ruby"), RubyLayer)?;
-
-        context.handle_layer(
-            layer_name!("bundler"),
-            BundlerLayer {
-                ruby_env: ruby_layer.env.apply(TargetLifecycle::Build, &Env::new()),
-            },
-        )?;
+        // The build environment of the context contains the Ruby installation of this layer
+        // afterwards, which is used by the bundler layer.
+        context.handle_layer(layer_name!("ruby"), RubyLayer)?;
+        context.handle_layer(layer_name!("bundler"), BundlerLayer)?;
 
         BuildResultBuilder::new()
             .launch(
@@ -74,13 +69,10 @@ pub enum RubyBuildpackError {
     RubyDownloadError(DownloadError),
     RubyUntarError(UntarError),
     CouldNotCreateTemporaryFile(std::io::Error),
-    CouldNotGenerateChecksum(std::io::Error),
     GemInstallBundlerCommandError(std::io::Error),
     GemInstallBundlerUnexpectedExitStatus(ExitStatus),
     BundleInstallCommandError(std::io::Error),
     BundleInstallUnexpectedExitStatus(ExitStatus),
-    BundleConfigCommandError(std::io::Error),
-    BundleConfigUnexpectedExitStatus(ExitStatus),
 }
 
 buildpack_main!(RubyBuildpack);
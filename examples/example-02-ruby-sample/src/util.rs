@@ -1,5 +1,4 @@
 use flate2::read::GzDecoder;
-use sha2::Digest;
 use std::fs;
 use std::io;
 use std::path::Path;
@@ -43,10 +42,6 @@ pub enum UntarError {
     CouldNotUnpack(std::io::Error),
 }
 
-pub fn sha256_checksum(path: impl AsRef<Path>) -> Result<String, std::io::Error> {
-    fs::read(path).map(|bytes| format!("{:x}", sha2::Sha256::digest(bytes)))
-}
-
 /// Helper to run very simple commands where we just need to handle IO errors and non-zero exit
 /// codes. Not very useful in complex scenarios, but can cut down the amount of code in simple
 /// cases.
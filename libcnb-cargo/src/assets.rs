@@ -0,0 +1,312 @@
+use cargo_metadata::MetadataCommand;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Finds the additional files that are packaged with the buildpack of the given project.
+///
+/// Files are configured with glob patterns in `package.metadata.libcnb.include` of the Cargo
+/// manifest. Patterns are relative to the project directory. Matched directories are included with
+/// all files they contain:
+///
+/// ```toml
+/// [package.metadata.libcnb]
+/// include = ["scripts/*.sh", "config/defaults.toml", "templates"]
+/// ```
+///
+/// Returns the paths of all matched files relative to the project directory, sorted and without
+/// duplicates. Projects without a Cargo package, for example meta-buildpacks at the root of a
+/// virtual workspace, have no additional files.
+///
+/// # Errors
+///
+/// Will return `Err` if the configuration is invalid, a pattern does not match any files or a
+/// matched file is at a path that is written by the packaging, such as the `bin` directory.
+pub fn find_buildpack_assets(project_path: impl AsRef<Path>) -> Result<Vec<PathBuf>, AssetError> {
+    let project_path = project_path.as_ref();
+    let manifest_path = project_path.join("Cargo.toml");
+
+    if !manifest_path.exists() {
+        return Ok(vec![]);
+    }
+
+    let cargo_metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .exec()
+        .map_err(AssetError::MetadataError)?;
+
+    let patterns = match cargo_metadata
+        .root_package()
+        .and_then(|package| package.metadata.pointer("/libcnb/include"))
+    {
+        None => return Ok(vec![]),
+        Some(serde_json::Value::Array(values)) => values
+            .iter()
+            .map(|value| value.as_str().map(String::from))
+            .collect::<Option<Vec<String>>>()
+            .ok_or(AssetError::InvalidIncludeMetadata)?,
+        Some(_) => return Err(AssetError::InvalidIncludeMetadata),
+    };
+
+    let mut asset_paths = vec![];
+
+    for pattern in patterns {
+        let is_outside_project = Path::new(&pattern).components().any(|component| {
+            matches!(
+                component,
+                Component::ParentDir | Component::RootDir | Component::Prefix(_)
+            )
+        });
+
+        if is_outside_project {
+            return Err(AssetError::PatternOutsideProject(pattern));
+        }
+
+        let absolute_pattern = format!(
+            "{}/{}",
+            glob::Pattern::escape(&project_path.to_string_lossy()),
+            pattern
+        );
+
+        let matched_paths = glob::glob(&absolute_pattern)
+            .map_err(|error| AssetError::InvalidPattern(pattern.clone(), error))?
+            .collect::<Result<Vec<PathBuf>, glob::GlobError>>()
+            .map_err(|error| AssetError::IoError(error.into()))?;
+
+        let asset_count = asset_paths.len();
+        for matched_path in matched_paths {
+            collect_files(&matched_path, &mut asset_paths)?;
+        }
+
+        if asset_paths.len() == asset_count {
+            return Err(AssetError::NoMatchingFiles(pattern));
+        }
+    }
+
+    let mut asset_paths = asset_paths
+        .into_iter()
+        .map(|asset_path| {
+            asset_path
+                .strip_prefix(project_path)
+                .map(Path::to_path_buf)
+                .unwrap_or(asset_path)
+        })
+        .collect::<Vec<_>>();
+
+    asset_paths.sort();
+    asset_paths.dedup();
+
+    if let Some(reserved_path) = asset_paths
+        .iter()
+        .find(|asset_path| is_reserved_path(asset_path))
+    {
+        return Err(AssetError::ReservedPath(reserved_path.clone()));
+    }
+
+    Ok(asset_paths)
+}
+
+/// Copies the given files, relative to the project directory, to the buildpack directory.
+///
+/// Files keep their path relative to the project directory and their permissions.
+///
+/// # Errors
+///
+/// Will return `Err` if a file could not be copied.
+pub fn copy_buildpack_assets(
+    project_path: impl AsRef<Path>,
+    destination_path: impl AsRef<Path>,
+    asset_paths: &[PathBuf],
+) -> io::Result<()> {
+    for asset_path in asset_paths {
+        let asset_destination_path = destination_path.as_ref().join(asset_path);
+
+        if let Some(parent) = asset_destination_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Permissions are copied as well, which keeps scripts executable.
+        fs::copy(
+            project_path.as_ref().join(asset_path),
+            asset_destination_path,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Whether the given path, relative to the buildpack directory, is written by the packaging
+/// itself. Besides `buildpack.toml`, this includes the `bin` directory with the buildpack binaries
+/// and additional binaries, as well as the `linux/<architecture>/bin` directories of buildpacks
+/// packaged for multiple platforms.
+fn is_reserved_path(path: &Path) -> bool {
+    let components: Vec<Component> = path.components().collect();
+
+    match components.as_slice() {
+        [Component::Normal(file_name)] => *file_name == "buildpack.toml",
+        [Component::Normal(directory_name), _, ..] => {
+            *directory_name == "bin"
+                || (*directory_name == "linux"
+                    && components.len() > 3
+                    && components[2] == Component::Normal("bin".as_ref()))
+        }
+        _ => false,
+    }
+}
+
+fn collect_files(path: &Path, file_paths: &mut Vec<PathBuf>) -> Result<(), AssetError> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path).map_err(AssetError::IoError)? {
+            collect_files(&entry.map_err(AssetError::IoError)?.path(), file_paths)?;
+        }
+    } else {
+        file_paths.push(path.to_path_buf());
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum AssetError {
+    MetadataError(cargo_metadata::Error),
+    InvalidIncludeMetadata,
+    InvalidPattern(String, glob::PatternError),
+    PatternOutsideProject(String),
+    NoMatchingFiles(String),
+    ReservedPath(PathBuf),
+    IoError(io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project_with_includes(includes: &str) -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project_path = temp_dir.path();
+
+        fs::write(
+            project_path.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"my-buildpack\"\nversion = \"0.1.0\"\n\n[package.metadata.libcnb]\ninclude = {}\n\n[workspace]\n",
+                includes
+            ),
+        )
+        .unwrap();
+        fs::create_dir_all(project_path.join("src")).unwrap();
+        fs::write(project_path.join("src").join("main.rs"), "fn main() {}").unwrap();
+        fs::create_dir_all(project_path.join("scripts").join("nested")).unwrap();
+        fs::write(project_path.join("scripts").join("a.sh"), "").unwrap();
+        fs::write(project_path.join("scripts").join("nested").join("b.sh"), "").unwrap();
+        fs::write(project_path.join("defaults.toml"), "").unwrap();
+
+        temp_dir
+    }
+
+    #[test]
+    fn finds_assets() {
+        let project = project_with_includes(r#"["scripts", "*.toml", "scripts/a.sh"]"#);
+
+        assert_eq!(
+            find_buildpack_assets(project.path()).unwrap(),
+            vec![
+                PathBuf::from("Cargo.toml"),
+                PathBuf::from("defaults.toml"),
+                PathBuf::from("scripts/a.sh"),
+                PathBuf::from("scripts/nested/b.sh"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reserved_paths() {
+        for path in [
+            "buildpack.toml",
+            "bin/build",
+            "bin/detect",
+            "bin/my-helper",
+            "bin/nested/file",
+            "linux/amd64/bin/build",
+            "linux/arm64/bin/my-helper",
+        ] {
+            assert!(is_reserved_path(Path::new(path)), "{}", path);
+        }
+
+        for path in [
+            "Cargo.toml",
+            "scripts/bin/run.sh",
+            "config/buildpack.toml",
+            "binaries/build",
+            "linux/amd64/scripts/run.sh",
+            "vendor/amd64/bin/tool",
+        ] {
+            assert!(!is_reserved_path(Path::new(path)), "{}", path);
+        }
+    }
+
+    #[test]
+    fn fails_on_reserved_path() {
+        let project = project_with_includes(r#"["bin"]"#);
+        fs::create_dir_all(project.path().join("bin")).unwrap();
+        fs::write(project.path().join("bin").join("helper"), "").unwrap();
+
+        assert!(matches!(
+            find_buildpack_assets(project.path()),
+            Err(AssetError::ReservedPath(path)) if path == Path::new("bin/helper")
+        ));
+    }
+
+    #[test]
+    fn fails_on_pattern_without_matches() {
+        let project = project_with_includes(r#"["config/*.toml"]"#);
+
+        assert!(matches!(
+            find_buildpack_assets(project.path()),
+            Err(AssetError::NoMatchingFiles(pattern)) if pattern == "config/*.toml"
+        ));
+    }
+
+    #[test]
+    fn fails_on_pattern_outside_project() {
+        let project = project_with_includes(r#"["../secrets"]"#);
+
+        assert!(matches!(
+            find_buildpack_assets(project.path()),
+            Err(AssetError::PatternOutsideProject(_))
+        ));
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn copies_assets_with_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let project = project_with_includes(r#"["scripts"]"#);
+        let script_path = project.path().join("scripts").join("a.sh");
+        fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let destination = tempfile::tempdir().unwrap();
+        copy_buildpack_assets(
+            project.path(),
+            destination.path(),
+            &find_buildpack_assets(project.path()).unwrap(),
+        )
+        .unwrap();
+
+        let copied_script_path = destination.path().join("scripts").join("a.sh");
+        assert_eq!(
+            fs::metadata(copied_script_path)
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777,
+            0o755
+        );
+        assert!(destination
+            .path()
+            .join("scripts")
+            .join("nested")
+            .join("b.sh")
+            .is_file());
+    }
+}
@@ -0,0 +1,740 @@
+use crate::{read_buildpack_data, BuildpackData, BuildpackDataError};
+use libcnb_data::buildpack::{BuildpackDescriptor, Stack, StackId};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tar::{EntryType, Header};
+
+/// Writes a buildpackage as an OCI image layout archive (`.cnb` file).
+///
+/// The archive contains one layer per buildpack with the buildpack directory at
+/// `/cnb/buildpacks/<id>/<version>`, as well as the `io.buildpacks.buildpackage.metadata` and
+/// `io.buildpacks.buildpack.layers` labels required by the [CNB distribution
+/// spec](https://github.com/buildpacks/spec/blob/main/distribution.md). The main buildpack can
+/// either be a single buildpack or a meta-buildpack. The directories of all buildpacks a
+/// meta-buildpack references must be passed as dependencies.
+///
+/// All files are written with timestamps set to `1980-01-01T00:00:01Z` and with root ownership.
+/// Packaging the same buildpack directories twice results in identical archives.
+///
+/// # Errors
+///
+/// Will return `Err` if the buildpack directories could not be read or the archive could not be
+/// written.
+pub fn write_buildpackage_archive(
+    destination_path: impl AsRef<Path>,
+    buildpack_path: impl AsRef<Path>,
+    dependency_paths: &[PathBuf],
+    platform: &OciPlatform,
+) -> Result<(), BuildpackageError> {
+    let main_buildpack = read_buildpackage_buildpack_data(buildpack_path.as_ref())?;
+
+    let dependencies = dependency_paths
+        .iter()
+        .map(|dependency_path| read_buildpackage_buildpack_data(dependency_path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut blobs = vec![];
+    let mut layer_descriptors = vec![];
+    let mut diff_ids = vec![];
+    let mut layers_label: BTreeMap<String, BTreeMap<String, Value>> = BTreeMap::new();
+
+    for (path, buildpack_data) in std::iter::once((buildpack_path.as_ref(), &main_buildpack)).chain(
+        dependency_paths
+            .iter()
+            .map(PathBuf::as_path)
+            .zip(dependencies.iter()),
+    ) {
+        let layer = buildpack_layer(path, &buildpack_data.buildpack_descriptor)?;
+        let buildpack = buildpack_data.buildpack_descriptor.buildpack();
+        let layer_digest = sha256_digest(&layer);
+
+        layer_descriptors.push(json!({
+            "mediaType": OCI_LAYER_MEDIA_TYPE,
+            "digest": layer_digest,
+            "size": layer.len(),
+        }));
+
+        diff_ids.push(Value::from(layer_digest.clone()));
+
+        layers_label
+            .entry(buildpack.id.to_string())
+            .or_default()
+            .insert(
+                buildpack.version.to_string(),
+                layers_label_entry(&buildpack_data.buildpack_descriptor, &layer_digest),
+            );
+
+        blobs.push(layer);
+    }
+
+    let config = serde_json::to_vec(&json!({
+        "architecture": platform.architecture,
+        "os": platform.os,
+        "created": REPRODUCIBLE_TIMESTAMP,
+        "config": {
+            "Labels": {
+                "io.buildpacks.buildpackage.metadata": serde_json::to_string(&buildpackage_metadata_label(&main_buildpack, &dependencies))?,
+                "io.buildpacks.buildpack.layers": serde_json::to_string(&layers_label)?,
+            }
+        },
+        "rootfs": {
+            "type": "layers",
+            "diff_ids": diff_ids,
+        },
+    }))?;
+
+    let manifest = serde_json::to_vec(&json!({
+        "schemaVersion": 2,
+        "mediaType": OCI_MANIFEST_MEDIA_TYPE,
+        "config": {
+            "mediaType": OCI_CONFIG_MEDIA_TYPE,
+            "digest": sha256_digest(&config),
+            "size": config.len(),
+        },
+        "layers": layer_descriptors,
+    }))?;
+
+    let index = serde_json::to_vec(&json!({
+        "schemaVersion": 2,
+        "manifests": [{
+            "mediaType": OCI_MANIFEST_MEDIA_TYPE,
+            "digest": sha256_digest(&manifest),
+            "size": manifest.len(),
+            "platform": {
+                "architecture": platform.architecture,
+                "os": platform.os,
+            }
+        }],
+    }))?;
+
+    blobs.push(config);
+    blobs.push(manifest);
+
+    let mut archive = tar::Builder::new(fs::File::create(destination_path.as_ref())?);
+    append_file(
+        &mut archive,
+        "oci-layout",
+        br#"{"imageLayoutVersion":"1.0.0"}"#,
+    )?;
+    append_file(&mut archive, "index.json", &index)?;
+    append_directory(&mut archive, "blobs")?;
+    append_directory(&mut archive, "blobs/sha256")?;
+
+    for blob in &blobs {
+        let digest = sha256_digest(blob);
+        let blob_path = format!("blobs/sha256/{}", digest.trim_start_matches("sha256:"));
+        append_file(&mut archive, blob_path, blob)?;
+    }
+
+    archive.into_inner()?;
+
+    Ok(())
+}
+
+/// The platform of a buildpackage image, using OCI/Go naming (`linux`, `amd64`, `arm64`, ...).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OciPlatform {
+    pub os: String,
+    pub architecture: String,
+}
+
+impl OciPlatform {
+    /// Determines the OCI platform for the given Rust target triple.
+    ///
+    /// Returns `None` if the target triple is not supported.
+    pub fn from_target_triple(target_triple: impl AsRef<str>) -> Option<Self> {
+        let mut parts = target_triple.as_ref().split('-');
+        let architecture = match parts.next()? {
+            "x86_64" => "amd64",
+            "aarch64" => "arm64",
+            _ => return None,
+        };
+
+        if parts.any(|part| part == "linux") {
+            Some(Self {
+                os: String::from("linux"),
+                architecture: String::from(architecture),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BuildpackageError {
+    IoError(io::Error),
+    CannotReadBuildpack(PathBuf, BuildpackDataError),
+    JsonError(serde_json::Error),
+}
+
+impl From<io::Error> for BuildpackageError {
+    fn from(error: io::Error) -> Self {
+        BuildpackageError::IoError(error)
+    }
+}
+
+impl From<serde_json::Error> for BuildpackageError {
+    fn from(error: serde_json::Error) -> Self {
+        BuildpackageError::JsonError(error)
+    }
+}
+
+fn read_buildpackage_buildpack_data(
+    buildpack_path: &Path,
+) -> Result<BuildpackData<Option<toml::Value>>, BuildpackageError> {
+    read_buildpack_data(buildpack_path).map_err(|error| {
+        BuildpackageError::CannotReadBuildpack(buildpack_path.to_path_buf(), error)
+    })
+}
+
+fn buildpackage_metadata_label(
+    main_buildpack: &BuildpackData<Option<toml::Value>>,
+    dependencies: &[BuildpackData<Option<toml::Value>>],
+) -> Value {
+    let buildpack = main_buildpack.buildpack_descriptor.buildpack();
+
+    let stacks = match &main_buildpack.buildpack_descriptor {
+        BuildpackDescriptor::Single(descriptor) => stacks_json(&descriptor.stacks),
+        BuildpackDescriptor::Meta(_) => common_stacks_json(dependencies),
+    };
+
+    let mut metadata = json!({
+        "id": buildpack.id.as_str(),
+        "version": buildpack.version.to_string(),
+        "stacks": stacks,
+    });
+
+    if let Some(homepage) = &buildpack.homepage {
+        metadata["homepage"] = Value::from(homepage.as_str());
+    }
+
+    metadata
+}
+
+fn layers_label_entry(
+    buildpack_descriptor: &BuildpackDescriptor<Option<toml::Value>>,
+    layer_digest: &str,
+) -> Value {
+    let mut entry = match buildpack_descriptor {
+        BuildpackDescriptor::Single(descriptor) => json!({
+            "api": descriptor.api.to_string(),
+            "stacks": stacks_json(&descriptor.stacks),
+        }),
+        BuildpackDescriptor::Meta(descriptor) => json!({
+            "api": descriptor.api.to_string(),
+            "order": descriptor.order.iter().map(|order| json!({
+                "group": order.group.iter().map(|group| {
+                    let mut group_json = json!({
+                        "id": group.id.as_str(),
+                        "version": group.version.to_string(),
+                    });
+
+                    if group.optional {
+                        group_json["optional"] = Value::from(true);
+                    }
+
+                    group_json
+                }).collect::<Vec<_>>()
+            })).collect::<Vec<_>>(),
+        }),
+    };
+
+    entry["layerDiffID"] = Value::from(layer_digest);
+
+    if let Some(homepage) = &buildpack_descriptor.buildpack().homepage {
+        entry["homepage"] = Value::from(homepage.as_str());
+    }
+
+    if let Some(name) = &buildpack_descriptor.buildpack().name {
+        entry["name"] = Value::from(name.as_str());
+    }
+
+    entry
+}
+
+fn stacks_json(stacks: &[Stack]) -> Vec<Value> {
+    stacks
+        .iter()
+        .map(|stack| match stack {
+            Stack::Any => json!({ "id": "*" }),
+            Stack::Specific { id, mixins } if mixins.is_empty() => json!({ "id": id.as_str() }),
+            Stack::Specific { id, mixins } => json!({ "id": id.as_str(), "mixins": mixins }),
+        })
+        .collect()
+}
+
+/// The stacks a meta-buildpack supports: those supported by all single buildpacks it references.
+/// The mixins of each common stack are the union of the mixins the referenced buildpacks require
+/// for it.
+fn common_stacks_json(dependencies: &[BuildpackData<Option<toml::Value>>]) -> Vec<Value> {
+    let dependency_stacks: Vec<&Vec<Stack>> = dependencies
+        .iter()
+        .filter_map(|dependency| match &dependency.buildpack_descriptor {
+            BuildpackDescriptor::Single(descriptor) => Some(&descriptor.stacks),
+            BuildpackDescriptor::Meta(_) => None,
+        })
+        .collect();
+
+    let specific_stack_ids: Vec<&StackId> = dependency_stacks
+        .iter()
+        .flat_map(|stacks| stacks.iter())
+        .filter_map(|stack| match stack {
+            Stack::Specific { id, .. } => Some(id),
+            Stack::Any => None,
+        })
+        .collect();
+
+    if specific_stack_ids.is_empty() {
+        return stacks_json(&[Stack::Any]);
+    }
+
+    let mut common_stacks: Vec<Stack> = vec![];
+    for id in specific_stack_ids {
+        let supported_by_all = dependency_stacks.iter().all(|stacks| {
+            stacks.iter().any(|stack| match stack {
+                Stack::Any => true,
+                Stack::Specific { id: other_id, .. } => other_id == id,
+            })
+        });
+
+        let already_added = common_stacks.iter().any(|common_stack| {
+            matches!(common_stack, Stack::Specific { id: common_id, .. } if common_id == id)
+        });
+
+        if supported_by_all && !already_added {
+            let mut common_mixins: Vec<String> = vec![];
+            for stacks in &dependency_stacks {
+                for stack in *stacks {
+                    if let Stack::Specific {
+                        id: other_id,
+                        mixins,
+                    } = stack
+                    {
+                        if other_id == id {
+                            for mixin in mixins {
+                                if !common_mixins.contains(mixin) {
+                                    common_mixins.push(mixin.clone());
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            common_stacks.push(Stack::Specific {
+                id: id.clone(),
+                mixins: common_mixins,
+            });
+        }
+    }
+
+    stacks_json(&common_stacks)
+}
+
+/// Creates the layer for a single buildpack or meta-buildpack. The `package.toml` of a
+/// meta-buildpack is only used for packaging and is therefore not added to the layer.
+fn buildpack_layer(
+    buildpack_path: &Path,
+    buildpack_descriptor: &BuildpackDescriptor<Option<toml::Value>>,
+) -> io::Result<Vec<u8>> {
+    let buildpack = buildpack_descriptor.buildpack();
+    let excluded_paths = match buildpack_descriptor {
+        BuildpackDescriptor::Single(_) => vec![],
+        BuildpackDescriptor::Meta(_) => vec![buildpack_path.join("package.toml")],
+    };
+
+    let layer_root = format!(
+        "cnb/buildpacks/{}/{}",
+        buildpack.id.replace('/', "_"),
+        buildpack.version
+    );
+
+    let mut layer = tar::Builder::new(Vec::new());
+    append_directory(&mut layer, "cnb")?;
+    append_directory(&mut layer, "cnb/buildpacks")?;
+    append_directory(
+        &mut layer,
+        format!("cnb/buildpacks/{}", buildpack.id.replace('/', "_")),
+    )?;
+    append_directory(&mut layer, &layer_root)?;
+    append_directory_contents(&mut layer, buildpack_path, &layer_root, &excluded_paths)?;
+
+    layer.into_inner()
+}
+
+fn append_directory_contents<W: io::Write>(
+    archive: &mut tar::Builder<W>,
+    directory_path: &Path,
+    archive_path: &str,
+    excluded_paths: &[PathBuf],
+) -> io::Result<()> {
+    let mut entries = fs::read_dir(directory_path)?.collect::<Result<Vec<_>, _>>()?;
+    // Sorting ensures a stable order of archive entries, regardless of the file system.
+    entries.sort_by_key(fs::DirEntry::file_name);
+
+    for entry in entries {
+        if excluded_paths.contains(&entry.path()) {
+            continue;
+        }
+
+        let entry_archive_path =
+            format!("{}/{}", archive_path, entry.file_name().to_string_lossy());
+        let metadata = fs::symlink_metadata(entry.path())?;
+
+        if metadata.file_type().is_symlink() {
+            let mut header = reproducible_header(EntryType::Symlink, 0o777);
+            archive.append_link(
+                &mut header,
+                &entry_archive_path,
+                fs::read_link(entry.path())?,
+            )?;
+        } else if metadata.is_dir() {
+            let mut header = reproducible_header(EntryType::Directory, file_mode(&metadata));
+            archive.append_data(&mut header, &entry_archive_path, io::empty())?;
+            append_directory_contents(archive, &entry.path(), &entry_archive_path, excluded_paths)?;
+        } else {
+            let mut header = reproducible_header(EntryType::Regular, file_mode(&metadata));
+            header.set_size(metadata.len());
+            archive.append_data(
+                &mut header,
+                &entry_archive_path,
+                fs::File::open(entry.path())?,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn append_directory<W: io::Write>(
+    archive: &mut tar::Builder<W>,
+    path: impl AsRef<Path>,
+) -> io::Result<()> {
+    let mut header = reproducible_header(EntryType::Directory, 0o755);
+    archive.append_data(&mut header, path, io::empty())
+}
+
+fn append_file<W: io::Write>(
+    archive: &mut tar::Builder<W>,
+    path: impl AsRef<Path>,
+    contents: &[u8],
+) -> io::Result<()> {
+    let mut header = reproducible_header(EntryType::Regular, 0o644);
+    header.set_size(contents.len() as u64);
+    archive.append_data(&mut header, path, contents)
+}
+
+fn reproducible_header(entry_type: EntryType, mode: u32) -> Header {
+    let mut header = Header::new_gnu();
+    header.set_entry_type(entry_type);
+    header.set_mode(mode);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mtime(REPRODUCIBLE_TIMESTAMP_SECONDS);
+    header.set_size(0);
+    header
+}
+
+#[cfg(target_family = "unix")]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(target_family = "windows")]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    if metadata.is_dir() {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+fn sha256_digest(data: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(data))
+}
+
+const OCI_LAYER_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar";
+const OCI_CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+
+// The same timestamp other CNB tools use for reproducible builds.
+const REPRODUCIBLE_TIMESTAMP: &str = "1980-01-01T00:00:01Z";
+const REPRODUCIBLE_TIMESTAMP_SECONDS: u64 = 315_532_801;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_buildpack;
+
+    fn archive_entries(path: &Path) -> Vec<(String, Vec<u8>)> {
+        let mut archive = tar::Archive::new(fs::File::open(path).unwrap());
+        archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let path = entry.path().unwrap().to_string_lossy().to_string();
+                let mut contents = vec![];
+                io::Read::read_to_end(&mut entry, &mut contents).unwrap();
+                (path, contents)
+            })
+            .collect()
+    }
+
+    fn archive_blob(entries: &[(String, Vec<u8>)], digest: &str) -> Vec<u8> {
+        let blob_path = format!("blobs/sha256/{}", digest.trim_start_matches("sha256:"));
+        entries
+            .iter()
+            .find(|(path, _)| *path == blob_path)
+            .unwrap()
+            .1
+            .clone()
+    }
+
+    fn buildpack_data(buildpack_descriptor: &str) -> BuildpackData<Option<toml::Value>> {
+        BuildpackData {
+            buildpack_descriptor_path: PathBuf::from("buildpack.toml"),
+            buildpack_descriptor: toml::from_str(buildpack_descriptor).unwrap(),
+        }
+    }
+
+    #[test]
+    fn oci_platform_from_target_triple() {
+        assert_eq!(
+            OciPlatform::from_target_triple("x86_64-unknown-linux-musl"),
+            Some(OciPlatform {
+                os: String::from("linux"),
+                architecture: String::from("amd64")
+            })
+        );
+        assert_eq!(
+            OciPlatform::from_target_triple("aarch64-unknown-linux-gnu")
+                .map(|platform| platform.architecture),
+            Some(String::from("arm64"))
+        );
+        assert_eq!(OciPlatform::from_target_triple("x86_64-apple-darwin"), None);
+    }
+
+    #[test]
+    fn writes_reproducible_buildpackage() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let buildpack_path = temp_dir.path().join("buildpack");
+        write_buildpack(&buildpack_path, "libcnb/test", "heroku-20");
+
+        let platform = OciPlatform::from_target_triple("x86_64-unknown-linux-musl").unwrap();
+        let first_archive_path = temp_dir.path().join("first.cnb");
+        let second_archive_path = temp_dir.path().join("second.cnb");
+
+        write_buildpackage_archive(&first_archive_path, &buildpack_path, &[], &platform).unwrap();
+        write_buildpackage_archive(&second_archive_path, &buildpack_path, &[], &platform).unwrap();
+
+        assert_eq!(
+            fs::read(&first_archive_path).unwrap(),
+            fs::read(&second_archive_path).unwrap()
+        );
+
+        let entries = archive_entries(&first_archive_path);
+        let index: Value = serde_json::from_slice(
+            &entries
+                .iter()
+                .find(|(path, _)| path == "index.json")
+                .unwrap()
+                .1,
+        )
+        .unwrap();
+
+        let manifest_digest = index["manifests"][0]["digest"].as_str().unwrap();
+        let blob = |digest: &str| archive_blob(&entries, digest);
+
+        let manifest: Value = serde_json::from_slice(&blob(manifest_digest)).unwrap();
+        let config: Value =
+            serde_json::from_slice(&blob(manifest["config"]["digest"].as_str().unwrap())).unwrap();
+
+        assert_eq!(config["os"], "linux");
+        assert_eq!(config["architecture"], "amd64");
+
+        let metadata: Value = serde_json::from_str(
+            config["config"]["Labels"]["io.buildpacks.buildpackage.metadata"]
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(metadata["id"], "libcnb/test");
+        assert_eq!(metadata["stacks"][0]["id"], "heroku-20");
+
+        let layers: Value = serde_json::from_str(
+            config["config"]["Labels"]["io.buildpacks.buildpack.layers"]
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            layers["libcnb/test"]["0.1.0"]["layerDiffID"],
+            manifest["layers"][0]["digest"]
+        );
+
+        let layer = blob(manifest["layers"][0]["digest"].as_str().unwrap());
+        let layer_paths: Vec<String> = tar::Archive::new(layer.as_slice())
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                assert_eq!(
+                    entry.header().mtime().unwrap(),
+                    REPRODUCIBLE_TIMESTAMP_SECONDS
+                );
+                entry.path().unwrap().to_string_lossy().to_string()
+            })
+            .collect();
+
+        assert!(layer_paths.contains(&String::from(
+            "cnb/buildpacks/libcnb_test/0.1.0/buildpack.toml"
+        )));
+        assert!(layer_paths.contains(&String::from("cnb/buildpacks/libcnb_test/0.1.0/bin/build")));
+    }
+
+    #[test]
+    fn common_stacks_intersect_ids_and_union_mixins() {
+        let dependencies = [
+            buildpack_data(
+                r#"
+api = "0.6"
+
+[buildpack]
+id = "heroku/jvm"
+version = "0.1.0"
+
+[[stacks]]
+id = "heroku-20"
+mixins = ["curl"]
+
+[[stacks]]
+id = "heroku-18"
+"#,
+            ),
+            buildpack_data(
+                r#"
+api = "0.6"
+
+[buildpack]
+id = "heroku/maven"
+version = "0.1.0"
+
+[[stacks]]
+id = "heroku-20"
+mixins = ["build:git", "curl"]
+"#,
+            ),
+            buildpack_data(
+                r#"
+api = "0.6"
+
+[buildpack]
+id = "heroku/procfile"
+version = "0.1.0"
+
+[[stacks]]
+id = "*"
+"#,
+            ),
+        ];
+
+        assert_eq!(
+            common_stacks_json(&dependencies),
+            vec![json!({ "id": "heroku-20", "mixins": ["curl", "build:git"] })]
+        );
+    }
+
+    #[test]
+    fn common_stacks_of_any_stack_dependencies() {
+        let dependencies = [buildpack_data(
+            r#"
+api = "0.6"
+
+[buildpack]
+id = "heroku/procfile"
+version = "0.1.0"
+
+[[stacks]]
+id = "*"
+"#,
+        )];
+
+        assert_eq!(
+            common_stacks_json(&dependencies),
+            vec![json!({ "id": "*" })]
+        );
+    }
+
+    #[test]
+    fn excludes_package_toml_from_meta_buildpack_layer() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dependency_path = temp_dir.path().join("dependency");
+        write_buildpack(&dependency_path, "libcnb/test", "heroku-20");
+
+        let meta_buildpack_path = temp_dir.path().join("meta");
+        fs::create_dir_all(&meta_buildpack_path).unwrap();
+        fs::write(
+            meta_buildpack_path.join("buildpack.toml"),
+            r#"
+api = "0.6"
+
+[buildpack]
+id = "libcnb/meta"
+version = "0.1.0"
+
+[[order]]
+
+[[order.group]]
+id = "libcnb/test"
+version = "0.1.0"
+"#,
+        )
+        .unwrap();
+        fs::write(meta_buildpack_path.join("package.toml"), "").unwrap();
+
+        let archive_path = temp_dir.path().join("meta.cnb");
+        write_buildpackage_archive(
+            &archive_path,
+            &meta_buildpack_path,
+            &[dependency_path],
+            &OciPlatform::from_target_triple("x86_64-unknown-linux-musl").unwrap(),
+        )
+        .unwrap();
+
+        let entries = archive_entries(&archive_path);
+        let index: Value = serde_json::from_slice(
+            &entries
+                .iter()
+                .find(|(path, _)| path == "index.json")
+                .unwrap()
+                .1,
+        )
+        .unwrap();
+        let manifest: Value = serde_json::from_slice(&archive_blob(
+            &entries,
+            index["manifests"][0]["digest"].as_str().unwrap(),
+        ))
+        .unwrap();
+
+        let meta_layer = archive_blob(&entries, manifest["layers"][0]["digest"].as_str().unwrap());
+        let meta_layer_paths: Vec<String> = tar::Archive::new(meta_layer.as_slice())
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+
+        assert!(meta_layer_paths.contains(&String::from(
+            "cnb/buildpacks/libcnb_meta/0.1.0/buildpack.toml"
+        )));
+        assert!(!meta_layer_paths.contains(&String::from(
+            "cnb/buildpacks/libcnb_meta/0.1.0/package.toml"
+        )));
+    }
+}
@@ -0,0 +1,587 @@
+use cargo_metadata::{Metadata, Package};
+use libcnb_data::buildpack::{
+    BuildpackApi, BuildpackId, BuildpackVersion, MetaBuildpackDescriptor,
+    SingleBuildpackDescriptor, StackId,
+};
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use toml::Spanned;
+
+/// A problem found by [`check_buildpack_project`] or [`check_buildpack_descriptor`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The position in `buildpack.toml` the problem refers to, if it is known.
+    pub position: Option<Position>,
+}
+
+impl Diagnostic {
+    fn error(message: impl Into<String>, position: Option<Position>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            position,
+        }
+    }
+
+    fn warning(message: impl Into<String>, position: Option<Position>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A position in a file. Lines and columns start at one.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Checks the `buildpack.toml` of the buildpack project at the given path.
+///
+/// In addition to the checks of [`check_buildpack_descriptor`], the descriptor is checked against
+/// the Cargo package in the same directory, if there is one: the buildpack version should match
+/// the package version and the Buildpack API version must be supported by the libcnb version the
+/// package depends on, as recorded in `Cargo.lock`.
+///
+/// # Errors
+///
+/// Will return `Err` if the `buildpack.toml` could not be read.
+pub fn check_buildpack_project(
+    project_path: impl AsRef<Path>,
+    cargo_metadata: &Metadata,
+) -> io::Result<Vec<Diagnostic>> {
+    let contents = fs::read_to_string(project_path.as_ref().join("buildpack.toml"))?;
+    let mut diagnostics = check_buildpack_descriptor(&contents);
+
+    if diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+    {
+        return Ok(diagnostics);
+    }
+
+    if let (Ok(fields), Some(package)) = (
+        toml::from_str::<DescriptorFields>(&contents),
+        find_package(cargo_metadata, project_path.as_ref()),
+    ) {
+        diagnostics.extend(check_cargo_package(
+            &contents,
+            &fields,
+            package,
+            cargo_metadata,
+        ));
+    }
+
+    Ok(diagnostics)
+}
+
+/// Checks the contents of a `buildpack.toml`.
+///
+/// The descriptor is validated as a [`SingleBuildpackDescriptor`], or as a
+/// [`MetaBuildpackDescriptor`] if it contains an `order`. Valid descriptors are additionally
+/// checked for deprecated stacks and license types that are not SPDX license identifiers.
+#[must_use]
+pub fn check_buildpack_descriptor(contents: &str) -> Vec<Diagnostic> {
+    let value = match toml::from_str::<toml::Value>(contents) {
+        Ok(value) => value,
+        Err(error) => return vec![toml_error_diagnostic(&error)],
+    };
+
+    // Parsing the fields separately allows reporting invalid values at their exact position.
+    // Values of an unexpected type are left to the descriptor parsing below.
+    let fields = toml::from_str::<DescriptorFields>(contents).ok();
+
+    let mut diagnostics = fields
+        .as_ref()
+        .map(|fields| check_field_values(contents, fields))
+        .unwrap_or_default();
+
+    if !diagnostics.is_empty() {
+        return diagnostics;
+    }
+
+    let parse_result = if value.get("order").is_some() {
+        toml::from_str::<MetaBuildpackDescriptor<Option<toml::Value>>>(contents).map(|_| ())
+    } else {
+        toml::from_str::<SingleBuildpackDescriptor<Option<toml::Value>>>(contents).map(|_| ())
+    };
+
+    if let Err(error) = parse_result {
+        return vec![toml_error_diagnostic(&error)];
+    }
+
+    if let Some(fields) = fields {
+        for stack_id in fields.stacks.iter().filter_map(|stack| stack.id.as_ref()) {
+            if let Some((_, replacement)) = DEPRECATED_STACK_IDS
+                .iter()
+                .find(|(deprecated_id, _)| deprecated_id == stack_id.get_ref())
+            {
+                diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "Stack `{}` is deprecated, consider targeting `{}` instead",
+                        stack_id.get_ref(),
+                        replacement
+                    ),
+                    Some(span_position(contents, stack_id)),
+                ));
+            }
+        }
+
+        for license_type in fields
+            .buildpack
+            .licenses
+            .iter()
+            .filter_map(|license| license.r#type.as_ref())
+        {
+            if let Some(message) = check_license_type(license_type.get_ref()) {
+                diagnostics.push(Diagnostic::warning(
+                    message,
+                    Some(span_position(contents, license_type)),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Stacks that reached their end of life, and the stack that replaces them.
+const DEPRECATED_STACK_IDS: &[(&str, &str)] =
+    &[("heroku-16", "heroku-20"), ("heroku-18", "heroku-20")];
+
+fn check_field_values(contents: &str, fields: &DescriptorFields) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    let mut check = |field: &Spanned<String>, is_valid: bool, message: String| {
+        if !is_valid {
+            diagnostics.push(Diagnostic::error(
+                message,
+                Some(span_position(contents, field)),
+            ));
+        }
+    };
+
+    if let Some(api) = &fields.api {
+        check(
+            api,
+            BuildpackApi::try_from(api.get_ref().as_str()).is_ok(),
+            format!(
+                "Invalid Buildpack API version `{}`, expected a version such as `0.6`",
+                api.get_ref()
+            ),
+        );
+    }
+
+    if let Some(id) = &fields.buildpack.id {
+        check(
+            id,
+            id.get_ref().parse::<BuildpackId>().is_ok(),
+            format!("Invalid buildpack ID `{}`, IDs may only contain letters, numbers, `.`, `/` and `-` and must not be `app` or `config`", id.get_ref()),
+        );
+    }
+
+    if let Some(version) = &fields.buildpack.version {
+        check(
+            version,
+            BuildpackVersion::try_from(version.get_ref().as_str()).is_ok(),
+            format!(
+                "Invalid buildpack version `{}`, expected a version of the form `<X>.<Y>.<Z>`",
+                version.get_ref()
+            ),
+        );
+    }
+
+    for stack_id in fields.stacks.iter().filter_map(|stack| stack.id.as_ref()) {
+        check(
+            stack_id,
+            stack_id.get_ref() == "*" || stack_id.get_ref().parse::<StackId>().is_ok(),
+            format!("Invalid stack ID `{}`", stack_id.get_ref()),
+        );
+    }
+
+    for group in fields.order.iter().flat_map(|order| order.group.iter()) {
+        if let Some(id) = &group.id {
+            check(
+                id,
+                id.get_ref().parse::<BuildpackId>().is_ok(),
+                format!("Invalid buildpack ID `{}` in order group", id.get_ref()),
+            );
+        }
+
+        if let Some(version) = &group.version {
+            check(
+                version,
+                BuildpackVersion::try_from(version.get_ref().as_str()).is_ok(),
+                format!(
+                    "Invalid buildpack version `{}` in order group",
+                    version.get_ref()
+                ),
+            );
+        }
+    }
+
+    diagnostics
+}
+
+fn check_license_type(license_type: &str) -> Option<String> {
+    match spdx::license_id(license_type) {
+        Some(license_id) if license_id.is_deprecated() => Some(format!(
+            "License type `{}` is a deprecated SPDX license identifier",
+            license_type
+        )),
+        Some(_) => None,
+        None => Some(match spdx::imprecise_license_id(license_type) {
+            Some((license_id, _)) => format!(
+                "License type `{}` is not a valid SPDX license identifier, did you mean `{}`?",
+                license_type, license_id.name
+            ),
+            None => format!(
+                "License type `{}` is not a valid SPDX license identifier",
+                license_type
+            ),
+        }),
+    }
+}
+
+fn check_cargo_package(
+    contents: &str,
+    fields: &DescriptorFields,
+    package: &Package,
+    cargo_metadata: &Metadata,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    if let Some(version) = &fields.buildpack.version {
+        if version.get_ref() != &package.version.to_string() {
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "Buildpack version `{}` differs from the version of Cargo package {} (`{}`)",
+                    version.get_ref(),
+                    package.name,
+                    package.version
+                ),
+                Some(span_position(contents, version)),
+            ));
+        }
+    }
+
+    // Meta-buildpacks are not implemented with libcnb and have no Buildpack API requirements.
+    if !fields.order.is_empty() {
+        return diagnostics;
+    }
+
+    let api = fields.api.as_ref().and_then(|api| {
+        BuildpackApi::try_from(api.get_ref().as_str())
+            .ok()
+            .map(|buildpack_api| (api, buildpack_api))
+    });
+
+    if let (Some((api, buildpack_api)), Some(libcnb_package)) =
+        (api, find_libcnb_dependency(cargo_metadata, package))
+    {
+        if let Some(supported_apis) = supported_buildpack_apis(&libcnb_package.metadata) {
+            if !supported_apis.contains(&buildpack_api) {
+                diagnostics.push(Diagnostic::error(
+                    format!(
+                        "Buildpack API version {} is not supported by libcnb {}, which supports versions {} to {}",
+                        buildpack_api,
+                        libcnb_package.version,
+                        supported_apis.start(),
+                        supported_apis.end()
+                    ),
+                    Some(span_position(contents, api)),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// The Buildpack API versions supported by a libcnb package, as declared in the
+/// `[package.metadata.libcnb]` table of its manifest. Returns `None` for versions of libcnb that
+/// do not declare them, in which case the Buildpack API version is not checked.
+fn supported_buildpack_apis(
+    libcnb_package_metadata: &serde_json::Value,
+) -> Option<RangeInclusive<BuildpackApi>> {
+    let buildpack_api = |key: &str| {
+        libcnb_package_metadata
+            .get("libcnb")?
+            .get(key)?
+            .as_str()
+            .and_then(|api| BuildpackApi::try_from(api).ok())
+    };
+
+    Some(buildpack_api("min-buildpack-api")?..=buildpack_api("max-buildpack-api")?)
+}
+
+fn find_package<'a>(cargo_metadata: &'a Metadata, project_path: &Path) -> Option<&'a Package> {
+    cargo_metadata.packages.iter().find(|package| {
+        package
+            .manifest_path
+            .parent()
+            .map_or(false, |package_path| package_path == project_path)
+    })
+}
+
+fn find_libcnb_dependency<'a>(
+    cargo_metadata: &'a Metadata,
+    package: &Package,
+) -> Option<&'a Package> {
+    cargo_metadata
+        .resolve
+        .as_ref()?
+        .nodes
+        .iter()
+        .find(|node| node.id == package.id)?
+        .dependencies
+        .iter()
+        .filter_map(|dependency_id| {
+            cargo_metadata
+                .packages
+                .iter()
+                .find(|package| &package.id == dependency_id)
+        })
+        .find(|dependency| dependency.name == "libcnb")
+}
+
+fn toml_error_diagnostic(error: &toml::de::Error) -> Diagnostic {
+    let message = error.to_string();
+
+    // The position is reported separately, the message should not contain it a second time.
+    let message = message
+        .rsplit_once(" at line ")
+        .map_or(message.as_str(), |(message, _)| message);
+
+    Diagnostic::error(
+        message,
+        error.line_col().map(|(line, column)| Position {
+            line: line + 1,
+            column: column + 1,
+        }),
+    )
+}
+
+fn span_position<T>(contents: &str, spanned: &Spanned<T>) -> Position {
+    let preceding = &contents[..spanned.start()];
+
+    Position {
+        line: preceding.matches('\n').count() + 1,
+        column: preceding
+            .rsplit('\n')
+            .next()
+            .map_or(0, |line| line.chars().count())
+            + 1,
+    }
+}
+
+/// The fields of `buildpack.toml` that are checked individually, with their positions.
+#[derive(Deserialize, Default)]
+struct DescriptorFields {
+    api: Option<Spanned<String>>,
+    #[serde(default)]
+    buildpack: BuildpackFields,
+    #[serde(default)]
+    stacks: Vec<StackFields>,
+    #[serde(default)]
+    order: Vec<OrderFields>,
+}
+
+#[derive(Deserialize, Default)]
+struct BuildpackFields {
+    id: Option<Spanned<String>>,
+    version: Option<Spanned<String>>,
+    #[serde(default)]
+    licenses: Vec<LicenseFields>,
+}
+
+#[derive(Deserialize)]
+struct LicenseFields {
+    r#type: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+struct StackFields {
+    id: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+struct OrderFields {
+    #[serde(default)]
+    group: Vec<GroupFields>,
+}
+
+#[derive(Deserialize)]
+struct GroupFields {
+    id: Option<Spanned<String>>,
+    version: Option<Spanned<String>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_descriptor() {
+        let diagnostics = check_buildpack_descriptor(
+            r#"
+api = "0.6"
+
+[buildpack]
+id = "heroku/jvm"
+version = "0.1.0"
+
+[[buildpack.licenses]]
+type = "BSD-3-Clause"
+
+[[stacks]]
+id = "heroku-20"
+"#,
+        );
+
+        assert_eq!(diagnostics, vec![]);
+    }
+
+    #[test]
+    fn invalid_value_position() {
+        let diagnostics = check_buildpack_descriptor(
+            r#"api = "0.6"
+
+[buildpack]
+id = "heroku/jvm"
+version = "0.1"
+"#,
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(
+            diagnostics[0].position,
+            Some(Position {
+                line: 5,
+                column: 11
+            })
+        );
+    }
+
+    #[test]
+    fn missing_field() {
+        let diagnostics = check_buildpack_descriptor(
+            r#"api = "0.6"
+
+[buildpack]
+id = "heroku/jvm"
+version = "0.1.0"
+"#,
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "missing field `stacks`");
+        assert!(diagnostics[0].position.is_some());
+    }
+
+    #[test]
+    fn meta_buildpack_descriptor() {
+        let diagnostics = check_buildpack_descriptor(
+            r#"
+api = "0.6"
+
+[buildpack]
+id = "heroku/java"
+version = "0.1.0"
+
+[[order]]
+
+[[order.group]]
+id = "heroku/jvm"
+version = "0.1"
+"#,
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "Invalid buildpack version `0.1` in order group"
+        );
+    }
+
+    #[test]
+    fn warnings() {
+        let diagnostics = check_buildpack_descriptor(
+            r#"api = "0.6"
+
+[buildpack]
+id = "heroku/jvm"
+version = "0.1.0"
+
+[[buildpack.licenses]]
+type = "BSD-3"
+
+[[stacks]]
+id = "heroku-18"
+"#,
+        );
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity == Severity::Warning));
+        assert_eq!(
+            diagnostics[0].position,
+            Some(Position {
+                line: 11,
+                column: 6
+            })
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "Stack `heroku-18` is deprecated, consider targeting `heroku-20` instead"
+        );
+        assert_eq!(
+            diagnostics[1].position,
+            Some(Position { line: 8, column: 8 })
+        );
+    }
+
+    #[test]
+    fn supported_buildpack_apis_from_package_metadata() {
+        assert_eq!(
+            supported_buildpack_apis(&serde_json::json!({
+                "libcnb": {
+                    "min-buildpack-api": "0.6",
+                    "max-buildpack-api": "0.8"
+                }
+            })),
+            Some(BuildpackApi { major: 0, minor: 6 }..=BuildpackApi { major: 0, minor: 8 })
+        );
+        assert_eq!(supported_buildpack_apis(&serde_json::Value::Null), None);
+        assert_eq!(
+            supported_buildpack_apis(&serde_json::json!({
+                "libcnb": { "min-buildpack-api": "0.6" }
+            })),
+            None
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use clap::{App, AppSettings, Arg, SubCommand};
 
+#[allow(clippy::too_many_lines)]
 pub(crate) fn setup_cli_parsing<'a, 'b>() -> clap::App<'a, 'b> {
     App::new(env!("CARGO_PKG_NAME"))
         .bin_name("cargo")
@@ -19,16 +20,124 @@ pub(crate) fn setup_cli_parsing<'a, 'b>() -> clap::App<'a, 'b> {
                                 .long("release")
                                 .help("Build in release mode, with optimizations"),
                         )
+                        .arg(
+                            Arg::with_name("profile")
+                                .long("profile")
+                                .takes_value(true)
+                                .conflicts_with("release")
+                                .help("Build with the given profile"),
+                        )
+                        .arg(
+                            Arg::with_name("features")
+                                .long("features")
+                                .takes_value(true)
+                                .multiple(true)
+                                .number_of_values(1)
+                                .help("Space or comma separated list of features to activate"),
+                        )
+                        .arg(
+                            Arg::with_name("no-default-features")
+                                .long("no-default-features")
+                                .help("Do not activate the `default` feature"),
+                        )
+                        .arg(
+                            Arg::with_name("locked")
+                                .long("locked")
+                                .help("Require Cargo.lock is up to date"),
+                        )
                         .arg(
                             Arg::with_name("target")
                                 .long("target")
+                                .multiple(true)
+                                .number_of_values(1)
                                 .default_value("x86_64-unknown-linux-musl")
-                                .help("Build for the target triple"),
+                                .help("Build for the target triple, can be given multiple times to package binaries for multiple platforms"),
+                        )
+                        .arg(
+                            Arg::with_name("bin")
+                                .long("bin")
+                                .takes_value(true)
+                                .help("Name of the binary target that implements the buildpack, if there are multiple"),
+                        )
+                        .arg(
+                            Arg::with_name("format")
+                                .long("format")
+                                .possible_values(&["dir", "oci"])
+                                .default_value("dir")
+                                .help("Output format: a buildpack directory or, additionally, a buildpackage OCI image layout archive (.cnb)"),
                         )
                         .arg(
                             Arg::with_name("no-cross-compile-assistance")
                                 .long("no-cross-compile-assistance")
                                 .help("Disable cross-compile assistance"),
+                        )
+                        .arg(
+                            Arg::with_name("dry-run")
+                                .long("dry-run")
+                                .help("List the files that would be packaged, without building or writing anything"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("build-app")
+                        .about("Packages the buildpack and runs it against an application, without containers")
+                        .arg(
+                            Arg::with_name("app")
+                                .required(true)
+                                .help("Path to the application directory, the buildpack runs against a copy of it"),
+                        )
+                        .arg(
+                            Arg::with_name("layers-dir")
+                                .long("layers-dir")
+                                .takes_value(true)
+                                .help("Layers directory to use, layers of a previous run are restored like the lifecycle would restore them"),
+                        )
+                        .arg(
+                            Arg::with_name("stack-id")
+                                .long("stack-id")
+                                .takes_value(true)
+                                .help("Stack ID to build with, defaults to the first stack in buildpack.toml"),
+                        )
+                        .arg(
+                            Arg::with_name("release")
+                                .long("release")
+                                .help("Build in release mode, with optimizations"),
+                        )
+                        .arg(
+                            Arg::with_name("target")
+                                .long("target")
+                                .takes_value(true)
+                                .help("Build for the target triple, defaults to the host target triple"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("check")
+                        .about("Checks buildpack.toml and its consistency with the Cargo package"),
+                )
+                .subcommand(
+                    SubCommand::with_name("new")
+                        .about("Creates a new libcnb.rs buildpack project")
+                        .arg(
+                            Arg::with_name("path")
+                                .required(true)
+                                .help("Path of the new project, its last component is used as the package name"),
+                        )
+                        .arg(
+                            Arg::with_name("id")
+                                .long("id")
+                                .takes_value(true)
+                                .required(true)
+                                .help("ID of the new buildpack, for example: my-org/my-buildpack"),
+                        )
+                        .arg(
+                            Arg::with_name("buildpack-version")
+                                .long("buildpack-version")
+                                .default_value("0.1.0")
+                                .help("Version of the new buildpack"),
+                        )
+                        .arg(
+                            Arg::with_name("meta")
+                                .long("meta")
+                                .help("Create a Cargo workspace with a meta-buildpack instead of a single buildpack"),
                         ),
                 ),
         )
@@ -1,65 +1,151 @@
 use std::ffi::OsString;
-use which::which;
+use std::path::PathBuf;
 
 /// Provides assistance for cross-compiling from the user's host platform to the desired target platform.
 ///
 /// This function will not install required toolchains, linkers or compilers automatically. It will
 /// look for the required tools and returns a human-readable help text if they cannot be found or
 /// any other issue has been detected.
+///
+/// Assistance is available for the `x86_64` and `aarch64` Linux targets, for both musl and glibc.
+/// From macOS, only the musl targets are supported.
 pub fn cross_compile_assistance(target_triple: impl AsRef<str>) -> CrossCompileAssistance {
-    // Background: https://omarkhawaja.com/cross-compiling-rust-from-macos-to-linux/
-    if target_triple.as_ref() == X86_64_UNKNOWN_LINUX_MUSL && cfg!(target_os = "macos") {
-        let gcc_binary_name = "x86_64-linux-musl-gcc";
-
-        match which(gcc_binary_name) {
-            Ok(gcc_binary_path) => {
-                CrossCompileAssistance::Configuration {
-                    cargo_env: vec![
-                        (
-                            // Required until Cargo can auto-detect the musl-cross gcc/linker itself,
-                            // since otherwise it checks for a binary named 'musl-gcc'
-                            // not 'x86_64-linux-musl-gcc':
-                            // https://github.com/FiloSottile/homebrew-musl-cross/issues/16
-                            // https://github.com/rust-lang/cargo/issues/4133
-                            OsString::from("CARGO_TARGET_X86_64_UNKNOWN_LINUX_MUSL_LINKER"),
-                            OsString::from(&gcc_binary_path),
-                        ),
-                        (
-                            // Required so that any crates that call out to gcc are also cross-compiled:
-                            // https://github.com/alexcrichton/cc-rs/issues/82
-                            OsString::from("CC_x86_64_unknown_linux_musl"),
-                            OsString::from(&gcc_binary_path),
-                        ),
-                    ],
-                }
-            }
-            Err(_) => CrossCompileAssistance::HelpText(String::from(
-                r#"For cross-compilation from macOS to x86_64-unknown-linux-musl, a C compiler and
+    cross_compile_assistance_for_host(
+        target_triple.as_ref(),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        &|binary_name| which::which(binary_name).ok(),
+    )
+}
+
+/// Implements [`cross_compile_assistance`] for the given host platform. Binaries are looked up
+/// with the given function, which allows testing all combinations of host and target platform.
+fn cross_compile_assistance_for_host(
+    target_triple: &str,
+    host_os: &str,
+    host_arch: &str,
+    find_binary: &dyn Fn(&str) -> Option<PathBuf>,
+) -> CrossCompileAssistance {
+    let target_arch = match target_triple {
+        X86_64_UNKNOWN_LINUX_MUSL | X86_64_UNKNOWN_LINUX_GNU => "x86_64",
+        AARCH64_UNKNOWN_LINUX_MUSL | AARCH64_UNKNOWN_LINUX_GNU => "aarch64",
+        _ => return CrossCompileAssistance::NoAssistance,
+    };
+
+    let is_musl = target_triple.ends_with("-musl");
+    let is_native_arch = target_arch == host_arch;
+
+    if host_os == "macos" && is_musl {
+        // Background: https://omarkhawaja.com/cross-compiling-rust-from-macos-to-linux/
+        linker_assistance(
+            target_triple,
+            find_binary,
+            &format!("{}-linux-musl-gcc", target_arch),
+            format!(
+                r"For cross-compilation from macOS to {target_triple}, a C compiler and
 linker for the target platform must be installed on your computer.
 
-The easiest way to install 'x86_64-linux-musl-gcc' is to follow the instructions in the linked
+The easiest way to install '{target_arch}-linux-musl-gcc' is to follow the instructions in the linked
 GitHub repository:
 
-https://github.com/FiloSottile/homebrew-musl-cross"#,
-            )),
-        }
-    } else if target_triple.as_ref() == X86_64_UNKNOWN_LINUX_MUSL && cfg!(target_os = "linux") {
-        match which("musl-gcc") {
-            Ok(_) => CrossCompileAssistance::Configuration { cargo_env: vec![] },
-            Err(_) => CrossCompileAssistance::HelpText(String::from(
-                r#"For cross-compilation from Linux to x86_64-unknown-linux-musl, a C compiler and
+https://github.com/FiloSottile/homebrew-musl-cross",
+                target_triple = target_triple,
+                target_arch = target_arch
+            ),
+        )
+    } else if host_os == "linux" && is_musl && is_native_arch {
+        match find_binary("musl-gcc") {
+            Some(_) => CrossCompileAssistance::Configuration { cargo_env: vec![] },
+            None => CrossCompileAssistance::HelpText(format!(
+                r"For cross-compilation from Linux to {}, a C compiler and
 linker for the target platform must be installed on your computer.
 
 The easiest way to install 'musl-gcc' is to install the 'musl-tools' package:
 - https://packages.ubuntu.com/focal/musl-tools
-- https://packages.debian.org/bullseye/musl-tools"#,
+- https://packages.debian.org/bullseye/musl-tools",
+                target_triple
             )),
         }
+    } else if host_os == "linux" && is_musl {
+        linker_assistance(
+            target_triple,
+            find_binary,
+            &format!("{}-linux-musl-gcc", target_arch),
+            format!(
+                r"For cross-compilation from Linux to {target_triple}, a C compiler and
+linker for the target platform must be installed on your computer.
+
+The 'musl-tools' package only supports the architecture of the host. Instead, install a musl
+cross-compiler that provides '{target_arch}-linux-musl-gcc', for example from:
+
+https://musl.cc",
+                target_triple = target_triple,
+                target_arch = target_arch
+            ),
+        )
+    } else if host_os == "linux" && is_native_arch {
+        // The host toolchain can be used as-is.
+        CrossCompileAssistance::Configuration { cargo_env: vec![] }
+    } else if host_os == "linux" {
+        linker_assistance(
+            target_triple,
+            find_binary,
+            &format!("{}-linux-gnu-gcc", target_arch),
+            format!(
+                r"For cross-compilation from Linux to {target_triple}, a C compiler and
+linker for the target platform must be installed on your computer.
+
+The easiest way to install '{target_arch}-linux-gnu-gcc' is to install the 'gcc-{package_arch}-linux-gnu' package:
+- https://packages.ubuntu.com/focal/gcc-{package_arch}-linux-gnu
+- https://packages.debian.org/bullseye/gcc-{package_arch}-linux-gnu",
+                target_triple = target_triple,
+                target_arch = target_arch,
+                package_arch = target_arch.replace('_', "-")
+            ),
+        )
     } else {
         CrossCompileAssistance::NoAssistance
     }
 }
 
+/// Configures Cargo to use the given GCC binary as the linker and C compiler for the target, or
+/// returns the given help text if the binary cannot be found.
+fn linker_assistance(
+    target_triple: &str,
+    find_binary: &dyn Fn(&str) -> Option<PathBuf>,
+    gcc_binary_name: &str,
+    help_text: String,
+) -> CrossCompileAssistance {
+    match find_binary(gcc_binary_name) {
+        Some(gcc_binary_path) => {
+            CrossCompileAssistance::Configuration {
+                cargo_env: vec![
+                    (
+                        // Required until Cargo can auto-detect the cross gcc/linker itself,
+                        // since otherwise it uses the linker of the host platform or checks
+                        // for a binary named 'musl-gcc':
+                        // https://github.com/FiloSottile/homebrew-musl-cross/issues/16
+                        // https://github.com/rust-lang/cargo/issues/4133
+                        OsString::from(format!(
+                            "CARGO_TARGET_{}_LINKER",
+                            target_triple.to_uppercase().replace('-', "_")
+                        )),
+                        OsString::from(&gcc_binary_path),
+                    ),
+                    (
+                        // Required so that any crates that call out to gcc are also cross-compiled:
+                        // https://github.com/alexcrichton/cc-rs/issues/82
+                        OsString::from(format!("CC_{}", target_triple.replace('-', "_"))),
+                        OsString::from(&gcc_binary_path),
+                    ),
+                ],
+            }
+        }
+        None => CrossCompileAssistance::HelpText(help_text),
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
 pub enum CrossCompileAssistance {
     /// No specific assistance available for the current host and target platform combination.
     NoAssistance,
@@ -73,3 +159,157 @@ pub enum CrossCompileAssistance {
 }
 
 const X86_64_UNKNOWN_LINUX_MUSL: &str = "x86_64-unknown-linux-musl";
+const X86_64_UNKNOWN_LINUX_GNU: &str = "x86_64-unknown-linux-gnu";
+const AARCH64_UNKNOWN_LINUX_MUSL: &str = "aarch64-unknown-linux-musl";
+const AARCH64_UNKNOWN_LINUX_GNU: &str = "aarch64-unknown-linux-gnu";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET_TRIPLES: [&str; 4] = [
+        X86_64_UNKNOWN_LINUX_MUSL,
+        X86_64_UNKNOWN_LINUX_GNU,
+        AARCH64_UNKNOWN_LINUX_MUSL,
+        AARCH64_UNKNOWN_LINUX_GNU,
+    ];
+
+    /// The expected assistance for a host and target platform combination. `NoConfiguration` and
+    /// `Linker` name the binary that must be installed, or an empty name if none is required.
+    /// Without that binary, a help text that mentions it is expected instead.
+    enum Expected {
+        NoAssistance,
+        NoConfiguration(&'static str),
+        Linker(&'static str),
+    }
+
+    fn assert_assistance(host_os: &str, host_arch: &str, target_triple: &str, expected: &Expected) {
+        let find_installed = |binary_name: &str| Some(PathBuf::from("/usr/bin").join(binary_name));
+        let find_none = |_: &str| None;
+
+        let installed =
+            cross_compile_assistance_for_host(target_triple, host_os, host_arch, &find_installed);
+        let missing =
+            cross_compile_assistance_for_host(target_triple, host_os, host_arch, &find_none);
+
+        let context = format!("{} on {} {}", target_triple, host_os, host_arch);
+
+        match expected {
+            Expected::NoAssistance => {
+                assert_eq!(
+                    installed,
+                    CrossCompileAssistance::NoAssistance,
+                    "{}",
+                    context
+                );
+                assert_eq!(missing, CrossCompileAssistance::NoAssistance, "{}", context);
+            }
+            Expected::NoConfiguration(binary_name) => {
+                assert_eq!(
+                    installed,
+                    CrossCompileAssistance::Configuration { cargo_env: vec![] },
+                    "{}",
+                    context
+                );
+
+                if binary_name.is_empty() {
+                    assert_eq!(missing, installed, "{}", context);
+                } else {
+                    assert!(
+                        matches!(&missing, CrossCompileAssistance::HelpText(help_text) if help_text.contains(binary_name)),
+                        "{}",
+                        context
+                    );
+                }
+            }
+            Expected::Linker(gcc_binary_name) => {
+                let gcc_binary_path = OsString::from(format!("/usr/bin/{}", gcc_binary_name));
+                assert_eq!(
+                    installed,
+                    CrossCompileAssistance::Configuration {
+                        cargo_env: vec![
+                            (
+                                OsString::from(format!(
+                                    "CARGO_TARGET_{}_LINKER",
+                                    target_triple.to_uppercase().replace('-', "_")
+                                )),
+                                gcc_binary_path.clone()
+                            ),
+                            (
+                                OsString::from(format!("CC_{}", target_triple.replace('-', "_"))),
+                                gcc_binary_path
+                            ),
+                        ]
+                    },
+                    "{}",
+                    context
+                );
+                assert!(
+                    matches!(&missing, CrossCompileAssistance::HelpText(help_text) if help_text.contains(gcc_binary_name)),
+                    "{}",
+                    context
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn assistance_from_linux_x86_64() {
+        let expected = [
+            Expected::NoConfiguration("musl-gcc"),
+            Expected::NoConfiguration(""),
+            Expected::Linker("aarch64-linux-musl-gcc"),
+            Expected::Linker("aarch64-linux-gnu-gcc"),
+        ];
+
+        for (target_triple, expected) in TARGET_TRIPLES.iter().zip(&expected) {
+            assert_assistance("linux", "x86_64", target_triple, expected);
+        }
+    }
+
+    #[test]
+    fn assistance_from_linux_aarch64() {
+        let expected = [
+            Expected::Linker("x86_64-linux-musl-gcc"),
+            Expected::Linker("x86_64-linux-gnu-gcc"),
+            Expected::NoConfiguration("musl-gcc"),
+            Expected::NoConfiguration(""),
+        ];
+
+        for (target_triple, expected) in TARGET_TRIPLES.iter().zip(&expected) {
+            assert_assistance("linux", "aarch64", target_triple, expected);
+        }
+    }
+
+    #[test]
+    fn assistance_from_macos() {
+        let expected = [
+            Expected::Linker("x86_64-linux-musl-gcc"),
+            Expected::NoAssistance,
+            Expected::Linker("aarch64-linux-musl-gcc"),
+            Expected::NoAssistance,
+        ];
+
+        for host_arch in ["x86_64", "aarch64"] {
+            for (target_triple, expected) in TARGET_TRIPLES.iter().zip(&expected) {
+                assert_assistance("macos", host_arch, target_triple, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn no_assistance_for_other_targets() {
+        assert_assistance(
+            "linux",
+            "x86_64",
+            "x86_64-apple-darwin",
+            &Expected::NoAssistance,
+        );
+        assert_assistance(
+            "windows",
+            "x86_64",
+            X86_64_UNKNOWN_LINUX_MUSL,
+            &Expected::NoAssistance,
+        );
+    }
+}
@@ -3,17 +3,29 @@
 // This lint is too noisy and enforces a style that reduces readability in many cases.
 #![allow(clippy::module_name_repetitions)]
 
+pub mod assets;
+pub mod buildpackage;
+pub mod check;
 pub mod cross_compile;
+pub mod local_lifecycle;
+pub mod meta_buildpack;
+pub mod scaffold;
 
-use cargo_metadata::MetadataCommand;
-use libcnb_data::buildpack::SingleBuildpackDescriptor;
+#[cfg(test)]
+mod test_support;
+
+use crate::buildpackage::OciPlatform;
+use cargo_metadata::{Message, MetadataCommand};
+use libcnb_data::buildpack::BuildpackDescriptor;
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs;
 use std::io;
+use std::io::BufReader;
 use std::path::{Path, PathBuf};
-use std::process::{Command, ExitStatus};
+use std::process::{Command, ExitStatus, Stdio};
 
-/// Builds a buildpack binary using Cargo.
+/// Builds the buildpack binaries using Cargo.
 ///
 /// It is designed to handle cross-compilation without requiring custom configuration in the Cargo
 /// manifest of the user's buildpack. The triple for the target platform is a mandatory
@@ -25,24 +37,37 @@ use std::process::{Command, ExitStatus};
 /// user.
 ///
 /// In many cases, cross-compilation requires external tools such as compilers and linkers to be
-/// installed on the user's machine. When a tool is missing, a `BuildError::CrossCompileError` is
-/// returned which provides additional information. Use the `cross_compile::cross_compile_help`
+/// installed on the user's machine. When a tool is missing, Cargo fails and a
+/// `BuildError::UnexpectedExitStatus` is returned. Use the `cross_compile::cross_compile_help`
 /// function to obtain human-readable instructions on how to setup the required tools.
 ///
-/// This function currently only supports projects with a single binary target. If the project
-/// does not contain exactly one target, the appropriate `BuildError` is returned.
+/// All binary targets of the project are built. One of them is the main buildpack binary that
+/// implements detect and build, all others are additional binaries that are shipped with the
+/// buildpack. The main buildpack binary is, in order of precedence:
+///
+/// 1. The binary target with the given `main_binary_name`
+/// 2. The binary target configured in `package.metadata.libcnb.main-binary` of the Cargo manifest
+/// 3. The only binary target of the project
+///
+/// If no main buildpack binary can be determined, the appropriate `BuildError` is returned.
 ///
-/// This function will write Cargo's output to stdout and stderr.
+/// The paths of the built binaries are taken from the JSON messages Cargo emits for each compiler
+/// artifact. This makes sure the correct paths are used, regardless of profile, target directory
+/// overrides or binary names.
+///
+/// This function will write Cargo's diagnostics to stderr.
 ///
 /// # Errors
 ///
 /// Will return `Err` if the build did not finish successfully.
-pub fn build_buildpack_binary<I, K, V>(
+pub fn build_buildpack_binaries<I, K, V>(
     project_path: impl AsRef<Path>,
-    cargo_profile: CargoProfile,
+    cargo_profile: &CargoProfile,
     target_triple: impl AsRef<str>,
+    main_binary_name: Option<&str>,
+    cargo_build_options: &CargoBuildOptions,
     cargo_env: I,
-) -> Result<PathBuf, BuildError>
+) -> Result<BuildpackBinaries, BuildError>
 where
     I: IntoIterator<Item = (K, V)>,
     K: AsRef<OsStr>,
@@ -57,61 +82,234 @@ where
         .root_package()
         .ok_or(BuildError::CouldNotFindRootPackage)?;
 
-    let target = match buildpack_cargo_package.targets.as_slice() {
-        [] => Err(BuildError::NoTargetsFound),
-        [single_target] => Ok(single_target),
-        _ => Err(BuildError::MultipleTargetsFound),
-    }?;
+    let main_binary_name = main_binary_target_name(buildpack_cargo_package, main_binary_name)?;
+
+    let mut cargo_args = vec![
+        String::from("build"),
+        // Diagnostics are still rendered to stderr, only the stdout output is JSON.
+        String::from("--message-format=json-render-diagnostics"),
+        String::from("--target"),
+        String::from(target_triple.as_ref()),
+    ];
 
-    let mut cargo_args = vec!["build", "--target", target_triple.as_ref()];
     match cargo_profile {
         CargoProfile::Dev => {}
-        CargoProfile::Release => cargo_args.push("--release"),
+        CargoProfile::Release => cargo_args.push(String::from("--release")),
+        CargoProfile::Custom(profile_name) => {
+            cargo_args.push(String::from("--profile"));
+            cargo_args.push(profile_name.clone());
+        }
+    }
+
+    if !cargo_build_options.features.is_empty() {
+        cargo_args.push(String::from("--features"));
+        cargo_args.push(cargo_build_options.features.join(","));
+    }
+
+    if cargo_build_options.no_default_features {
+        cargo_args.push(String::from("--no-default-features"));
     }
 
-    let exit_status = Command::new("cargo")
+    if cargo_build_options.locked {
+        cargo_args.push(String::from("--locked"));
+    }
+
+    let mut cargo_process = Command::new("cargo")
         .args(cargo_args)
         .envs(cargo_env)
         .current_dir(&project_path)
+        .stdout(Stdio::piped())
         .spawn()
-        .and_then(|mut child| child.wait())
         .map_err(BuildError::IoError)?;
 
+    let mut binary_paths = HashMap::new();
+    if let Some(cargo_stdout) = cargo_process.stdout.take() {
+        for message in Message::parse_stream(BufReader::new(cargo_stdout)) {
+            let message = match message {
+                Ok(message) => message,
+                Err(error) => {
+                    // Cargo must not keep running, or linger as a zombie process, when its output
+                    // cannot be read. Errors are ignored since the read error is more relevant.
+                    let _ = cargo_process.kill();
+                    let _ = cargo_process.wait();
+                    return Err(BuildError::IoError(error));
+                }
+            };
+
+            match message {
+                Message::CompilerArtifact(artifact)
+                    if artifact.package_id == buildpack_cargo_package.id
+                        && is_binary_target(&artifact.target) =>
+                {
+                    if let Some(executable) = artifact.executable {
+                        binary_paths.insert(artifact.target.name, executable.into_std_path_buf());
+                    }
+                }
+                // Output that is not JSON, for example from build scripts, is passed through as-is.
+                Message::TextLine(line) => println!("{}", line),
+                _ => {}
+            }
+        }
+    }
+
+    let exit_status = cargo_process.wait().map_err(BuildError::IoError)?;
+
     if exit_status.success() {
-        let binary_path = cargo_metadata
-            .target_directory
-            .join(target_triple.as_ref())
-            .join(match cargo_profile {
-                CargoProfile::Dev => "debug",
-                CargoProfile::Release => "release",
-            })
-            .join(&target.name)
-            .into_std_path_buf();
-
-        Ok(binary_path)
+        let buildpack_target_binary_path = binary_paths
+            .remove(&main_binary_name)
+            .ok_or(BuildError::BinaryNotBuilt(main_binary_name))?;
+
+        Ok(BuildpackBinaries {
+            buildpack_target_binary_path,
+            additional_target_binary_paths: binary_paths,
+        })
     } else {
         Err(BuildError::UnexpectedExitStatus(exit_status))
     }
 }
 
+/// Determines the binary targets [`build_buildpack_binaries`] would build, without building them.
+///
+/// Returns the name of the main buildpack binary and the names of all additional binaries.
+///
+/// # Errors
+///
+/// Will return `Err` if the Cargo metadata could not be read or no main buildpack binary can be
+/// determined.
+pub fn buildpack_binary_target_names(
+    project_path: impl AsRef<Path>,
+    main_binary_name: Option<&str>,
+) -> Result<(String, Vec<String>), BuildError> {
+    let cargo_metadata = MetadataCommand::new()
+        .manifest_path(project_path.as_ref().join("Cargo.toml"))
+        .exec()
+        .map_err(BuildError::MetadataError)?;
+
+    let buildpack_cargo_package = cargo_metadata
+        .root_package()
+        .ok_or(BuildError::CouldNotFindRootPackage)?;
+
+    let main_binary_name = main_binary_target_name(buildpack_cargo_package, main_binary_name)?;
+
+    let additional_binary_names = buildpack_cargo_package
+        .targets
+        .iter()
+        .filter(|target| is_binary_target(target) && target.name != main_binary_name)
+        .map(|target| target.name.clone())
+        .collect();
+
+    Ok((main_binary_name, additional_binary_names))
+}
+
+fn main_binary_target_name(
+    buildpack_cargo_package: &cargo_metadata::Package,
+    main_binary_name: Option<&str>,
+) -> Result<String, BuildError> {
+    let binary_target_names: Vec<&str> = buildpack_cargo_package
+        .targets
+        .iter()
+        .filter(|target| is_binary_target(target))
+        .map(|target| target.name.as_str())
+        .collect();
+
+    let main_binary_name = match main_binary_name {
+        Some(main_binary_name) => Some(String::from(main_binary_name)),
+        None => configured_main_binary_name(buildpack_cargo_package)?,
+    };
+
+    match (main_binary_name, binary_target_names.as_slice()) {
+        (Some(main_binary_name), _) => {
+            if binary_target_names.contains(&main_binary_name.as_str()) {
+                Ok(main_binary_name)
+            } else {
+                Err(BuildError::BinaryTargetNotFound(main_binary_name))
+            }
+        }
+        (None, []) => Err(BuildError::NoBinaryTargetsFound),
+        (None, [single_binary_target_name]) => Ok(String::from(*single_binary_target_name)),
+        (None, _) => Err(BuildError::MultipleBinaryTargetsFound),
+    }
+}
+
+fn is_binary_target(target: &cargo_metadata::Target) -> bool {
+    target.kind.iter().any(|kind| kind == "bin")
+}
+
+/// Reads `package.metadata.libcnb.main-binary` from the given Cargo package.
+fn configured_main_binary_name(
+    cargo_package: &cargo_metadata::Package,
+) -> Result<Option<String>, BuildError> {
+    match cargo_package.metadata.pointer("/libcnb/main-binary") {
+        None => Ok(None),
+        Some(serde_json::Value::String(main_binary_name)) => Ok(Some(main_binary_name.clone())),
+        Some(_) => Err(BuildError::InvalidMainBinaryMetadata),
+    }
+}
+
+/// The binaries of a buildpack, as built by [`build_buildpack_binaries`].
+#[derive(Debug)]
+pub struct BuildpackBinaries {
+    /// The main buildpack binary that implements detect and build.
+    pub buildpack_target_binary_path: PathBuf,
+    /// Additional binaries, keyed by their binary target name.
+    pub additional_target_binary_paths: HashMap<String, PathBuf>,
+}
+
 #[derive(Debug)]
 pub enum BuildError {
     IoError(std::io::Error),
     UnexpectedExitStatus(ExitStatus),
-    NoTargetsFound,
-    MultipleTargetsFound,
+    NoBinaryTargetsFound,
+    MultipleBinaryTargetsFound,
+    BinaryTargetNotFound(String),
+    BinaryNotBuilt(String),
+    InvalidMainBinaryMetadata,
     MetadataError(cargo_metadata::Error),
     CouldNotFindRootPackage,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum CargoProfile {
     Dev,
     Release,
+    /// A custom profile, defined in the Cargo manifest.
+    Custom(String),
+}
+
+impl CargoProfile {
+    /// The name of the directory that contains build output for this profile.
+    ///
+    /// Uses the same directory names as Cargo does for its build artifacts. Like `dev` and
+    /// `release`, the built-in `test` and `bench` profiles write to `debug` and `release`.
+    #[must_use]
+    pub fn directory_name(&self) -> &str {
+        match self {
+            CargoProfile::Dev => "debug",
+            CargoProfile::Release => "release",
+            CargoProfile::Custom(profile_name) => match profile_name.as_str() {
+                "test" => "debug",
+                "bench" => "release",
+                profile_name => profile_name,
+            },
+        }
+    }
+}
+
+/// Options that are passed through to `cargo build`.
+#[derive(Clone, Debug, Default)]
+pub struct CargoBuildOptions {
+    /// Features to activate (`--features`).
+    pub features: Vec<String>,
+    /// Do not activate the default features (`--no-default-features`).
+    pub no_default_features: bool,
+    /// Require `Cargo.lock` to be up to date (`--locked`).
+    pub locked: bool,
 }
 
 /// Reads buildpack data from the given project path.
 ///
+/// The buildpack descriptor can either describe a single buildpack or a meta-buildpack.
+///
 /// # Errors
 ///
 /// Will return `Err` if the buildpack data could not be read successfully.
@@ -139,7 +337,7 @@ pub enum BuildpackDataError {
 
 pub struct BuildpackData<BM> {
     pub buildpack_descriptor_path: PathBuf,
-    pub buildpack_descriptor: SingleBuildpackDescriptor<BM>,
+    pub buildpack_descriptor: BuildpackDescriptor<BM>,
 }
 
 /// Creates a buildpack directory and copies all buildpack assets to it.
@@ -148,6 +346,9 @@ pub struct BuildpackData<BM> {
 /// the buildpack binary is only copied once and symlinks are used to refer to it when the CNB
 /// spec requires different file(name)s.
 ///
+/// Additional binaries are copied to the `bin` directory, next to the main buildpack binary. Their
+/// names must not be `build` or `detect`.
+///
 /// This function will not validate if the buildpack descriptor at the given path is valid and will
 /// use it as-is.
 ///
@@ -157,7 +358,7 @@ pub struct BuildpackData<BM> {
 pub fn assemble_buildpack_directory(
     destination_path: impl AsRef<Path>,
     buildpack_descriptor_path: impl AsRef<Path>,
-    buildpack_binary_path: impl AsRef<Path>,
+    buildpack_binaries: &BuildpackBinaries,
 ) -> std::io::Result<()> {
     if destination_path.as_ref().exists() {
         Err(io::Error::new(
@@ -165,6 +366,8 @@ pub fn assemble_buildpack_directory(
             "Destination path already exists!",
         ))
     } else {
+        check_additional_binary_names(buildpack_binaries)?;
+
         fs::create_dir_all(destination_path.as_ref())?;
 
         fs::copy(
@@ -172,16 +375,94 @@ pub fn assemble_buildpack_directory(
             destination_path.as_ref().join("buildpack.toml"),
         )?;
 
-        let bin_path = destination_path.as_ref().join("bin");
-        fs::create_dir_all(&bin_path)?;
+        write_bin_directory(destination_path.as_ref().join("bin"), buildpack_binaries)
+    }
+}
 
-        fs::copy(buildpack_binary_path.as_ref(), bin_path.join("build"))?;
-        create_file_symlink("build", bin_path.join("detect"))?;
+/// Creates a buildpack directory that contains binaries for multiple platforms.
+///
+/// The binaries of each platform are written to an `<os>/<architecture>/bin` directory, for
+/// example `linux/arm64/bin`, using the same layout as [`assemble_buildpack_directory`]. The
+/// buildpack descriptor is copied to the root of the buildpack directory.
+///
+/// # Errors
+///
+/// Will return `Err` if the buildpack directory already exists or the directory could not be
+/// assembled.
+pub fn assemble_multi_platform_buildpack_directory(
+    destination_path: impl AsRef<Path>,
+    buildpack_descriptor_path: impl AsRef<Path>,
+    platform_binaries: &[(OciPlatform, BuildpackBinaries)],
+) -> std::io::Result<()> {
+    if destination_path.as_ref().exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "Destination path already exists!",
+        ));
+    }
 
-        Ok(())
+    for (_, buildpack_binaries) in platform_binaries {
+        check_additional_binary_names(buildpack_binaries)?;
+    }
+
+    fs::create_dir_all(destination_path.as_ref())?;
+
+    fs::copy(
+        buildpack_descriptor_path.as_ref(),
+        destination_path.as_ref().join("buildpack.toml"),
+    )?;
+
+    for (platform, buildpack_binaries) in platform_binaries {
+        write_bin_directory(
+            destination_path
+                .as_ref()
+                .join(&platform.os)
+                .join(&platform.architecture)
+                .join("bin"),
+            buildpack_binaries,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn check_additional_binary_names(buildpack_binaries: &BuildpackBinaries) -> io::Result<()> {
+    match ["build", "detect"].iter().find(|reserved_name| {
+        buildpack_binaries
+            .additional_target_binary_paths
+            .contains_key(**reserved_name)
+    }) {
+        Some(reserved_name) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Additional binary name '{}' is reserved for the buildpack binary!",
+                reserved_name
+            ),
+        )),
+        None => Ok(()),
     }
 }
 
+fn write_bin_directory(
+    bin_path: impl AsRef<Path>,
+    buildpack_binaries: &BuildpackBinaries,
+) -> io::Result<()> {
+    let bin_path = bin_path.as_ref();
+    fs::create_dir_all(bin_path)?;
+
+    fs::copy(
+        &buildpack_binaries.buildpack_target_binary_path,
+        bin_path.join("build"),
+    )?;
+    create_file_symlink("build", bin_path.join("detect"))?;
+
+    for (binary_name, binary_path) in &buildpack_binaries.additional_target_binary_paths {
+        fs::copy(binary_path, bin_path.join(binary_name))?;
+    }
+
+    Ok(())
+}
+
 #[cfg(target_family = "unix")]
 fn create_file_symlink<P: AsRef<Path>, Q: AsRef<Path>>(
     original: P,
@@ -203,7 +484,68 @@ fn create_file_symlink<P: AsRef<Path>, Q: AsRef<Path>>(
 /// This function ensures the resulting name is valid and does not contain problematic characters
 /// such as `/`.
 pub fn default_buildpack_directory_name<BM>(
-    buildpack_descriptor: &SingleBuildpackDescriptor<BM>,
+    buildpack_descriptor: &BuildpackDescriptor<BM>,
 ) -> String {
-    buildpack_descriptor.buildpack.id.replace("/", "_")
+    buildpack_descriptor.buildpack().id.replace('/', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cargo_profile_directory_names() {
+        for (cargo_profile, directory_name) in [
+            (CargoProfile::Dev, "debug"),
+            (CargoProfile::Release, "release"),
+            (CargoProfile::Custom(String::from("test")), "debug"),
+            (CargoProfile::Custom(String::from("bench")), "release"),
+            (
+                CargoProfile::Custom(String::from("production")),
+                "production",
+            ),
+        ] {
+            assert_eq!(cargo_profile.directory_name(), directory_name);
+        }
+    }
+
+    #[test]
+    fn assembles_multi_platform_buildpack_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let descriptor_path = temp_dir.path().join("buildpack.toml");
+        let binary_path = temp_dir.path().join("my-buildpack");
+        fs::write(&descriptor_path, "").unwrap();
+        fs::write(&binary_path, "").unwrap();
+
+        let buildpack_binaries = || BuildpackBinaries {
+            buildpack_target_binary_path: binary_path.clone(),
+            additional_target_binary_paths: HashMap::new(),
+        };
+
+        let platform = |architecture: &str| OciPlatform {
+            os: String::from("linux"),
+            architecture: String::from(architecture),
+        };
+
+        let destination_path = temp_dir.path().join("buildpack");
+        assemble_multi_platform_buildpack_directory(
+            &destination_path,
+            &descriptor_path,
+            &[
+                (platform("amd64"), buildpack_binaries()),
+                (platform("arm64"), buildpack_binaries()),
+            ],
+        )
+        .unwrap();
+
+        assert!(destination_path.join("buildpack.toml").is_file());
+        for architecture in ["amd64", "arm64"] {
+            let bin_path = destination_path
+                .join("linux")
+                .join(architecture)
+                .join("bin");
+            assert!(bin_path.join("build").is_file());
+            assert!(bin_path.join("detect").is_file());
+        }
+    }
 }
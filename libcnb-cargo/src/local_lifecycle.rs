@@ -0,0 +1,454 @@
+use libcnb_data::build_plan::BuildPlan;
+use libcnb_data::build_plan_resolution::{resolve, DetectedBuildpack, Detection, ResolveError};
+use libcnb_data::buildpack::{BuildpackId, StackId};
+use libcnb_data::buildpack_plan::BuildpackPlan;
+use libcnb_data::launch::{Launch, Process};
+use libcnb_data::layer_content_metadata::{LayerContentMetadata, LayerTypes};
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+/// Runs the phases of a packaged single buildpack on the local machine, similar to how the CNB
+/// lifecycle would run them inside a build container.
+///
+/// The phase inputs are passed both as arguments and as `CNB_*` environment variables, so
+/// buildpacks targeting any supported Buildpack API version can read them. All directories must
+/// exist before the phases are run.
+#[derive(Debug, Clone)]
+pub struct LocalLifecycle {
+    /// The directory of the packaged buildpack, containing `bin/detect` and `bin/build`.
+    pub buildpack_dir: PathBuf,
+    /// The application directory, also used as the working directory of the phases.
+    pub app_dir: PathBuf,
+    /// The layers directory of the buildpack.
+    pub layers_dir: PathBuf,
+    pub platform_dir: PathBuf,
+    /// The directory the build plan and buildpack plan files are written to.
+    pub plan_dir: PathBuf,
+    pub stack_id: StackId,
+}
+
+impl LocalLifecycle {
+    /// Runs `bin/detect` of the buildpack.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `bin/detect` could not be run, exited with an unexpected exit code or
+    /// wrote an invalid build plan.
+    pub fn detect(&self) -> Result<Detection, LocalLifecycleError> {
+        let build_plan_path = self.plan_dir.join("plan.toml");
+        fs::write(&build_plan_path, "")?;
+
+        let exit_status = self
+            .command("detect")
+            .arg(&self.platform_dir)
+            .arg(&build_plan_path)
+            .env("CNB_PLATFORM_DIR", &self.platform_dir)
+            .env("CNB_BUILD_PLAN_PATH", &build_plan_path)
+            .status()?;
+
+        match exit_status.code() {
+            Some(0) => {
+                let build_plan =
+                    toml::from_str::<BuildPlan>(&fs::read_to_string(&build_plan_path)?)
+                        .map_err(LocalLifecycleError::CannotReadBuildPlan)?;
+
+                Ok(Detection::Pass(build_plan))
+            }
+            Some(100) => Ok(Detection::Fail),
+            _ => Err(LocalLifecycleError::UnexpectedExitStatus(exit_status)),
+        }
+    }
+
+    /// Runs `bin/build` of the buildpack with the given buildpack plan.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the buildpack plan could not be written or if `bin/build` could not
+    /// be run or did not exit successfully.
+    pub fn build(&self, buildpack_plan: &BuildpackPlan) -> Result<(), LocalLifecycleError> {
+        let buildpack_plan_path = self.plan_dir.join("buildpack-plan.toml");
+        fs::write(
+            &buildpack_plan_path,
+            toml::to_string(buildpack_plan)
+                .map_err(LocalLifecycleError::CannotWriteBuildpackPlan)?,
+        )?;
+
+        let exit_status = self
+            .command("build")
+            .arg(&self.layers_dir)
+            .arg(&self.platform_dir)
+            .arg(&buildpack_plan_path)
+            .env("CNB_LAYERS_DIR", &self.layers_dir)
+            .env("CNB_PLATFORM_DIR", &self.platform_dir)
+            .env("CNB_BP_PLAN_PATH", &buildpack_plan_path)
+            .status()?;
+
+        if exit_status.success() {
+            Ok(())
+        } else {
+            Err(LocalLifecycleError::UnexpectedExitStatus(exit_status))
+        }
+    }
+
+    fn command(&self, phase: &str) -> Command {
+        let mut command = Command::new(self.buildpack_dir.join("bin").join(phase));
+        command
+            .current_dir(&self.app_dir)
+            .env("CNB_STACK_ID", self.stack_id.as_str())
+            .env("CNB_BUILDPACK_DIR", &self.buildpack_dir);
+
+        command
+    }
+}
+
+/// Resolves the buildpack plan of a single buildpack from the outcome of its detect phase.
+///
+/// # Errors
+///
+/// Will return `Err` if the buildpack failed detection or its build plan does not resolve, for
+/// example because it requires an entry it does not provide itself.
+pub fn resolve_buildpack_plan(
+    buildpack_id: &BuildpackId,
+    detection: Detection,
+) -> Result<BuildpackPlan, ResolveError> {
+    let resolution = resolve(&[DetectedBuildpack {
+        id: buildpack_id.clone(),
+        optional: false,
+        detection,
+    }])?;
+
+    Ok(resolution.buildpack_plan(buildpack_id).unwrap_or_default())
+}
+
+/// Prepares a layers directory of a previous build for another build, like the lifecycle does when
+/// it restores layers from a previous image and its cache.
+///
+/// Cached layers are kept as-is. Launch layers that are not cached only keep their content
+/// metadata, since the lifecycle restores them from the previous image without their contents.
+/// All other layers and the `launch.toml` and `build.toml` files are removed.
+///
+/// # Errors
+///
+/// Will return `Err` if the layers directory could not be read or modified.
+pub fn restore_layers(layers_dir: impl AsRef<Path>) -> Result<(), LocalLifecycleError> {
+    let layers_dir = layers_dir.as_ref();
+
+    for file_name in ["launch.toml", "build.toml"] {
+        let path = layers_dir.join(file_name);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+
+    let layer_types = read_layer_types(layers_dir)?;
+
+    for entry in fs::read_dir(layers_dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            let types = path
+                .file_name()
+                .and_then(|file_name| {
+                    layer_types
+                        .iter()
+                        .find(|(name, _)| file_name == name.as_str())
+                })
+                .map(|(_, types)| types);
+
+            if !types.map_or(false, |types| types.cache) {
+                fs::remove_dir_all(&path)?;
+            }
+        }
+    }
+
+    for (name, types) in layer_types {
+        if !types.cache && !types.launch {
+            fs::remove_file(layers_dir.join(format!("{}.toml", name)))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A summary of what a build wrote to the layers directory.
+#[derive(Debug)]
+pub struct BuildSummary {
+    /// The layers, sorted by name.
+    pub layers: Vec<LayerSummary>,
+    /// The processes declared in `launch.toml`.
+    pub processes: Vec<Process>,
+}
+
+#[derive(Debug)]
+pub struct LayerSummary {
+    pub name: String,
+    pub types: LayerTypes,
+    /// The environment variable modifications of the layer, sorted by scope and name.
+    pub env: Vec<EnvModification>,
+}
+
+/// A file in one of the `env` directories of a layer.
+#[derive(Debug, Eq, PartialEq)]
+pub struct EnvModification {
+    /// The name of the `env` directory, for example `env`, `env.build` or `env.launch/web`.
+    pub scope: String,
+    pub name: String,
+    /// The modification behavior, for example `override`, `prepend` or `delim`.
+    pub behavior: String,
+    pub value: String,
+}
+
+/// Reads a summary of the layers and processes the build wrote to the given layers directory.
+///
+/// # Errors
+///
+/// Will return `Err` if the layers directory or any of its files could not be read.
+pub fn read_build_summary(
+    layers_dir: impl AsRef<Path>,
+) -> Result<BuildSummary, LocalLifecycleError> {
+    let layers_dir = layers_dir.as_ref();
+
+    let layers = read_layer_types(layers_dir)?
+        .into_iter()
+        .map(|(name, types)| {
+            let layer_path = layers_dir.join(&name);
+            let mut env = vec![];
+
+            for scope in ["env", "env.build", "env.launch"] {
+                let scope_dir = layer_path.join(scope);
+                env.append(&mut read_env_modifications(&scope_dir, scope)?);
+
+                // Process-specific environment variables are stored in subdirectories of env.launch.
+                if scope == "env.launch" && scope_dir.is_dir() {
+                    for process_dir in sorted_dir_entries(&scope_dir)? {
+                        if process_dir.is_dir() {
+                            let process_scope = format!(
+                                "{}/{}",
+                                scope,
+                                process_dir
+                                    .file_name()
+                                    .unwrap_or_default()
+                                    .to_string_lossy()
+                            );
+                            env.append(&mut read_env_modifications(&process_dir, &process_scope)?);
+                        }
+                    }
+                }
+            }
+
+            Ok(LayerSummary { name, types, env })
+        })
+        .collect::<Result<Vec<_>, LocalLifecycleError>>()?;
+
+    let launch_toml_path = layers_dir.join("launch.toml");
+    let processes = if launch_toml_path.exists() {
+        toml::from_str::<Launch>(&fs::read_to_string(&launch_toml_path)?)
+            .map_err(LocalLifecycleError::CannotReadLaunch)?
+            .processes
+    } else {
+        vec![]
+    };
+
+    Ok(BuildSummary { layers, processes })
+}
+
+/// Reads the types of all layers in the given layers directory, sorted by layer name.
+fn read_layer_types(layers_dir: &Path) -> Result<Vec<(String, LayerTypes)>, LocalLifecycleError> {
+    let mut layer_types = vec![];
+
+    for path in sorted_dir_entries(layers_dir)? {
+        let name = match path.file_stem() {
+            Some(name) if path.extension() == Some(OsStr::new("toml")) => {
+                name.to_string_lossy().to_string()
+            }
+            _ => continue,
+        };
+
+        if ["launch", "build", "store"].contains(&name.as_str()) {
+            continue;
+        }
+
+        let layer_content_metadata = toml::from_str::<LayerContentMetadata<Option<toml::Value>>>(
+            &fs::read_to_string(&path)?,
+        )
+        .map_err(|error| LocalLifecycleError::CannotReadLayerContentMetadata(path, error))?;
+
+        layer_types.push((name, layer_content_metadata.types.unwrap_or_default()));
+    }
+
+    Ok(layer_types)
+}
+
+fn read_env_modifications(
+    scope_dir: &Path,
+    scope: &str,
+) -> Result<Vec<EnvModification>, LocalLifecycleError> {
+    let mut modifications = vec![];
+
+    if !scope_dir.is_dir() {
+        return Ok(modifications);
+    }
+
+    for path in sorted_dir_entries(scope_dir)? {
+        if !path.is_file() {
+            continue;
+        }
+
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        // Files without a known suffix override the variable, as specified since Buildpack API 0.5.
+        let (name, behavior) = match file_name.rsplit_once('.') {
+            Some((name, behavior))
+                if ["override", "append", "prepend", "default", "delim"].contains(&behavior) =>
+            {
+                (name, behavior)
+            }
+            _ => (file_name.as_ref(), "override"),
+        };
+
+        modifications.push(EnvModification {
+            scope: String::from(scope),
+            name: String::from(name),
+            behavior: String::from(behavior),
+            value: fs::read_to_string(&path)?,
+        });
+    }
+
+    Ok(modifications)
+}
+
+fn sorted_dir_entries(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    paths.sort();
+    Ok(paths)
+}
+
+#[derive(Debug)]
+pub enum LocalLifecycleError {
+    IoError(io::Error),
+    UnexpectedExitStatus(ExitStatus),
+    CannotReadBuildPlan(toml::de::Error),
+    CannotWriteBuildpackPlan(toml::ser::Error),
+    CannotReadLayerContentMetadata(PathBuf, toml::de::Error),
+    CannotReadLaunch(toml::de::Error),
+}
+
+impl From<io::Error> for LocalLifecycleError {
+    fn from(error: io::Error) -> Self {
+        LocalLifecycleError::IoError(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_layer(layers_dir: &Path, name: &str, types: &str) {
+        fs::create_dir_all(layers_dir.join(name).join("env.launch").join("web")).unwrap();
+        fs::write(
+            layers_dir.join(format!("{}.toml", name)),
+            format!("[types]\n{}\n", types),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn restores_layers_like_the_lifecycle() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let layers_dir = temp_dir.path();
+
+        write_layer(layers_dir, "cached", "cache = true");
+        write_layer(layers_dir, "runtime", "launch = true");
+        write_layer(layers_dir, "tools", "build = true");
+        fs::create_dir(layers_dir.join("no-metadata")).unwrap();
+        fs::write(layers_dir.join("launch.toml"), "").unwrap();
+        fs::write(layers_dir.join("store.toml"), "").unwrap();
+
+        restore_layers(layers_dir).unwrap();
+
+        let mut remaining = sorted_dir_entries(layers_dir)
+            .unwrap()
+            .into_iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        remaining.sort();
+
+        assert_eq!(
+            remaining,
+            vec!["cached", "cached.toml", "runtime.toml", "store.toml"]
+        );
+    }
+
+    #[test]
+    fn summarizes_layers() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let layers_dir = temp_dir.path();
+
+        write_layer(layers_dir, "ruby", "launch = true\nbuild = true");
+        fs::create_dir(layers_dir.join("ruby").join("env")).unwrap();
+        fs::write(
+            layers_dir.join("ruby").join("env").join("GEM_HOME"),
+            "/gems",
+        )
+        .unwrap();
+        fs::write(
+            layers_dir
+                .join("ruby")
+                .join("env.launch")
+                .join("web")
+                .join("PORT.default"),
+            "8080",
+        )
+        .unwrap();
+        fs::write(
+            layers_dir.join("launch.toml"),
+            "[[processes]]\ntype = \"web\"\ncommand = \"ruby\"\n",
+        )
+        .unwrap();
+
+        let summary = read_build_summary(layers_dir).unwrap();
+
+        assert_eq!(summary.layers.len(), 1);
+        assert_eq!(summary.layers[0].name, "ruby");
+        assert!(summary.layers[0].types.launch);
+        assert_eq!(
+            summary.layers[0].env,
+            vec![
+                EnvModification {
+                    scope: String::from("env"),
+                    name: String::from("GEM_HOME"),
+                    behavior: String::from("override"),
+                    value: String::from("/gems"),
+                },
+                EnvModification {
+                    scope: String::from("env.launch/web"),
+                    name: String::from("PORT"),
+                    behavior: String::from("default"),
+                    value: String::from("8080"),
+                }
+            ]
+        );
+        assert_eq!(summary.processes.len(), 1);
+    }
+
+    #[test]
+    fn resolves_plan_of_a_single_buildpack() {
+        let build_plan = toml::from_str::<BuildPlan>(
+            "[[provides]]\nname = \"ruby\"\n\n[[requires]]\nname = \"ruby\"\n",
+        )
+        .unwrap();
+
+        let buildpack_plan =
+            resolve_buildpack_plan(&"heroku/ruby".parse().unwrap(), Detection::Pass(build_plan))
+                .unwrap();
+
+        assert_eq!(buildpack_plan.entries.len(), 1);
+        assert_eq!(buildpack_plan.entries[0].name, "ruby");
+    }
+}
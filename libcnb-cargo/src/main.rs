@@ -6,15 +6,32 @@
 mod cli;
 use cargo_metadata::MetadataCommand;
 use clap::ArgMatches;
+use libcnb_cargo::assets::{copy_buildpack_assets, find_buildpack_assets, AssetError};
+use libcnb_cargo::buildpackage::{write_buildpackage_archive, BuildpackageError, OciPlatform};
+use libcnb_cargo::check::{check_buildpack_project, Severity};
 use libcnb_cargo::cross_compile::{cross_compile_assistance, CrossCompileAssistance};
+use libcnb_cargo::local_lifecycle::{
+    read_build_summary, resolve_buildpack_plan, restore_layers, LocalLifecycle, LocalLifecycleError,
+};
+use libcnb_cargo::meta_buildpack::{
+    assemble_meta_buildpack_directory, find_workspace_buildpacks,
+    resolve_meta_buildpack_dependencies, MetaBuildpackError,
+};
+use libcnb_cargo::scaffold::{scaffold_buildpack_project, ProjectTemplate, ScaffoldError};
 use libcnb_cargo::{
-    assemble_buildpack_directory, build_buildpack_binary, default_buildpack_directory_name,
-    read_buildpack_data, BuildError, BuildpackDataError, CargoProfile,
+    assemble_buildpack_directory, assemble_multi_platform_buildpack_directory,
+    build_buildpack_binaries, buildpack_binary_target_names, default_buildpack_directory_name,
+    read_buildpack_data, BuildError, BuildpackData, BuildpackDataError, CargoBuildOptions,
+    CargoProfile,
 };
+use libcnb_data::buildpack::{BuildpackDescriptor, BuildpackId, BuildpackVersion, Stack, StackId};
 use log::info;
 use log::{error, warn};
 use size_format::SizeFormatterSI;
+use std::convert::TryFrom;
+use std::ffi::OsString;
 use std::fs;
+use std::path::{Path, PathBuf};
 
 fn main() {
     setup_logging();
@@ -22,8 +39,13 @@ fn main() {
     match cli::setup_cli_parsing().get_matches().subcommand() {
         ("libcnb", Some(matches)) => match matches.subcommand() {
             ("package", Some(matches)) => handle_libcnb_package(matches),
+            ("build-app", Some(matches)) => handle_libcnb_build_app(matches),
+            ("check", Some(_)) => handle_libcnb_check(),
+            ("new", Some(matches)) => handle_libcnb_new(matches),
             // This should never be reached since clap will catch unknown subcommands for us
-            _ => unimplemented!("Only the \"package\" subcommand is currently implemented!"),
+            _ => unimplemented!(
+                "Only the \"package\", \"build-app\", \"check\" and \"new\" subcommands are currently implemented!"
+            ),
         },
         // This should never be reached since clap will catch unknown subcommands for us
         _ => unimplemented!("Only the \"libcnb\" subcommand is currently implemented!"),
@@ -32,20 +54,83 @@ fn main() {
 
 #[allow(clippy::too_many_lines)]
 fn handle_libcnb_package(matches: &ArgMatches) {
-    let cargo_profile = if matches.is_present("release") {
-        CargoProfile::Release
-    } else {
-        CargoProfile::Dev
+    let cargo_profile = match matches.value_of("profile") {
+        Some("dev") => CargoProfile::Dev,
+        Some("release") => CargoProfile::Release,
+        Some(profile_name) => CargoProfile::Custom(String::from(profile_name)),
+        None if matches.is_present("release") => CargoProfile::Release,
+        None => CargoProfile::Dev,
     };
 
-    let target_triple = match matches.value_of("target") {
-        None => {
-            error!("Could not determine target triple!");
-            std::process::exit(1);
-        }
-        Some(target_triple) => target_triple,
+    let cargo_build_options = CargoBuildOptions {
+        features: matches
+            .values_of("features")
+            .map(|values| values.map(String::from).collect())
+            .unwrap_or_default(),
+        no_default_features: matches.is_present("no-default-features"),
+        locked: matches.is_present("locked"),
     };
 
+    let target_triples: Vec<&str> = matches
+        .values_of("target")
+        .map(Iterator::collect)
+        .unwrap_or_default();
+
+    if target_triples.is_empty() {
+        error!("Could not determine target triple!");
+        std::process::exit(1);
+    }
+
+    let is_multi_target = target_triples.len() > 1;
+    let is_oci_format = matches.value_of("format") == Some("oci");
+
+    if is_multi_target && is_oci_format {
+        error!("Buildpackage archives can only be written for a single target triple.");
+        std::process::exit(1);
+    }
+
+    // Determined early to fail before building anything if a target is not supported.
+    let target_platforms: Vec<Option<OciPlatform>> = target_triples
+        .iter()
+        .map(|target_triple| {
+            let platform = OciPlatform::from_target_triple(target_triple);
+
+            if platform.is_none() && (is_multi_target || is_oci_format) {
+                error!(
+                    "Could not determine the platform for target triple {}.",
+                    target_triple
+                );
+                error!("Buildpackage archives and buildpacks for multiple targets can only be written for Linux x86_64 and aarch64 targets.");
+                std::process::exit(1);
+            }
+
+            platform
+        })
+        .collect();
+
+    let oci_platform = if is_oci_format {
+        target_platforms.first().cloned().flatten()
+    } else {
+        None
+    };
+
+    let duplicate_platform = target_platforms
+        .iter()
+        .enumerate()
+        .find_map(|(index, platform)| {
+            platform
+                .as_ref()
+                .filter(|_| target_platforms[..index].contains(platform))
+        });
+
+    if let Some(platform) = duplicate_platform {
+        error!(
+            "Multiple target triples were given for platform {}/{}.",
+            platform.os, platform.architecture
+        );
+        std::process::exit(1);
+    }
+
     let current_dir = match std::env::current_dir() {
         Ok(current_dir) => current_dir,
         Err(io_error) => {
@@ -55,37 +140,274 @@ fn handle_libcnb_package(matches: &ArgMatches) {
     };
 
     info!("Reading buildpack metadata...");
-    let buildpack_data = match read_buildpack_data(&current_dir) {
-        Ok(buildpack_data) => buildpack_data,
+    let buildpack_data = read_buildpack_data_or_exit(&current_dir);
+
+    match &buildpack_data.buildpack_descriptor {
+        BuildpackDescriptor::Single(buildpack_descriptor) => info!(
+            "Found buildpack {} with version {}.",
+            buildpack_descriptor.buildpack.id, buildpack_descriptor.buildpack.version
+        ),
+        BuildpackDescriptor::Meta(buildpack_descriptor) => info!(
+            "Found meta-buildpack {} with version {}.",
+            buildpack_descriptor.buildpack.id, buildpack_descriptor.buildpack.version
+        ),
+    }
+
+    // For meta-buildpacks, the current directory does not need to be a Cargo package. Cargo will
+    // find the surrounding workspace instead.
+    let cargo_metadata = match MetadataCommand::new().current_dir(&current_dir).exec() {
+        Ok(cargo_metadata) => cargo_metadata,
         Err(error) => {
+            error!("Could not obtain metadata from Cargo: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let buildpacks_output_path = cargo_metadata
+        .target_directory
+        .join("buildpack")
+        .join(cargo_profile.directory_name())
+        .into_std_path_buf();
+
+    let output_path = buildpacks_output_path.join(default_buildpack_directory_name(
+        &buildpack_data.buildpack_descriptor,
+    ));
+
+    let relative_output_path =
+        pathdiff::diff_paths(&output_path, &current_dir).unwrap_or_else(|| output_path.clone());
+
+    let dry_run = matches.is_present("dry-run");
+
+    // Nothing is built during a dry run, cross-compile settings are not needed.
+    let no_cross_compile_assistance = dry_run || matches.is_present("no-cross-compile-assistance");
+    if !no_cross_compile_assistance {
+        info!("Determining automatic cross-compile settings...");
+    }
+
+    let build_targets: Vec<BuildTarget> = target_triples
+        .iter()
+        .zip(target_platforms)
+        .map(|(target_triple, platform)| BuildTarget {
+            target_triple: String::from(*target_triple),
+            platform,
+            cargo_env: if no_cross_compile_assistance {
+                vec![]
+            } else {
+                cross_compile_env_or_exit(target_triple)
+            },
+        })
+        .collect();
+
+    let dependency_paths = match &buildpack_data.buildpack_descriptor {
+        BuildpackDescriptor::Single(_) => {
+            package_single_buildpack(
+                &current_dir,
+                &buildpack_data,
+                &output_path,
+                &cargo_profile,
+                &build_targets,
+                matches.value_of("bin"),
+                &cargo_build_options,
+                dry_run,
+            );
+
+            vec![]
+        }
+        BuildpackDescriptor::Meta(buildpack_descriptor) => {
+            info!("Resolving meta-buildpack dependencies in Cargo workspace...");
+            let workspace_buildpacks = find_workspace_buildpacks(&cargo_metadata)
+                .unwrap_or_else(|error| exit_with_meta_buildpack_error(error));
+
+            let dependencies =
+                resolve_meta_buildpack_dependencies(buildpack_descriptor, &workspace_buildpacks)
+                    .unwrap_or_else(|error| exit_with_meta_buildpack_error(error));
+
+            // Features are specific to a package and are therefore not passed to dependencies.
+            let dependency_build_options = CargoBuildOptions {
+                locked: cargo_build_options.locked,
+                ..CargoBuildOptions::default()
+            };
+
+            let dependency_paths: Vec<PathBuf> = dependencies
+                .iter()
+                .map(|dependency| {
+                    let dependency_descriptor = &dependency.buildpack_data.buildpack_descriptor;
+                    info!(
+                        "Packaging dependency {} with version {}...",
+                        dependency_descriptor.buildpack().id,
+                        dependency_descriptor.buildpack().version
+                    );
+
+                    let dependency_output_path = buildpacks_output_path
+                        .join(default_buildpack_directory_name(dependency_descriptor));
+
+                    package_single_buildpack(
+                        &dependency.project_path,
+                        &dependency.buildpack_data,
+                        &dependency_output_path,
+                        &cargo_profile,
+                        &build_targets,
+                        None,
+                        &dependency_build_options,
+                        dry_run,
+                    );
+
+                    dependency_output_path
+                })
+                .collect();
+
+            if dry_run {
+                log_dry_run_files(
+                    &output_path,
+                    &[
+                        PathBuf::from("buildpack.toml"),
+                        PathBuf::from("package.toml"),
+                    ],
+                );
+                info!("Dry run finished, nothing was written.");
+                return;
+            }
+
+            info!("Writing meta-buildpack directory...");
+            remove_dir_or_exit(&output_path);
+
+            if let Err(io_error) = assemble_meta_buildpack_directory(
+                &output_path,
+                &buildpack_data.buildpack_descriptor_path,
+                &dependency_paths,
+            ) {
+                error!(
+                    "IO error while writing meta-buildpack directory: {}",
+                    io_error
+                );
+                std::process::exit(1);
+            }
+
+            dependency_paths
+        }
+    };
+
+    if dry_run {
+        info!("Dry run finished, nothing was written.");
+        return;
+    }
+
+    info!(
+        "Successfully wrote buildpack directory: {} ({})",
+        relative_output_path.to_string_lossy(),
+        fs_extra::dir::get_size(&output_path).map_or_else(
+            |_| String::from("unknown size"),
+            |size| SizeFormatterSI::new(size).to_string()
+        )
+    );
+
+    if let Some(platform) = oci_platform {
+        let archive_path = output_path.with_extension("cnb");
+        let relative_archive_path = pathdiff::diff_paths(&archive_path, &current_dir)
+            .unwrap_or_else(|| archive_path.clone());
+
+        info!("Writing buildpackage archive...");
+        if let Err(error) =
+            write_buildpackage_archive(&archive_path, &output_path, &dependency_paths, &platform)
+        {
             match error {
-                BuildpackDataError::IoError(io_error) => {
-                    error!("Unable to read buildpack metadata: {}", io_error);
-                    error!("Hint: Verify that a readable file named \"buildpack.toml\" exists at the root of your project.");
+                BuildpackageError::IoError(io_error) => {
+                    error!("IO error while writing buildpackage archive: {}", io_error);
                 }
-                BuildpackDataError::DeserializationError(deserialization_error) => {
+                BuildpackageError::CannotReadBuildpack(buildpack_path, buildpack_data_error) => {
                     error!(
-                        "Unable to deserialize buildpack metadata: {}",
-                        deserialization_error
+                        "Could not read packaged buildpack at {}.",
+                        buildpack_path.to_string_lossy()
                     );
-                    error!("Hint: Verify that your \"buildpack.toml\" is valid.");
+                    log_buildpack_data_error(buildpack_data_error);
+                }
+                BuildpackageError::JsonError(json_error) => {
+                    error!("Could not serialize buildpackage metadata: {}", json_error);
                 }
             }
 
             std::process::exit(1);
         }
+
+        info!(
+            "Successfully wrote buildpackage archive: {} ({})",
+            relative_archive_path.to_string_lossy(),
+            fs::metadata(&archive_path).map_or_else(
+                |_| String::from("unknown size"),
+                |metadata| SizeFormatterSI::new(metadata.len()).to_string()
+            )
+        );
+    }
+
+    info!("Packaging successfully finished!");
+    match &buildpack_data.buildpack_descriptor {
+        BuildpackDescriptor::Single(_) => {
+            info!("Hint: To test your buildpack locally with pack, run: pack build my-image --buildpack {} --path /path/to/application", relative_output_path.to_string_lossy());
+        }
+        BuildpackDescriptor::Meta(_) => {
+            info!("Hint: To create a buildpackage with pack, run: pack buildpack package my-buildpackage --config {}", relative_output_path.join("package.toml").to_string_lossy());
+        }
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+fn handle_libcnb_build_app(matches: &ArgMatches) {
+    let current_dir = match std::env::current_dir() {
+        Ok(current_dir) => current_dir,
+        Err(io_error) => {
+            error!("Could not determine current directory: {}", io_error);
+            std::process::exit(1);
+        }
+    };
+
+    info!("Reading buildpack metadata...");
+    let buildpack_data = read_buildpack_data_or_exit(&current_dir);
+
+    let buildpack_descriptor = match &buildpack_data.buildpack_descriptor {
+        BuildpackDescriptor::Single(buildpack_descriptor) => buildpack_descriptor,
+        BuildpackDescriptor::Meta(_) => {
+            error!("Meta-buildpacks cannot be run locally, run build-app in the directory of one of its buildpacks instead.");
+            std::process::exit(1);
+        }
     };
 
     info!(
         "Found buildpack {} with version {}.",
-        buildpack_data.buildpack_descriptor.buildpack.id,
-        buildpack_data.buildpack_descriptor.buildpack.version
+        buildpack_descriptor.buildpack.id, buildpack_descriptor.buildpack.version
     );
 
-    let cargo_metadata = match MetadataCommand::new()
-        .manifest_path(&current_dir.join("Cargo.toml"))
-        .exec()
-    {
+    let stack_id = if let Some(stack_id) = matches.value_of("stack-id") {
+        stack_id.parse::<StackId>().unwrap_or_else(|error| {
+            error!("Invalid stack ID: {}", error);
+            std::process::exit(1);
+        })
+    } else {
+        let first_stack_id = buildpack_descriptor
+            .stacks
+            .iter()
+            .find_map(|stack| match stack {
+                Stack::Specific { id, .. } => Some(id.clone()),
+                Stack::Any => None,
+            });
+
+        first_stack_id.unwrap_or_else(|| {
+            error!("Could not determine a stack ID from buildpack.toml.");
+            error!("Hint: Pass the stack ID to build with, for example: --stack-id heroku-20");
+            std::process::exit(1);
+        })
+    };
+
+    let target_triple = matches
+        .value_of("target")
+        .map_or_else(host_target_triple_or_exit, String::from);
+
+    let cargo_profile = if matches.is_present("release") {
+        CargoProfile::Release
+    } else {
+        CargoProfile::Dev
+    };
+
+    let cargo_metadata = match MetadataCommand::new().current_dir(&current_dir).exec() {
         Ok(cargo_metadata) => cargo_metadata,
         Err(error) => {
             error!("Could not obtain metadata from Cargo: {}", error);
@@ -93,114 +415,640 @@ fn handle_libcnb_package(matches: &ArgMatches) {
         }
     };
 
-    let output_path = cargo_metadata
+    let buildpack_dir = cargo_metadata
         .target_directory
         .join("buildpack")
-        .join(match cargo_profile {
-            CargoProfile::Dev => "debug",
-            CargoProfile::Release => "release",
-        })
+        .join(cargo_profile.directory_name())
         .join(default_buildpack_directory_name(
             &buildpack_data.buildpack_descriptor,
         ))
         .into_std_path_buf();
 
-    let relative_output_path =
-        pathdiff::diff_paths(&output_path, &current_dir).unwrap_or_else(|| output_path.clone());
+    package_single_buildpack(
+        &current_dir,
+        &buildpack_data,
+        &buildpack_dir,
+        &cargo_profile,
+        &[BuildTarget {
+            target_triple,
+            platform: None,
+            cargo_env: vec![],
+        }],
+        None,
+        &CargoBuildOptions::default(),
+        false,
+    );
 
-    let cargo_build_env = if matches.is_present("no-cross-compile-assistance") {
-        vec![]
-    } else {
-        info!("Determining automatic cross-compile settings...");
-        match cross_compile_assistance(target_triple) {
-            CrossCompileAssistance::HelpText(help_text) => {
-                error!("{}", help_text);
-                info!("To disable cross-compile assistance, pass --no-cross-compile-assistance.");
-                std::process::exit(1);
-            }
-            CrossCompileAssistance::NoAssistance => {
-                warn!(
-                    "Could not determine automatic cross-compile settings for target triple {}.",
-                    &target_triple
-                );
-                warn!("This is not an error, but without proper cross-compile settings in your Cargo manifest and locally installed toolchains, compilation might fail.");
-                warn!("To disable this warning, pass --no-cross-compile-assistance.");
-                vec![]
+    let temp_dir = tempfile::tempdir().unwrap_or_else(|io_error| {
+        error!("Could not create temporary directory: {}", io_error);
+        std::process::exit(1);
+    });
+
+    let layers_dir = match matches.value_of("layers-dir") {
+        Some(layers_dir) => {
+            // The phases run in the application directory, relative paths would not resolve there.
+            let layers_dir = current_dir.join(layers_dir);
+
+            if layers_dir.exists() {
+                info!("Restoring layers of previous build...");
+                restore_layers(&layers_dir)
+                    .unwrap_or_else(|error| exit_with_local_lifecycle_error(error));
             }
-            CrossCompileAssistance::Configuration { cargo_env } => cargo_env,
+
+            layers_dir
         }
+        None => temp_dir.path().join("layers"),
     };
 
-    info!("Building buildpack binary ({})...", &target_triple);
-    let binary_path = match build_buildpack_binary(
-        &current_dir,
-        cargo_profile,
-        &target_triple,
-        cargo_build_env,
-    ) {
-        Ok(binary_path) => binary_path,
+    let lifecycle = LocalLifecycle {
+        buildpack_dir,
+        app_dir: temp_dir.path().join("app"),
+        layers_dir,
+        platform_dir: temp_dir.path().join("platform"),
+        plan_dir: temp_dir.path().join("plan"),
+        stack_id,
+    };
+
+    info!("Copying application...");
+    let app_path = PathBuf::from(matches.value_of("app").unwrap_or_default());
+    let copy_result = [
+        &lifecycle.app_dir,
+        &lifecycle.layers_dir,
+        &lifecycle.platform_dir.join("env"),
+        &lifecycle.plan_dir,
+    ]
+    .iter()
+    .try_for_each(fs::create_dir_all)
+    .map_err(fs_extra::error::Error::from)
+    .and_then(|()| {
+        fs_extra::dir::copy(
+            &app_path,
+            &lifecycle.app_dir,
+            &fs_extra::dir::CopyOptions {
+                content_only: true,
+                ..fs_extra::dir::CopyOptions::new()
+            },
+        )
+    });
+
+    if let Err(error) = copy_result {
+        error!(
+            "Could not copy application {}: {}",
+            app_path.to_string_lossy(),
+            error
+        );
+        std::process::exit(1);
+    }
+
+    info!("Running detect with stack {}...", lifecycle.stack_id);
+    let detection = lifecycle
+        .detect()
+        .unwrap_or_else(|error| exit_with_local_lifecycle_error(error));
+
+    let buildpack_plan = match resolve_buildpack_plan(&buildpack_descriptor.buildpack.id, detection)
+    {
+        Ok(buildpack_plan) => buildpack_plan,
         Err(error) => {
-            error!("Packaging buildpack failed due to a build related error!");
+            error!("{}", error);
+            std::process::exit(1);
+        }
+    };
 
-            match error {
-                BuildError::IoError(io_error) => {
-                    error!("IO error while executing Cargo: {}", io_error);
-                }
-                BuildError::UnexpectedExitStatus(exit_status) => {
-                    error!(
-                        "Unexpected Cargo exit status: {}",
-                        exit_status
-                            .code()
-                            .map_or_else(|| String::from("<unknown>"), |code| code.to_string())
-                    );
-                    error!("Examine Cargo output for details and potential compilation errors.");
-                }
-                BuildError::NoTargetsFound => {
-                    error!("No targets were found in the Cargo manifest. Ensure that there is exactly one binary target and try again.");
-                }
-                BuildError::MultipleTargetsFound => {
-                    error!("Multiple targets were found in the Cargo manifest. Ensure that there is exactly one binary target and try again.");
-                }
-                BuildError::MetadataError(metadata_error) => {
-                    error!("Unable to obtain metadata from Cargo: {}", metadata_error);
-                }
-                BuildError::CouldNotFindRootPackage => {
-                    error!("Root package could not be determined from the Cargo manifest.");
-                }
-            }
+    info!("Running build...");
+    lifecycle
+        .build(&buildpack_plan)
+        .unwrap_or_else(|error| exit_with_local_lifecycle_error(error));
+
+    let build_summary = read_build_summary(&lifecycle.layers_dir)
+        .unwrap_or_else(|error| exit_with_local_lifecycle_error(error));
+
+    info!("Build finished successfully!");
+    info!("Layers:");
+    for layer in &build_summary.layers {
+        let types: Vec<&str> = [
+            (layer.types.launch, "launch"),
+            (layer.types.build, "build"),
+            (layer.types.cache, "cache"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| *name)
+        .collect();
+
+        info!("  {} ({})", layer.name, types.join(", "));
+        for modification in &layer.env {
+            info!(
+                "    {}: {} {} {}",
+                modification.scope, modification.name, modification.behavior, modification.value
+            );
+        }
+    }
 
+    info!("Processes:");
+    for process in &build_summary.processes {
+        info!(
+            "  {}: {} {}",
+            process.r#type,
+            process.command,
+            process.args.clone().unwrap_or_default().join(" ")
+        );
+    }
+
+    if matches.value_of("layers-dir").is_none() {
+        info!("Hint: Pass --layers-dir to keep the layers and run the build again against them.");
+    }
+}
+
+fn host_target_triple_or_exit() -> String {
+    let output = std::process::Command::new("rustc")
+        .arg("-vV")
+        .output()
+        .unwrap_or_else(|io_error| {
+            error!(
+                "Could not run rustc to determine the host target triple: {}",
+                io_error
+            );
+            std::process::exit(1);
+        });
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map_or_else(
+            || {
+                error!("Could not determine the host target triple, pass --target explicitly.");
+                std::process::exit(1);
+            },
+            String::from,
+        )
+}
+
+fn exit_with_local_lifecycle_error(error: LocalLifecycleError) -> ! {
+    match error {
+        LocalLifecycleError::IoError(io_error) => {
+            error!("IO error while running buildpack: {}", io_error);
+        }
+        LocalLifecycleError::UnexpectedExitStatus(exit_status) => {
+            error!(
+                "Buildpack exited with unexpected exit status: {}",
+                exit_status
+                    .code()
+                    .map_or_else(|| String::from("<unknown>"), |code| code.to_string())
+            );
+        }
+        LocalLifecycleError::CannotReadBuildPlan(toml_error) => {
+            error!(
+                "Could not read build plan written by detect: {}",
+                toml_error
+            );
+        }
+        LocalLifecycleError::CannotWriteBuildpackPlan(toml_error) => {
+            error!("Could not write buildpack plan: {}", toml_error);
+        }
+        LocalLifecycleError::CannotReadLayerContentMetadata(path, toml_error) => {
+            error!(
+                "Could not read layer content metadata {}: {}",
+                path.to_string_lossy(),
+                toml_error
+            );
+        }
+        LocalLifecycleError::CannotReadLaunch(toml_error) => {
+            error!("Could not read launch.toml: {}", toml_error);
+        }
+    }
+
+    std::process::exit(1);
+}
+
+fn handle_libcnb_check() {
+    let current_dir = match std::env::current_dir() {
+        Ok(current_dir) => current_dir,
+        Err(io_error) => {
+            error!("Could not determine current directory: {}", io_error);
             std::process::exit(1);
         }
     };
 
-    info!("Writing buildpack directory...");
-    if output_path.exists() {
-        if let Err(error) = fs::remove_dir_all(&output_path) {
+    let cargo_metadata = match MetadataCommand::new().current_dir(&current_dir).exec() {
+        Ok(cargo_metadata) => cargo_metadata,
+        Err(error) => {
+            error!("Could not obtain metadata from Cargo: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    info!("Checking buildpack.toml...");
+    let diagnostics = match check_buildpack_project(&current_dir, &cargo_metadata) {
+        Ok(diagnostics) => diagnostics,
+        Err(io_error) => {
+            error!("Could not read buildpack.toml: {}", io_error);
+            std::process::exit(1);
+        }
+    };
+
+    for diagnostic in &diagnostics {
+        let location = diagnostic.position.map_or_else(
+            || String::from("buildpack.toml"),
+            |position| format!("buildpack.toml:{}", position),
+        );
+
+        match diagnostic.severity {
+            Severity::Error => error!("{}: {}", location, diagnostic.message),
+            Severity::Warning => warn!("{}: {}", location, diagnostic.message),
+        }
+    }
+
+    let error_count = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+
+    if error_count > 0 {
+        error!(
+            "Check failed with {} error(s) and {} warning(s).",
+            error_count,
+            diagnostics.len() - error_count
+        );
+        std::process::exit(1);
+    }
+
+    info!(
+        "Check finished with {} warning(s).",
+        diagnostics.len() - error_count
+    );
+}
+
+fn handle_libcnb_new(matches: &ArgMatches) {
+    // clap ensures that required arguments and arguments with default values are present.
+    let path = PathBuf::from(matches.value_of("path").unwrap_or_default());
+
+    let buildpack_id = match matches
+        .value_of("id")
+        .unwrap_or_default()
+        .parse::<BuildpackId>()
+    {
+        Ok(buildpack_id) => buildpack_id,
+        Err(error) => {
+            error!("Invalid buildpack ID: {}", error);
+            error!("Hint: Buildpack IDs may only contain letters, numbers and the characters '.', '/' and '-'. They must not be 'config' or 'app'.");
+            std::process::exit(1);
+        }
+    };
+
+    let buildpack_version =
+        match BuildpackVersion::try_from(matches.value_of("buildpack-version").unwrap_or_default())
+        {
+            Ok(buildpack_version) => buildpack_version,
+            Err(error) => {
+                error!("{}", error);
+                error!(
+                "Hint: Buildpack versions must be in the form <X>.<Y>.<Z>, without leading zeros."
+            );
+                std::process::exit(1);
+            }
+        };
+
+    let template = if matches.is_present("meta") {
+        ProjectTemplate::Meta
+    } else {
+        ProjectTemplate::Single
+    };
+
+    info!("Creating buildpack project...");
+    if let Err(error) =
+        scaffold_buildpack_project(&path, &buildpack_id, &buildpack_version, template)
+    {
+        match error {
+            ScaffoldError::IoError(io_error) => {
+                error!("IO error while writing project: {}", io_error);
+            }
+            ScaffoldError::DestinationExists(path) => {
+                error!("Destination {} already exists.", path.to_string_lossy());
+            }
+            ScaffoldError::InvalidPackageName(path) => {
+                error!(
+                    "Could not derive a package name from {}.",
+                    path.to_string_lossy()
+                );
+                error!("Hint: The last component of the path must start with a letter and may only contain letters, numbers, '-' and '_'.");
+            }
+            ScaffoldError::InvalidBuildpackId(error) => {
+                error!("Invalid buildpack ID: {}", error);
+            }
+        }
+
+        std::process::exit(1);
+    }
+
+    info!(
+        "Successfully created buildpack {} in {}.",
+        buildpack_id,
+        path.to_string_lossy()
+    );
+    info!(
+        "Hint: To package the buildpack, run: cd {} && cargo libcnb package",
+        path.to_string_lossy()
+    );
+}
+
+fn read_buildpack_data_or_exit(project_path: &Path) -> BuildpackData<Option<toml::Value>> {
+    match read_buildpack_data(project_path) {
+        Ok(buildpack_data) => buildpack_data,
+        Err(error) => {
+            log_buildpack_data_error(error);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn log_buildpack_data_error(error: BuildpackDataError) {
+    match error {
+        BuildpackDataError::IoError(io_error) => {
+            error!("Unable to read buildpack metadata: {}", io_error);
+            error!("Hint: Verify that a readable file named \"buildpack.toml\" exists at the root of your project.");
+        }
+        BuildpackDataError::DeserializationError(deserialization_error) => {
+            error!(
+                "Unable to deserialize buildpack metadata: {}",
+                deserialization_error
+            );
+            error!("Hint: Verify that your \"buildpack.toml\" is valid.");
+        }
+    }
+}
+
+fn exit_with_meta_buildpack_error(error: MetaBuildpackError) -> ! {
+    match error {
+        MetaBuildpackError::CannotReadWorkspaceBuildpack(project_path, buildpack_data_error) => {
+            error!(
+                "Could not read buildpack in Cargo workspace at {}.",
+                project_path.to_string_lossy()
+            );
+            log_buildpack_data_error(buildpack_data_error);
+        }
+        MetaBuildpackError::BuildpackNotFound(id, version) => {
+            error!(
+                "Buildpack {} with version {} is referenced by the meta-buildpack, but not part of the Cargo workspace.",
+                id, version
+            );
+        }
+        MetaBuildpackError::BuildpackVersionMismatch {
+            id,
+            version,
+            workspace_version,
+        } => {
+            error!(
+                "Buildpack {} is referenced with version {} by the meta-buildpack, but has version {} in the Cargo workspace.",
+                id, version, workspace_version
+            );
+        }
+    }
+
+    std::process::exit(1);
+}
+
+fn remove_dir_or_exit(path: &Path) {
+    if path.exists() {
+        if let Err(error) = fs::remove_dir_all(path) {
             error!("Could not remove buildpack directory: {}", &error);
             std::process::exit(1);
+        }
+    }
+}
+
+/// A target triple to build buildpack binaries for.
+struct BuildTarget {
+    target_triple: String,
+    /// The platform of the binaries, required when packaging for more than one target.
+    platform: Option<OciPlatform>,
+    cargo_env: Vec<(OsString, OsString)>,
+}
+
+/// Builds the buildpack binaries of the given project and assembles the buildpack directory.
+///
+/// When building for more than one target, the binaries of each target are written to a separate
+/// directory per platform.
+///
+/// Exits the process if any of the steps fail.
+#[allow(clippy::too_many_arguments)]
+fn package_single_buildpack(
+    project_path: &Path,
+    buildpack_data: &BuildpackData<Option<toml::Value>>,
+    output_path: &Path,
+    cargo_profile: &CargoProfile,
+    build_targets: &[BuildTarget],
+    main_binary_name: Option<&str>,
+    cargo_build_options: &CargoBuildOptions,
+    dry_run: bool,
+) {
+    let asset_paths =
+        find_buildpack_assets(project_path).unwrap_or_else(|error| exit_with_asset_error(error));
+
+    if dry_run {
+        let (main_binary_name, additional_binary_names) =
+            buildpack_binary_target_names(project_path, main_binary_name)
+                .unwrap_or_else(|error| exit_with_build_error(error));
+
+        let bin_paths: Vec<PathBuf> = match build_targets {
+            [_] => vec![PathBuf::from("bin")],
+            _ => build_targets
+                .iter()
+                .filter_map(|build_target| build_target.platform.as_ref())
+                .map(|platform| {
+                    PathBuf::from(&platform.os)
+                        .join(&platform.architecture)
+                        .join("bin")
+                })
+                .collect(),
         };
+
+        info!(
+            "Binary target {} would be packaged as the build and detect executables.",
+            main_binary_name
+        );
+
+        let mut file_paths = vec![PathBuf::from("buildpack.toml")];
+        for bin_path in bin_paths {
+            file_paths.push(bin_path.join("build"));
+            file_paths.push(bin_path.join("detect"));
+            for additional_binary_name in &additional_binary_names {
+                file_paths.push(bin_path.join(additional_binary_name));
+            }
+        }
+        file_paths.extend(asset_paths);
+
+        log_dry_run_files(output_path, &file_paths);
+        return;
+    }
+
+    let mut platform_binaries = vec![];
+
+    for build_target in build_targets {
+        info!(
+            "Building buildpack binaries ({})...",
+            &build_target.target_triple
+        );
+
+        let buildpack_binaries = build_buildpack_binaries(
+            project_path,
+            cargo_profile,
+            &build_target.target_triple,
+            main_binary_name,
+            cargo_build_options,
+            build_target.cargo_env.iter().cloned(),
+        )
+        .unwrap_or_else(|error| exit_with_build_error(error));
+
+        platform_binaries.push((build_target.platform.clone(), buildpack_binaries));
     }
 
-    if let Err(io_error) = assemble_buildpack_directory(
-        &output_path,
-        &buildpack_data.buildpack_descriptor_path,
-        &binary_path,
-    ) {
+    info!("Writing buildpack directory...");
+    remove_dir_or_exit(output_path);
+
+    let assemble_result = if let [(_, buildpack_binaries)] = platform_binaries.as_slice() {
+        assemble_buildpack_directory(
+            output_path,
+            &buildpack_data.buildpack_descriptor_path,
+            buildpack_binaries,
+        )
+    } else {
+        let platform_binaries: Vec<_> = platform_binaries
+            .into_iter()
+            .filter_map(|(platform, buildpack_binaries)| {
+                platform.map(|platform| (platform, buildpack_binaries))
+            })
+            .collect();
+
+        assemble_multi_platform_buildpack_directory(
+            output_path,
+            &buildpack_data.buildpack_descriptor_path,
+            &platform_binaries,
+        )
+    };
+
+    if let Err(io_error) = assemble_result
+        .and_then(|()| copy_buildpack_assets(project_path, output_path, &asset_paths))
+    {
         error!("IO error while writing buildpack directory: {}", io_error);
         std::process::exit(1);
-    };
+    }
+}
 
+fn log_dry_run_files(output_path: &Path, file_paths: &[PathBuf]) {
     info!(
-        "Successfully wrote buildpack directory: {} ({})",
-        relative_output_path.to_string_lossy(),
-        fs_extra::dir::get_size(&output_path).map_or_else(
-            |_| String::from("unknown size"),
-            |size| SizeFormatterSI::new(size).to_string()
-        )
+        "Would write buildpack directory {}:",
+        output_path.to_string_lossy()
     );
 
-    info!("Packaging successfully finished!");
-    info!("Hint: To test your buildpack locally with pack, run: pack build my-image --buildpack {} --path /path/to/application", relative_output_path.to_string_lossy());
+    for file_path in file_paths {
+        info!("  {}", file_path.to_string_lossy());
+    }
+}
+
+fn exit_with_asset_error(error: AssetError) -> ! {
+    error!("Could not determine additional buildpack files!");
+
+    match error {
+        AssetError::MetadataError(metadata_error) => {
+            error!("Unable to obtain metadata from Cargo: {}", metadata_error);
+        }
+        AssetError::InvalidIncludeMetadata => {
+            error!("The package.metadata.libcnb.include setting in the Cargo manifest must be a list of strings.");
+        }
+        AssetError::InvalidPattern(pattern, pattern_error) => {
+            error!("Invalid include pattern {}: {}", pattern, pattern_error);
+        }
+        AssetError::PatternOutsideProject(pattern) => {
+            error!(
+                "Include pattern {} refers to files outside of the project directory.",
+                pattern
+            );
+        }
+        AssetError::NoMatchingFiles(pattern) => {
+            error!("Include pattern {} did not match any files.", pattern);
+            error!("Hint: Patterns in package.metadata.libcnb.include are relative to the directory of the Cargo manifest.");
+        }
+        AssetError::ReservedPath(path) => {
+            error!(
+                "Included file {} would overwrite a file that is written by packaging.",
+                path.to_string_lossy()
+            );
+        }
+        AssetError::IoError(io_error) => {
+            error!("IO error while finding included files: {}", io_error);
+        }
+    }
+
+    std::process::exit(1);
+}
+
+fn cross_compile_env_or_exit(target_triple: &str) -> Vec<(OsString, OsString)> {
+    match cross_compile_assistance(target_triple) {
+        CrossCompileAssistance::HelpText(help_text) => {
+            error!("{}", help_text);
+            info!("To disable cross-compile assistance, pass --no-cross-compile-assistance.");
+            std::process::exit(1);
+        }
+        CrossCompileAssistance::NoAssistance => {
+            warn!(
+                "Could not determine automatic cross-compile settings for target triple {}.",
+                target_triple
+            );
+            warn!("This is not an error, but without proper cross-compile settings in your Cargo manifest and locally installed toolchains, compilation might fail.");
+            warn!("To disable this warning, pass --no-cross-compile-assistance.");
+            vec![]
+        }
+        CrossCompileAssistance::Configuration { cargo_env } => cargo_env,
+    }
+}
+
+fn exit_with_build_error(error: BuildError) -> ! {
+    error!("Packaging buildpack failed due to a build related error!");
+
+    match error {
+        BuildError::IoError(io_error) => {
+            error!("IO error while executing Cargo: {}", io_error);
+        }
+        BuildError::UnexpectedExitStatus(exit_status) => {
+            error!(
+                "Unexpected Cargo exit status: {}",
+                exit_status
+                    .code()
+                    .map_or_else(|| String::from("<unknown>"), |code| code.to_string())
+            );
+            error!("Examine Cargo output for details and potential compilation errors.");
+        }
+        BuildError::NoBinaryTargetsFound => {
+            error!("No binary targets were found in the Cargo manifest. Ensure that there is at least one binary target and try again.");
+        }
+        BuildError::MultipleBinaryTargetsFound => {
+            error!("Multiple binary targets were found in the Cargo manifest, but the buildpack binary could not be determined.");
+            error!("Hint: Pass --bin <name> or set package.metadata.libcnb.main-binary in the Cargo manifest.");
+        }
+        BuildError::BinaryTargetNotFound(binary_name) => {
+            error!(
+                "Binary target {} was not found in the Cargo manifest.",
+                binary_name
+            );
+        }
+        BuildError::BinaryNotBuilt(binary_name) => {
+            error!(
+                "Cargo did not report an executable for binary target {}.",
+                binary_name
+            );
+        }
+        BuildError::InvalidMainBinaryMetadata => {
+            error!("The package.metadata.libcnb.main-binary setting in the Cargo manifest must be a string.");
+        }
+        BuildError::MetadataError(metadata_error) => {
+            error!("Unable to obtain metadata from Cargo: {}", metadata_error);
+        }
+        BuildError::CouldNotFindRootPackage => {
+            error!("Root package could not be determined from the Cargo manifest.");
+        }
+    }
+
+    std::process::exit(1);
 }
 
 fn setup_logging() {
@@ -0,0 +1,304 @@
+use crate::{read_buildpack_data, BuildpackData, BuildpackDataError};
+use cargo_metadata::Metadata;
+use libcnb_data::buildpack::{
+    BuildpackDescriptor, BuildpackId, BuildpackVersion, MetaBuildpackDescriptor,
+};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A single buildpack that is a member of the Cargo workspace.
+pub struct WorkspaceBuildpack {
+    pub project_path: PathBuf,
+    pub buildpack_data: BuildpackData<Option<toml::Value>>,
+}
+
+/// Finds all single buildpacks in the Cargo workspace described by the given metadata.
+///
+/// A workspace member is considered a buildpack if its package directory contains a
+/// `buildpack.toml` that describes a single buildpack. Meta-buildpacks are ignored.
+///
+/// # Errors
+///
+/// Will return `Err` if the buildpack data of a workspace member could not be read.
+pub fn find_workspace_buildpacks(
+    cargo_metadata: &Metadata,
+) -> Result<Vec<WorkspaceBuildpack>, MetaBuildpackError> {
+    let mut workspace_buildpacks = vec![];
+
+    let workspace_packages = cargo_metadata
+        .packages
+        .iter()
+        .filter(|package| cargo_metadata.workspace_members.contains(&package.id));
+
+    for package in workspace_packages {
+        let project_path = match package.manifest_path.parent() {
+            Some(project_path) => project_path.as_std_path().to_path_buf(),
+            None => continue,
+        };
+
+        if !project_path.join("buildpack.toml").exists() {
+            continue;
+        }
+
+        let buildpack_data = read_buildpack_data(&project_path).map_err(|error| {
+            MetaBuildpackError::CannotReadWorkspaceBuildpack(project_path.clone(), error)
+        })?;
+
+        if let BuildpackDescriptor::Single(_) = buildpack_data.buildpack_descriptor {
+            workspace_buildpacks.push(WorkspaceBuildpack {
+                project_path,
+                buildpack_data,
+            });
+        }
+    }
+
+    Ok(workspace_buildpacks)
+}
+
+/// Resolves the buildpacks referenced in the order of a meta-buildpack to buildpacks of the
+/// Cargo workspace.
+///
+/// Each buildpack is only returned once, in the order it is first referenced.
+///
+/// # Errors
+///
+/// Will return `Err` if a referenced buildpack is not part of the workspace or if the version of
+/// the workspace buildpack does not match the referenced version.
+pub fn resolve_meta_buildpack_dependencies<'a, BM>(
+    buildpack_descriptor: &MetaBuildpackDescriptor<BM>,
+    workspace_buildpacks: &'a [WorkspaceBuildpack],
+) -> Result<Vec<&'a WorkspaceBuildpack>, MetaBuildpackError> {
+    let mut dependencies: Vec<&WorkspaceBuildpack> = vec![];
+
+    for group in buildpack_descriptor
+        .order
+        .iter()
+        .flat_map(|order| order.group.iter())
+    {
+        let workspace_buildpack = workspace_buildpacks
+            .iter()
+            .find(|workspace_buildpack| {
+                workspace_buildpack
+                    .buildpack_data
+                    .buildpack_descriptor
+                    .buildpack()
+                    .id
+                    == group.id
+            })
+            .ok_or_else(|| {
+                MetaBuildpackError::BuildpackNotFound(group.id.clone(), group.version.clone())
+            })?;
+
+        let workspace_version = &workspace_buildpack
+            .buildpack_data
+            .buildpack_descriptor
+            .buildpack()
+            .version;
+
+        if workspace_version != &group.version {
+            return Err(MetaBuildpackError::BuildpackVersionMismatch {
+                id: group.id.clone(),
+                version: group.version.clone(),
+                workspace_version: workspace_version.clone(),
+            });
+        }
+
+        if !dependencies
+            .iter()
+            .any(|dependency| std::ptr::eq(*dependency, workspace_buildpack))
+        {
+            dependencies.push(workspace_buildpack);
+        }
+    }
+
+    Ok(dependencies)
+}
+
+#[derive(Debug)]
+pub enum MetaBuildpackError {
+    CannotReadWorkspaceBuildpack(PathBuf, BuildpackDataError),
+    BuildpackNotFound(BuildpackId, BuildpackVersion),
+    BuildpackVersionMismatch {
+        id: BuildpackId,
+        version: BuildpackVersion,
+        workspace_version: BuildpackVersion,
+    },
+}
+
+/// Creates a meta-buildpack directory with a `package.toml` that references the given, already
+/// packaged, buildpack directories.
+///
+/// The resulting `package.toml` can be used with `pack buildpack package` to create a buildpackage
+/// that contains the meta-buildpack and all of its dependencies. Dependencies are referenced by
+/// their path relative to the destination path.
+///
+/// # Errors
+///
+/// Will return `Err` if the buildpack directory already exists or could not be assembled.
+pub fn assemble_meta_buildpack_directory(
+    destination_path: impl AsRef<Path>,
+    buildpack_descriptor_path: impl AsRef<Path>,
+    dependency_paths: &[PathBuf],
+) -> io::Result<()> {
+    if destination_path.as_ref().exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "Destination path already exists!",
+        ));
+    }
+
+    fs::create_dir_all(destination_path.as_ref())?;
+
+    fs::copy(
+        buildpack_descriptor_path.as_ref(),
+        destination_path.as_ref().join("buildpack.toml"),
+    )?;
+
+    let package_descriptor = PackageDescriptor {
+        buildpack: PackageDescriptorBuildpack {
+            uri: String::from("."),
+        },
+        dependencies: dependency_paths
+            .iter()
+            .map(|dependency_path| PackageDescriptorDependency {
+                uri: pathdiff::diff_paths(dependency_path, destination_path.as_ref())
+                    .unwrap_or_else(|| dependency_path.clone())
+                    .to_string_lossy()
+                    .to_string(),
+            })
+            .collect(),
+    };
+
+    let package_descriptor_contents = toml::to_string(&package_descriptor)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    fs::write(
+        destination_path.as_ref().join("package.toml"),
+        package_descriptor_contents,
+    )
+}
+
+/// The `package.toml` format used by `pack buildpack package`.
+#[derive(Serialize)]
+struct PackageDescriptor {
+    buildpack: PackageDescriptorBuildpack,
+    dependencies: Vec<PackageDescriptorDependency>,
+}
+
+#[derive(Serialize)]
+struct PackageDescriptorBuildpack {
+    uri: String,
+}
+
+#[derive(Serialize)]
+struct PackageDescriptorDependency {
+    uri: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::buildpack_descriptor_toml;
+
+    fn workspace_buildpack(id: &str, version: &str) -> WorkspaceBuildpack {
+        let buildpack_descriptor =
+            toml::from_str(&buildpack_descriptor_toml(id, version, "*")).unwrap();
+
+        WorkspaceBuildpack {
+            project_path: PathBuf::from(id),
+            buildpack_data: BuildpackData {
+                buildpack_descriptor_path: PathBuf::from(id).join("buildpack.toml"),
+                buildpack_descriptor,
+            },
+        }
+    }
+
+    fn meta_buildpack_descriptor() -> MetaBuildpackDescriptor<Option<toml::Value>> {
+        toml::from_str(
+            r#"
+api = "0.6"
+
+[buildpack]
+id = "heroku/java"
+version = "0.1.0"
+
+[[order]]
+
+[[order.group]]
+id = "heroku/jvm"
+version = "0.1.0"
+
+[[order.group]]
+id = "heroku/maven"
+version = "0.2.0"
+
+[[order]]
+
+[[order.group]]
+id = "heroku/jvm"
+version = "0.1.0"
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn resolves_dependencies_once() {
+        let workspace_buildpacks = vec![
+            workspace_buildpack("heroku/maven", "0.2.0"),
+            workspace_buildpack("heroku/jvm", "0.1.0"),
+            workspace_buildpack("heroku/ruby", "0.1.0"),
+        ];
+
+        let dependencies = resolve_meta_buildpack_dependencies(
+            &meta_buildpack_descriptor(),
+            &workspace_buildpacks,
+        )
+        .unwrap();
+
+        let project_paths: Vec<&PathBuf> = dependencies
+            .iter()
+            .map(|dependency| &dependency.project_path)
+            .collect();
+
+        assert_eq!(
+            project_paths,
+            vec![&PathBuf::from("heroku/jvm"), &PathBuf::from("heroku/maven")]
+        );
+    }
+
+    #[test]
+    fn fails_on_version_mismatch() {
+        let workspace_buildpacks = vec![
+            workspace_buildpack("heroku/jvm", "0.1.0"),
+            workspace_buildpack("heroku/maven", "0.1.0"),
+        ];
+
+        let result = resolve_meta_buildpack_dependencies(
+            &meta_buildpack_descriptor(),
+            &workspace_buildpacks,
+        );
+
+        assert!(matches!(
+            result,
+            Err(MetaBuildpackError::BuildpackVersionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn fails_on_missing_buildpack() {
+        let workspace_buildpacks = vec![workspace_buildpack("heroku/jvm", "0.1.0")];
+
+        let result = resolve_meta_buildpack_dependencies(
+            &meta_buildpack_descriptor(),
+            &workspace_buildpacks,
+        );
+
+        assert!(matches!(
+            result,
+            Err(MetaBuildpackError::BuildpackNotFound(id, _)) if id.as_str() == "heroku/maven"
+        ));
+    }
+}
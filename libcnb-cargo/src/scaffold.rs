@@ -0,0 +1,324 @@
+use libcnb::internals::LIBCNB_VERSION;
+use libcnb_data::buildpack::{BuildpackId, BuildpackIdError, BuildpackVersion};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The kind of project [`scaffold_buildpack_project`] generates.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ProjectTemplate {
+    /// A Cargo project for a single buildpack.
+    Single,
+    /// A Cargo workspace with a meta-buildpack and a single buildpack it references.
+    Meta,
+}
+
+/// Generates a new buildpack project at the given path.
+///
+/// The generated project contains a `buildpack.toml`, a [`Buildpack`] implementation with a
+/// sample [`Layer`] and a test that runs the buildpack with libcnb's `TestRunner`. It depends on
+/// the version of libcnb this crate was built with. The name of the Cargo package is derived from the last
+/// component of the given path.
+///
+/// For meta-buildpacks, a Cargo workspace is generated. The meta-buildpack descriptor is placed at
+/// the workspace root and references a single buildpack in `buildpacks/base` with the ID
+/// `<buildpack_id>-base`.
+///
+/// [`Buildpack`]: https://docs.rs/libcnb/latest/libcnb/trait.Buildpack.html
+/// [`Layer`]: https://docs.rs/libcnb/latest/libcnb/layer/trait.Layer.html
+///
+/// # Errors
+///
+/// Will return `Err` if the destination path already exists, no valid package name can be derived
+/// from the path or the project could not be written.
+pub fn scaffold_buildpack_project(
+    destination_path: impl AsRef<Path>,
+    buildpack_id: &BuildpackId,
+    buildpack_version: &BuildpackVersion,
+    template: ProjectTemplate,
+) -> Result<(), ScaffoldError> {
+    let destination_path = destination_path.as_ref();
+
+    if destination_path.exists() {
+        return Err(ScaffoldError::DestinationExists(
+            destination_path.to_path_buf(),
+        ));
+    }
+
+    let package_name = destination_path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .filter(|package_name| is_valid_package_name(package_name))
+        .ok_or_else(|| ScaffoldError::InvalidPackageName(destination_path.to_path_buf()))?;
+
+    match template {
+        ProjectTemplate::Single => write_single_buildpack_project(
+            destination_path,
+            &package_name,
+            buildpack_id,
+            buildpack_version,
+        )?,
+        ProjectTemplate::Meta => {
+            let dependency_buildpack_id = format!("{}-base", buildpack_id)
+                .parse::<BuildpackId>()
+                .map_err(ScaffoldError::InvalidBuildpackId)?;
+
+            write_single_buildpack_project(
+                &destination_path.join("buildpacks").join("base"),
+                &format!("{}-base", package_name),
+                &dependency_buildpack_id,
+                buildpack_version,
+            )?;
+
+            let replacements = [
+                ("buildpack_id", buildpack_id.as_str()),
+                ("buildpack_version", &buildpack_version.to_string()),
+                ("dependency_buildpack_id", dependency_buildpack_id.as_str()),
+                ("dependency_directory_name", "base"),
+            ]
+            .iter()
+            .map(|(key, value)| (*key, String::from(*value)))
+            .collect::<Vec<_>>();
+
+            write_template(
+                &destination_path.join("Cargo.toml"),
+                include_str!("templates/workspace_Cargo.toml.tpl"),
+                &replacements,
+            )?;
+            write_template(
+                &destination_path.join("buildpack.toml"),
+                include_str!("templates/meta_buildpack.toml.tpl"),
+                &replacements,
+            )?;
+            write_template(
+                &destination_path.join(".gitignore"),
+                include_str!("templates/gitignore.tpl"),
+                &replacements,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum ScaffoldError {
+    IoError(io::Error),
+    DestinationExists(PathBuf),
+    InvalidPackageName(PathBuf),
+    InvalidBuildpackId(BuildpackIdError),
+}
+
+impl From<io::Error> for ScaffoldError {
+    fn from(error: io::Error) -> Self {
+        ScaffoldError::IoError(error)
+    }
+}
+
+fn write_single_buildpack_project(
+    destination_path: &Path,
+    package_name: &str,
+    buildpack_id: &BuildpackId,
+    buildpack_version: &BuildpackVersion,
+) -> io::Result<()> {
+    let replacements = vec![
+        ("package_name", String::from(package_name)),
+        ("buildpack_type", buildpack_type_name(package_name)),
+        ("buildpack_id", buildpack_id.to_string()),
+        ("buildpack_version", buildpack_version.to_string()),
+        ("libcnb_version", String::from(LIBCNB_VERSION)),
+    ];
+
+    let files = [
+        ("Cargo.toml", include_str!("templates/Cargo.toml.tpl")),
+        (
+            "buildpack.toml",
+            include_str!("templates/buildpack.toml.tpl"),
+        ),
+        (".gitignore", include_str!("templates/gitignore.tpl")),
+        ("src/main.rs", include_str!("templates/main.rs.tpl")),
+        ("src/layer.rs", include_str!("templates/layer.rs.tpl")),
+    ];
+
+    for (file_path, template) in files {
+        write_template(&destination_path.join(file_path), template, &replacements)?;
+    }
+
+    Ok(())
+}
+
+fn write_template(path: &Path, template: &str, replacements: &[(&str, String)]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let contents = replacements
+        .iter()
+        .fold(String::from(template), |contents, (key, value)| {
+            contents.replace(&format!("{{{{{}}}}}", key), value)
+        });
+
+    fs::write(path, contents)
+}
+
+/// Cargo package names are more permissive, but names that can also be turned into Rust type
+/// names keep the templates simple.
+fn is_valid_package_name(package_name: &str) -> bool {
+    package_name
+        .chars()
+        .next()
+        .map_or(false, |first_char| first_char.is_ascii_alphabetic())
+        && package_name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+}
+
+/// Converts a package name such as `my-ruby` to a buildpack type name such as `MyRubyBuildpack`.
+fn buildpack_type_name(package_name: &str) -> String {
+    let mut type_name: String = package_name
+        .split(|char| char == '-' || char == '_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or_else(String::new, |first_char| {
+                first_char.to_ascii_uppercase().to_string() + chars.as_str()
+            })
+        })
+        .collect();
+
+    if !type_name.ends_with("Buildpack") {
+        type_name.push_str("Buildpack");
+    }
+
+    type_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libcnb_data::buildpack::{MetaBuildpackDescriptor, SingleBuildpackDescriptor};
+
+    #[test]
+    fn buildpack_type_names() {
+        assert_eq!(buildpack_type_name("ruby"), "RubyBuildpack");
+        assert_eq!(buildpack_type_name("my-ruby_app"), "MyRubyAppBuildpack");
+        assert_eq!(buildpack_type_name("my-buildpack"), "MyBuildpack");
+    }
+
+    #[test]
+    fn package_name_validation() {
+        assert!(is_valid_package_name("my-buildpack_2"));
+        assert!(!is_valid_package_name("2-buildpack"));
+        assert!(!is_valid_package_name("my.buildpack"));
+    }
+
+    #[test]
+    fn scaffolds_single_buildpack() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project_path = temp_dir.path().join("my-buildpack");
+
+        scaffold_buildpack_project(
+            &project_path,
+            &"acme/my-buildpack".parse().unwrap(),
+            &BuildpackVersion::new(0, 1, 0),
+            ProjectTemplate::Single,
+        )
+        .unwrap();
+
+        let buildpack_descriptor: SingleBuildpackDescriptor<Option<toml::Value>> =
+            toml::from_str(&fs::read_to_string(project_path.join("buildpack.toml")).unwrap())
+                .unwrap();
+        assert_eq!(
+            buildpack_descriptor.buildpack.id.as_str(),
+            "acme/my-buildpack"
+        );
+
+        let cargo_toml: toml::Value =
+            toml::from_str(&fs::read_to_string(project_path.join("Cargo.toml")).unwrap()).unwrap();
+        assert_eq!(cargo_toml["package"]["name"].as_str(), Some("my-buildpack"));
+
+        let main_rs = fs::read_to_string(project_path.join("src/main.rs")).unwrap();
+        assert!(main_rs.contains("buildpack_main!(MyBuildpack);"));
+        assert!(!main_rs.contains("{{"));
+        assert!(project_path.join("src/layer.rs").exists());
+    }
+
+    #[test]
+    fn scaffolded_buildpack_passes_its_tests() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project_path = temp_dir.path().join("my-buildpack");
+
+        scaffold_buildpack_project(
+            &project_path,
+            &"acme/my-buildpack".parse().unwrap(),
+            &BuildpackVersion::new(0, 1, 0),
+            ProjectTemplate::Single,
+        )
+        .unwrap();
+
+        // Builds against the libcnb of this workspace, so changes to libcnb and the templates can
+        // be tested together and no network access is required.
+        let libcnb_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../libcnb");
+        let cargo_toml = format!(
+            "{}\n[patch.crates-io]\nlibcnb = {{ path = {} }}\n",
+            fs::read_to_string(project_path.join("Cargo.toml")).unwrap(),
+            toml::Value::from(libcnb_path.to_string_lossy().to_string())
+        );
+        fs::write(project_path.join("Cargo.toml"), cargo_toml).unwrap();
+
+        let output = std::process::Command::new("cargo")
+            .args(["test", "--offline", "--quiet"])
+            .env("CARGO_TARGET_DIR", temp_dir.path().join("target"))
+            .current_dir(&project_path)
+            .output()
+            .unwrap();
+
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    #[test]
+    fn scaffolds_meta_buildpack() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project_path = temp_dir.path().join("java");
+
+        scaffold_buildpack_project(
+            &project_path,
+            &"acme/java".parse().unwrap(),
+            &BuildpackVersion::new(1, 0, 0),
+            ProjectTemplate::Meta,
+        )
+        .unwrap();
+
+        let meta_buildpack_descriptor: MetaBuildpackDescriptor<Option<toml::Value>> =
+            toml::from_str(&fs::read_to_string(project_path.join("buildpack.toml")).unwrap())
+                .unwrap();
+        assert_eq!(
+            meta_buildpack_descriptor.order[0].group[0].id.as_str(),
+            "acme/java-base"
+        );
+
+        let buildpack_descriptor: SingleBuildpackDescriptor<Option<toml::Value>> = toml::from_str(
+            &fs::read_to_string(project_path.join("buildpacks/base/buildpack.toml")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(buildpack_descriptor.buildpack.id.as_str(), "acme/java-base");
+    }
+
+    #[test]
+    fn fails_on_existing_destination() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let result = scaffold_buildpack_project(
+            temp_dir.path(),
+            &"acme/my-buildpack".parse().unwrap(),
+            &BuildpackVersion::new(0, 1, 0),
+            ProjectTemplate::Single,
+        );
+
+        assert!(matches!(result, Err(ScaffoldError::DestinationExists(_))));
+    }
+}
@@ -0,0 +1,11 @@
+[package]
+name = "{{package_name}}"
+version = "{{buildpack_version}}"
+edition = "2021"
+rust-version = "1.56"
+
+[dependencies]
+libcnb = "{{libcnb_version}}"
+
+[dev-dependencies]
+libcnb = { version = "{{libcnb_version}}", features = ["testing"] }
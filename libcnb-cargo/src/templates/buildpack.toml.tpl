@@ -0,0 +1,8 @@
+api = "0.6"
+
+[buildpack]
+id = "{{buildpack_id}}"
+version = "{{buildpack_version}}"
+
+[[stacks]]
+id = "*"
@@ -0,0 +1 @@
+/target
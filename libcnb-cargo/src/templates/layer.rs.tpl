@@ -0,0 +1,30 @@
+use crate::{{buildpack_type}};
+use libcnb::build::BuildContext;
+use libcnb::data::layer_content_metadata::LayerTypes;
+use libcnb::generic::{GenericError, GenericMetadata};
+use libcnb::layer::{Layer, LayerResult, LayerResultBuilder};
+use std::path::Path;
+
+pub struct HelloLayer;
+
+impl Layer for HelloLayer {
+    type Buildpack = {{buildpack_type}};
+    type Metadata = GenericMetadata;
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            build: false,
+            launch: true,
+            cache: false,
+        }
+    }
+
+    fn create(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_path: &Path,
+    ) -> Result<LayerResult<Self::Metadata>, GenericError> {
+        println!("---> Creating layer at {}", layer_path.display());
+        LayerResultBuilder::new(GenericMetadata::default()).build()
+    }
+}
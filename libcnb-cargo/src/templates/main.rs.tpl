@@ -0,0 +1,47 @@
+mod layer;
+
+use crate::layer::HelloLayer;
+use libcnb::build::{BuildContext, BuildResult, BuildResultBuilder};
+use libcnb::data::layer_name;
+use libcnb::detect::{DetectContext, DetectResult, DetectResultBuilder};
+use libcnb::generic::{GenericError, GenericMetadata, GenericPlatform};
+use libcnb::{buildpack_main, Buildpack};
+
+pub struct {{buildpack_type}};
+
+impl Buildpack for {{buildpack_type}} {
+    type Platform = GenericPlatform;
+    type Metadata = GenericMetadata;
+    type Error = GenericError;
+
+    fn detect(&self, _context: DetectContext<Self>) -> libcnb::Result<DetectResult, Self::Error> {
+        DetectResultBuilder::pass().build()
+    }
+
+    fn build(&self, context: BuildContext<Self>) -> libcnb::Result<BuildResult, Self::Error> {
+        context.handle_layer(layer_name!("hello"), HelloLayer)?;
+        BuildResultBuilder::new().build()
+    }
+}
+
+buildpack_main!({{buildpack_type}});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libcnb::testing::{DetectOutcome, TestRunner};
+
+    #[test]
+    fn creates_hello_layer() {
+        let test_runner = TestRunner::new().unwrap();
+
+        assert!(matches!(
+            test_runner.detect(&{{buildpack_type}}).unwrap(),
+            DetectOutcome::Pass(_)
+        ));
+
+        let build_outcome = test_runner.build(&{{buildpack_type}}).unwrap();
+        let hello_layer = build_outcome.layer(&layer_name!("hello")).unwrap();
+        assert!(hello_layer.path.is_dir());
+    }
+}
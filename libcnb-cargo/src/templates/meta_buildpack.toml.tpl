@@ -0,0 +1,11 @@
+api = "0.6"
+
+[buildpack]
+id = "{{buildpack_id}}"
+version = "{{buildpack_version}}"
+
+[[order]]
+
+[[order.group]]
+id = "{{dependency_buildpack_id}}"
+version = "{{buildpack_version}}"
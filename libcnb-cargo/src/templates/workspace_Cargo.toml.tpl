@@ -0,0 +1,2 @@
+[workspace]
+members = ["buildpacks/{{dependency_directory_name}}"]
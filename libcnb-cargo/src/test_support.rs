@@ -0,0 +1,33 @@
+//! Fixtures shared by the tests of this crate.
+
+use std::fs;
+use std::path::Path;
+
+/// Returns the contents of a minimal single buildpack descriptor.
+pub(crate) fn buildpack_descriptor_toml(id: &str, version: &str, stack_id: &str) -> String {
+    format!(
+        r#"
+api = "0.6"
+
+[buildpack]
+id = "{}"
+version = "{}"
+
+[[stacks]]
+id = "{}"
+"#,
+        id, version, stack_id
+    )
+}
+
+/// Writes a minimal buildpack with the given id to the given directory. The buildpack consists of
+/// a `buildpack.toml` and a `bin/build` script.
+pub(crate) fn write_buildpack(path: &Path, id: &str, stack_id: &str) {
+    fs::create_dir_all(path.join("bin")).unwrap();
+    fs::write(
+        path.join("buildpack.toml"),
+        buildpack_descriptor_toml(id, "0.1.0", stack_id),
+    )
+    .unwrap();
+    fs::write(path.join("bin/build"), "#!/usr/bin/env bash").unwrap();
+}
@@ -1,8 +1,94 @@
 use crate::bom;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Debug)]
+/// Data Structure for the build.toml file.
+///
+/// # Examples
+/// ```
+/// use libcnb_data::build::{Build, Unmet};
+///
+/// let mut build_toml = Build::new();
+/// build_toml.unmet.push(Unmet {
+///     name: String::from("ruby"),
+/// });
+///
+/// assert!(toml::to_string(&build_toml).is_ok());
+/// ```
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Build {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub bom: bom::Bom,
-    pub unmet: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unmet: Vec<Unmet>,
+}
+
+impl Build {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            bom: bom::Bom::new(),
+            unmet: Vec::new(),
+        }
+    }
+}
+
+/// A buildpack plan entry that the buildpack did not satisfy.
+///
+/// Unmet entries are passed on to subsequent buildpacks in the same group that provide
+/// an entry with the same name.
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Unmet {
+    pub name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_empty_build() {
+        assert_eq!(toml::to_string(&Build::new()).unwrap(), "");
+    }
+
+    #[test]
+    fn serialize_unmet_entries() {
+        let mut build = Build::new();
+        build.unmet.push(Unmet {
+            name: String::from("ruby"),
+        });
+        build.unmet.push(Unmet {
+            name: String::from("bundler"),
+        });
+
+        assert_eq!(
+            toml::to_string(&build).unwrap(),
+            "[[unmet]]\nname = \"ruby\"\n\n[[unmet]]\nname = \"bundler\"\n"
+        );
+    }
+
+    #[test]
+    fn deserialize_build_with_bom() {
+        let toml_str = r#"
+[[bom]]
+name = "ruby"
+
+[bom.metadata]
+version = "3.0.2"
+
+[[unmet]]
+name = "node"
+"#;
+
+        let build: Build = toml::from_str(toml_str).unwrap();
+        assert_eq!(build.bom.len(), 1);
+        assert_eq!(build.bom[0].name, "ruby");
+        assert_eq!(
+            build.unmet,
+            vec![Unmet {
+                name: String::from("node")
+            }]
+        );
+    }
 }
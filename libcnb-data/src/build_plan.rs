@@ -1,15 +1,16 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use toml::value::Table;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct BuildPlan {
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub provides: Vec<Provide>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub requires: Vec<Require>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    or: Vec<Or>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub or: Vec<Or>,
 }
 
 impl BuildPlan {
@@ -45,11 +46,13 @@ impl BuildPlanBuilder {
         }
     }
 
+    #[must_use]
     pub fn provides(mut self, name: impl AsRef<str>) -> Self {
         self.current_provides.push(Provide::new(name.as_ref()));
         self
     }
 
+    #[must_use]
     pub fn requires(mut self, name: impl AsRef<str>) -> Self {
         self.current_requires.push(Require::new(name.as_ref()));
         self
@@ -94,17 +97,19 @@ impl Default for BuildPlanBuilder {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Or {
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    provides: Vec<Provide>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    requires: Vec<Require>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provides: Vec<Provide>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<Require>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Provide {
-    name: String,
+    pub name: String,
 }
 
 impl Provide {
@@ -113,9 +118,11 @@ impl Provide {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Require {
     pub name: String,
+    #[serde(default)]
     pub metadata: Table,
 }
 
@@ -140,4 +147,21 @@ mod tests {
 
         assert!(toml::to_string(&build_plan).is_ok());
     }
+
+    #[test]
+    fn it_reads_build_plan_with_alternatives() {
+        let build_plan = BuildPlanBuilder::new()
+            .provides("rust")
+            .requires("rust")
+            .or()
+            .provides("cargo")
+            .build();
+
+        let toml_string = toml::to_string(&build_plan).unwrap();
+        let read_build_plan: BuildPlan = toml::from_str(&toml_string).unwrap();
+
+        assert_eq!(read_build_plan, build_plan);
+        assert_eq!(read_build_plan.or.len(), 1);
+        assert_eq!(read_build_plan.or[0].provides, vec![Provide::new("cargo")]);
+    }
 }
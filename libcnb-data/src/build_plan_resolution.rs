@@ -0,0 +1,644 @@
+//! Resolution of build plans of a buildpack group, as performed by the CNB lifecycle.
+//!
+//! After all buildpacks of a group ran their detect phase, the lifecycle resolves their build
+//! plans into the buildpack plans each buildpack receives during the build phase. This module
+//! implements the same algorithm, which allows checking build plans of multiple buildpacks
+//! against each other without running the lifecycle.
+//!
+//! # Example:
+//! ```
+//! use libcnb_data::build_plan::BuildPlanBuilder;
+//! use libcnb_data::build_plan_resolution::{resolve, DetectedBuildpack};
+//! use libcnb_data::buildpack_id;
+//!
+//! let resolution = resolve(&[
+//!     DetectedBuildpack::pass(
+//!         buildpack_id!("heroku/jvm"),
+//!         false,
+//!         BuildPlanBuilder::new().provides("jdk").build(),
+//!     ),
+//!     DetectedBuildpack::pass(
+//!         buildpack_id!("heroku/maven"),
+//!         false,
+//!         BuildPlanBuilder::new().requires("jdk").build(),
+//!     ),
+//! ])
+//! .unwrap();
+//!
+//! let jvm_plan = resolution.buildpack_plan(&buildpack_id!("heroku/jvm")).unwrap();
+//! assert_eq!(jvm_plan.entries[0].name, "jdk");
+//! ```
+use crate::build_plan::{BuildPlan, Provide, Require};
+use crate::buildpack::{BuildpackId, Group};
+use crate::buildpack_plan::{BuildpackPlan, Entry};
+use std::fmt::{self, Display, Formatter};
+
+/// A buildpack of a group, together with the outcome of its detect phase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedBuildpack {
+    pub id: BuildpackId,
+    pub optional: bool,
+    pub detection: Detection,
+}
+
+impl DetectedBuildpack {
+    /// A buildpack that passed detection with the given build plan.
+    ///
+    /// Use an empty [`BuildPlan`] for buildpacks that pass detection without a build plan.
+    #[must_use]
+    pub fn pass(id: BuildpackId, optional: bool, build_plan: BuildPlan) -> Self {
+        Self {
+            id,
+            optional,
+            detection: Detection::Pass(build_plan),
+        }
+    }
+
+    /// A buildpack that failed detection.
+    #[must_use]
+    pub fn fail(id: BuildpackId, optional: bool) -> Self {
+        Self {
+            id,
+            optional,
+            detection: Detection::Fail,
+        }
+    }
+
+    /// A buildpack of an order group that passed detection with the given build plan.
+    #[must_use]
+    pub fn pass_group(group: &Group, build_plan: BuildPlan) -> Self {
+        Self::pass(group.id.clone(), group.optional, build_plan)
+    }
+
+    /// A buildpack of an order group that failed detection.
+    #[must_use]
+    pub fn fail_group(group: &Group) -> Self {
+        Self::fail(group.id.clone(), group.optional)
+    }
+}
+
+/// The outcome of the detect phase of a buildpack.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Detection {
+    Pass(BuildPlan),
+    Fail,
+}
+
+/// The result of a successful build plan resolution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolution {
+    /// The buildpacks that participate in the build, in group order.
+    pub buildpacks: Vec<BuildpackId>,
+    /// The resolved build plan entries, in the order they were first required.
+    pub entries: Vec<ResolvedEntry>,
+}
+
+/// A build plan entry that is required by at least one buildpack and provided by at least one
+/// buildpack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedEntry {
+    pub name: String,
+    /// The buildpacks that provide this entry, in group order.
+    pub providers: Vec<BuildpackId>,
+    /// All requirements for this entry, in group order.
+    pub requires: Vec<Require>,
+}
+
+impl Resolution {
+    /// Returns the buildpack plan the given buildpack receives during the build phase.
+    ///
+    /// During the build phase, the lifecycle passes each entry only to its first provider. Later
+    /// providers only receive an entry if earlier providers mark it as unmet. This method assumes
+    /// that all buildpacks meet all of their entries. Use [`entries`](Self::entries) to simulate
+    /// other cases.
+    ///
+    /// Returns `None` if the given buildpack does not participate in the build.
+    #[must_use]
+    pub fn buildpack_plan(&self, id: &BuildpackId) -> Option<BuildpackPlan> {
+        if !self.buildpacks.contains(id) {
+            return None;
+        }
+
+        let entries = self
+            .entries
+            .iter()
+            .filter(|entry| entry.providers.first() == Some(id))
+            .flat_map(|entry| entry.requires.iter())
+            .map(|require| Entry {
+                name: require.name.clone(),
+                metadata: require.metadata.clone(),
+            })
+            .collect();
+
+        Some(BuildpackPlan { entries })
+    }
+
+    /// Returns the buildpack plans of all participating buildpacks, in group order.
+    ///
+    /// See [`buildpack_plan`](Self::buildpack_plan) for details.
+    #[must_use]
+    pub fn buildpack_plans(&self) -> Vec<(BuildpackId, BuildpackPlan)> {
+        self.buildpacks
+            .iter()
+            .filter_map(|id| {
+                self.buildpack_plan(id)
+                    .map(|buildpack_plan| (id.clone(), buildpack_plan))
+            })
+            .collect()
+    }
+}
+
+/// Resolves the build plans of the given buildpack group.
+///
+/// Implements the resolution algorithm of the CNB lifecycle:
+///
+/// * Buildpacks that failed detection are removed from the group. If a non-optional buildpack
+///   failed detection, the whole group fails.
+/// * Each combination of the build plan alternatives (`or`) of the buildpacks is tried in order,
+///   until one resolves.
+/// * Within a combination, every require must be provided by the same or an earlier buildpack and
+///   every provide must be required by the same or a later buildpack. Optional buildpacks that
+///   violate this are removed from the combination. Only the last alternative of an optional
+///   buildpack is considered optional.
+///
+/// # Errors
+/// Returns an error that describes why the group cannot be resolved. If no combination of
+/// alternatives resolves, the error describes why the last one failed.
+pub fn resolve(group: &[DetectedBuildpack]) -> Result<Resolution, ResolveError> {
+    let mut passed = vec![];
+    for buildpack in group {
+        match &buildpack.detection {
+            Detection::Pass(build_plan) => passed.push((buildpack, build_plan)),
+            Detection::Fail if buildpack.optional => {}
+            Detection::Fail => {
+                return Err(ResolveError::RequiredBuildpackFailedDetection(
+                    buildpack.id.clone(),
+                ));
+            }
+        }
+    }
+
+    let options: Vec<Vec<TrialOption>> = passed
+        .iter()
+        .map(|(buildpack, build_plan)| trial_options(buildpack, build_plan))
+        .collect();
+
+    let mut trials = 0;
+    let mut indices = vec![0; options.len()];
+
+    loop {
+        trials += 1;
+
+        let trial: Vec<TrialOption> = indices
+            .iter()
+            .zip(&options)
+            .map(|(index, buildpack_options)| buildpack_options[*index].clone())
+            .collect();
+
+        let failure = match run_trial(trial) {
+            Ok(resolution) => return Ok(resolution),
+            Err(failure) => failure,
+        };
+
+        // Advance to the next combination, varying the options of the last buildpack first.
+        let next = (0..indices.len())
+            .rev()
+            .find(|&position| indices[position] + 1 < options[position].len());
+
+        match next {
+            Some(position) => {
+                indices[position] += 1;
+                for index in &mut indices[position + 1..] {
+                    *index = 0;
+                }
+            }
+            None => {
+                return Err(ResolveError::Unresolvable {
+                    trials,
+                    last_failure: failure,
+                })
+            }
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum ResolveError {
+    #[error("Buildpack {0} failed detection, but is not optional")]
+    RequiredBuildpackFailedDetection(BuildpackId),
+
+    #[error("Build plan cannot be resolved, tried {trials} combination(s) of alternatives. Last failure: {last_failure}")]
+    Unresolvable {
+        trials: usize,
+        last_failure: TrialFailure,
+    },
+}
+
+/// Describes why a combination of build plan alternatives does not resolve.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum TrialFailure {
+    /// A non-optional buildpack requires an entry that no earlier buildpack provides.
+    UnprovidedRequire {
+        buildpack: BuildpackId,
+        name: String,
+    },
+    /// A non-optional buildpack provides an entry that no later buildpack requires.
+    UnrequiredProvide {
+        buildpack: BuildpackId,
+        name: String,
+    },
+    /// No buildpack remained after removing optional buildpacks.
+    NoViableBuildpacks,
+}
+
+impl Display for TrialFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TrialFailure::UnprovidedRequire { buildpack, name } => write!(
+                f,
+                "{} requires `{}`, but no buildpack before it provides it",
+                buildpack, name
+            ),
+            TrialFailure::UnrequiredProvide { buildpack, name } => write!(
+                f,
+                "{} provides `{}`, but no buildpack after it requires it",
+                buildpack, name
+            ),
+            TrialFailure::NoViableBuildpacks => write!(f, "no viable buildpacks in group"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TrialOption<'a> {
+    id: &'a BuildpackId,
+    optional: bool,
+    provides: &'a [Provide],
+    requires: &'a [Require],
+}
+
+fn trial_options<'a>(
+    buildpack: &'a DetectedBuildpack,
+    build_plan: &'a BuildPlan,
+) -> Vec<TrialOption<'a>> {
+    let alternatives = std::iter::once((&build_plan.provides, &build_plan.requires)).chain(
+        build_plan
+            .or
+            .iter()
+            .map(|alternative| (&alternative.provides, &alternative.requires)),
+    );
+
+    alternatives
+        .enumerate()
+        .map(|(index, (provides, requires))| TrialOption {
+            id: &buildpack.id,
+            optional: buildpack.optional && index == build_plan.or.len(),
+            provides,
+            requires,
+        })
+        .collect()
+}
+
+fn run_trial(mut trial: Vec<TrialOption>) -> Result<Resolution, TrialFailure> {
+    loop {
+        let dependencies = Dependencies::new(&trial);
+        let mut retry = false;
+
+        for (name, id, failure) in dependencies.unmet() {
+            retry = true;
+
+            // A buildpack can have multiple unmet dependencies and might already be removed.
+            if let Some(position) = trial.iter().position(|option| option.id == id) {
+                if trial[position].optional {
+                    trial.remove(position);
+                } else {
+                    return Err(failure(id.clone(), String::from(name)));
+                }
+            }
+        }
+
+        if !retry {
+            if trial.is_empty() {
+                return Err(TrialFailure::NoViableBuildpacks);
+            }
+
+            return Ok(Resolution {
+                buildpacks: trial.iter().map(|option| option.id.clone()).collect(),
+                entries: dependencies
+                    .entries
+                    .into_iter()
+                    .filter(|(_, dependency)| !dependency.providers.is_empty())
+                    .map(|(name, dependency)| ResolvedEntry {
+                        name: String::from(name),
+                        providers: dependency.providers.into_iter().cloned().collect(),
+                        requires: dependency.requires.into_iter().cloned().collect(),
+                    })
+                    .collect(),
+            });
+        }
+    }
+}
+
+type FailureConstructor = fn(BuildpackId, String) -> TrialFailure;
+
+/// Tracks providers and requirements per entry name, in the order names are first seen.
+struct Dependencies<'a> {
+    entries: Vec<(&'a str, Dependency<'a>)>,
+}
+
+#[derive(Default)]
+struct Dependency<'a> {
+    providers: Vec<&'a BuildpackId>,
+    requires: Vec<&'a Require>,
+    extra_provides: Vec<&'a BuildpackId>,
+    early_requires: Vec<&'a BuildpackId>,
+}
+
+impl<'a> Dependencies<'a> {
+    fn new(trial: &[TrialOption<'a>]) -> Self {
+        let mut dependencies = Self { entries: vec![] };
+
+        for option in trial {
+            for provide in option.provides {
+                dependencies
+                    .entry(&provide.name)
+                    .extra_provides
+                    .push(option.id);
+            }
+
+            for require in option.requires {
+                let dependency = dependencies.entry(&require.name);
+                dependency.providers.append(&mut dependency.extra_provides);
+
+                if dependency.providers.is_empty() {
+                    dependency.early_requires.push(option.id);
+                } else {
+                    dependency.requires.push(require);
+                }
+            }
+        }
+
+        dependencies
+    }
+
+    fn entry(&mut self, name: &'a str) -> &mut Dependency<'a> {
+        let position = self
+            .entries
+            .iter()
+            .position(|(entry_name, _)| *entry_name == name)
+            .unwrap_or_else(|| {
+                self.entries.push((name, Dependency::default()));
+                self.entries.len() - 1
+            });
+
+        &mut self.entries[position].1
+    }
+
+    /// Unmet requires first, then unmet provides, each with the matching failure constructor.
+    fn unmet(&self) -> Vec<(&'a str, &'a BuildpackId, FailureConstructor)> {
+        let unprovided_require: FailureConstructor =
+            |buildpack, name| TrialFailure::UnprovidedRequire { buildpack, name };
+        let unrequired_provide: FailureConstructor =
+            |buildpack, name| TrialFailure::UnrequiredProvide { buildpack, name };
+
+        let early_requires = self.entries.iter().flat_map(|(name, dependency)| {
+            dependency
+                .early_requires
+                .iter()
+                .map(move |id| (*name, *id, unprovided_require))
+        });
+
+        let extra_provides = self.entries.iter().flat_map(|(name, dependency)| {
+            dependency
+                .extra_provides
+                .iter()
+                .map(move |id| (*name, *id, unrequired_provide))
+        });
+
+        early_requires.chain(extra_provides).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_plan::BuildPlanBuilder;
+
+    fn id(value: &str) -> BuildpackId {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn resolves_simple_group() {
+        let resolution = resolve(&[
+            DetectedBuildpack::pass(
+                id("heroku/jvm"),
+                false,
+                BuildPlanBuilder::new().provides("jdk").build(),
+            ),
+            DetectedBuildpack::pass(
+                id("heroku/maven"),
+                false,
+                BuildPlanBuilder::new().requires("jdk").build(),
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            resolution.buildpacks,
+            vec![id("heroku/jvm"), id("heroku/maven")]
+        );
+
+        let plans = resolution.buildpack_plans();
+        assert_eq!(plans[0].1.entries.len(), 1);
+        assert_eq!(plans[0].1.entries[0].name, "jdk");
+        assert!(plans[1].1.entries.is_empty());
+    }
+
+    #[test]
+    fn passes_require_metadata_to_first_provider() {
+        let mut require = Require::new("ruby");
+        require
+            .metadata
+            .insert(String::from("version"), toml::Value::from("3.0.2"));
+
+        let mut app_build_plan = BuildPlan::new();
+        app_build_plan.requires.push(require.clone());
+
+        let resolution = resolve(&[
+            DetectedBuildpack::pass(
+                id("heroku/ruby"),
+                false,
+                BuildPlanBuilder::new()
+                    .provides("ruby")
+                    .requires("ruby")
+                    .build(),
+            ),
+            DetectedBuildpack::pass(id("heroku/app"), false, app_build_plan),
+        ])
+        .unwrap();
+
+        let plan = resolution.buildpack_plan(&id("heroku/ruby")).unwrap();
+
+        assert_eq!(plan.entries.len(), 2);
+        assert_eq!(plan.entries[1].metadata, require.metadata);
+    }
+
+    #[test]
+    fn tries_alternatives() {
+        let resolution = resolve(&[
+            DetectedBuildpack::pass(
+                id("heroku/node"),
+                false,
+                BuildPlanBuilder::new()
+                    .provides("node")
+                    .requires("node")
+                    .or()
+                    .provides("yarn")
+                    .requires("yarn")
+                    .build(),
+            ),
+            DetectedBuildpack::pass(
+                id("heroku/yarn"),
+                false,
+                BuildPlanBuilder::new().requires("yarn").build(),
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(resolution.entries.len(), 1);
+        assert_eq!(resolution.entries[0].name, "yarn");
+        assert_eq!(resolution.entries[0].requires.len(), 2);
+    }
+
+    #[test]
+    fn removes_optional_buildpacks() {
+        let resolution = resolve(&[
+            DetectedBuildpack::fail(id("heroku/procfile"), true),
+            DetectedBuildpack::pass(
+                id("heroku/jvm"),
+                true,
+                BuildPlanBuilder::new().provides("jdk").build(),
+            ),
+            DetectedBuildpack::pass(
+                id("heroku/ruby"),
+                false,
+                BuildPlanBuilder::new()
+                    .provides("ruby")
+                    .requires("ruby")
+                    .build(),
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(resolution.buildpacks, vec![id("heroku/ruby")]);
+        assert_eq!(resolution.buildpack_plan(&id("heroku/jvm")), None);
+    }
+
+    #[test]
+    fn only_last_alternative_is_optional() {
+        let group = [
+            DetectedBuildpack::pass(
+                id("heroku/jvm"),
+                true,
+                BuildPlanBuilder::new().provides("jdk").or().build(),
+            ),
+            DetectedBuildpack::pass(id("heroku/maven"), false, BuildPlan::new()),
+        ];
+
+        // The first alternative of the optional buildpack is not optional. Instead of removing the
+        // buildpack, the second (empty) alternative is tried, which resolves.
+        let resolution = resolve(&group).unwrap();
+        assert_eq!(
+            resolution.buildpacks,
+            vec![id("heroku/jvm"), id("heroku/maven")]
+        );
+        assert!(resolution.entries.is_empty());
+    }
+
+    #[test]
+    fn fails_on_required_buildpack_detect_failure() {
+        let result = resolve(&[DetectedBuildpack::fail(id("heroku/ruby"), false)]);
+
+        assert_eq!(
+            result,
+            Err(ResolveError::RequiredBuildpackFailedDetection(id(
+                "heroku/ruby"
+            )))
+        );
+    }
+
+    #[test]
+    fn fails_on_unprovided_require() {
+        let result = resolve(&[
+            DetectedBuildpack::pass(
+                id("heroku/maven"),
+                false,
+                BuildPlanBuilder::new().requires("jdk").build(),
+            ),
+            DetectedBuildpack::pass(
+                id("heroku/jvm"),
+                false,
+                BuildPlanBuilder::new().provides("jdk").build(),
+            ),
+        ]);
+
+        let error = result.unwrap_err();
+        assert_eq!(
+            error,
+            ResolveError::Unresolvable {
+                trials: 1,
+                last_failure: TrialFailure::UnprovidedRequire {
+                    buildpack: id("heroku/maven"),
+                    name: String::from("jdk")
+                }
+            }
+        );
+
+        assert_eq!(
+            error.to_string(),
+            "Build plan cannot be resolved, tried 1 combination(s) of alternatives. Last failure: heroku/maven requires `jdk`, but no buildpack before it provides it"
+        );
+    }
+
+    #[test]
+    fn fails_on_unrequired_provide_after_all_alternatives() {
+        let result = resolve(&[DetectedBuildpack::pass(
+            id("heroku/jvm"),
+            false,
+            BuildPlanBuilder::new()
+                .provides("jdk")
+                .or()
+                .provides("jre")
+                .build(),
+        )]);
+
+        assert_eq!(
+            result,
+            Err(ResolveError::Unresolvable {
+                trials: 2,
+                last_failure: TrialFailure::UnrequiredProvide {
+                    buildpack: id("heroku/jvm"),
+                    name: String::from("jre")
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn fails_without_viable_buildpacks() {
+        let result = resolve(&[DetectedBuildpack::pass(
+            id("heroku/jvm"),
+            true,
+            BuildPlanBuilder::new().provides("jdk").build(),
+        )]);
+
+        assert_eq!(
+            result,
+            Err(ResolveError::Unresolvable {
+                trials: 1,
+                last_failure: TrialFailure::NoViableBuildpacks
+            })
+        );
+    }
+}
@@ -7,7 +7,10 @@ use serde::Deserialize;
 /// The Buildpack API version.
 ///
 /// This MUST be in form `<major>.<minor>` or `<major>`, where `<major>` is equivalent to `<major>.0`.
-#[derive(Deserialize, Debug, Eq, PartialEq)]
+///
+/// Versions are ordered by major and then minor version, which allows checking if a version is
+/// within a supported range.
+#[derive(Deserialize, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
 #[serde(try_from = "&str")]
 pub struct BuildpackApi {
     pub major: u32,
@@ -118,4 +121,26 @@ mod tests {
             "0.10"
         );
     }
+
+    #[test]
+    fn buildpack_api_ordering() {
+        assert!(BuildpackApi { major: 0, minor: 6 } < BuildpackApi { major: 0, minor: 7 });
+        assert!(
+            BuildpackApi {
+                major: 0,
+                minor: 10
+            } > BuildpackApi { major: 0, minor: 9 }
+        );
+        assert!(
+            BuildpackApi { major: 1, minor: 0 }
+                > BuildpackApi {
+                    major: 0,
+                    minor: 10
+                }
+        );
+        assert!(
+            (BuildpackApi { major: 0, minor: 6 }..=BuildpackApi { major: 0, minor: 8 })
+                .contains(&BuildpackApi { major: 0, minor: 7 })
+        );
+    }
 }
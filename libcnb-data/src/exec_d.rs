@@ -1,5 +1,68 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::iter::FromIterator;
 
-#[derive(Serialize)]
-pub type ExecD = HashMap<String, String>;
+/// Output of a CNB exec.d program.
+///
+/// exec.d programs write this data as TOML to file descriptor 3. Each entry describes an
+/// environment variable that will be set for the launched process.
+///
+/// # Examples
+/// ```
+/// use libcnb_data::exec_d::ExecDProgramOutput;
+///
+/// let output: ExecDProgramOutput = vec![("JAVA_OPTS", "-Xmx512m")].into_iter().collect();
+/// assert_eq!(toml::to_string(&output).unwrap(), "JAVA_OPTS = \"-Xmx512m\"\n");
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, Default, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct ExecDProgramOutput(HashMap<String, String>);
+
+impl ExecDProgramOutput {
+    #[must_use]
+    pub fn new(map: HashMap<String, String>) -> Self {
+        Self(map)
+    }
+
+    /// Returns the environment variables of this output.
+    #[must_use]
+    pub fn variables(&self) -> &HashMap<String, String> {
+        &self.0
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for ExecDProgramOutput {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_empty_output() {
+        assert_eq!(toml::to_string(&ExecDProgramOutput::default()).unwrap(), "");
+    }
+
+    #[test]
+    fn deserialize_output() {
+        let output: ExecDProgramOutput = toml::from_str(
+            r#"
+FOO = "bar"
+BAZ = "qux"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            output,
+            vec![("FOO", "bar"), ("BAZ", "qux")].into_iter().collect()
+        );
+    }
+}
@@ -11,8 +11,10 @@
 pub mod bom;
 pub mod build;
 pub mod build_plan;
+pub mod build_plan_resolution;
 pub mod buildpack;
 pub mod buildpack_plan;
+pub mod exec_d;
 pub mod launch;
 pub mod layer;
 pub mod layer_content_metadata;
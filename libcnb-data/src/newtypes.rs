@@ -19,22 +19,22 @@
 /// calls that might fail at runtime.
 ///
 /// # Usage:
+/// The macro takes, in order:
+/// - The module of this crate that exports the newtype publicly. Since it might differ from the
+///   actual module structure, the macro needs a way to determine how to import the type from a
+///   user's buildpack crate.
+/// - The name of the literal macro, optionally preceded by its rustdoc.
+/// - The name of the newtype, optionally preceded by its rustdoc.
+/// - The name of the newtype error, optionally preceded by its rustdoc.
+/// - The regular expression that must match for the String to be valid. Uses the `fancy_regex`
+///   crate which supports negative lookarounds.
+///
+/// For example, [`BuildpackId`](crate::buildpack::BuildpackId) is declared with
+/// `libcnb_newtype!(buildpack, buildpack_id, BuildpackId, BuildpackIdError, r"...")` and can then
+/// be used like this:
 /// ```
-/// libcnb_newtype!(
-///     // The module of this crate that exports the newtype publicly. Since it might differ from
-///     // the actual module structure, the macro needs a way to determine how to import the type
-///     // from a user's buildpack crate.
-///     tests::doctest
-///     /// RustDoc for the macro (optional)
-///     buildpack_id,
-///     /// RustDoc for the newtype itself (optional)
-///     BuildpackId,
-///     /// RustDoc for the newtype error (optional)
-///     BuildpackIdError,
-///     // The regular expression that must match for the String to be valid. Uses the `fancy_regex`
-///     // crate which supports negative lookarounds.
-///     r"^[[:alnum:]./-]+$",
-/// );
+/// use libcnb_data::buildpack::BuildpackId;
+/// use libcnb_data::buildpack_id;
 ///
 /// // Using the type:
 /// let bp_id = "bar".parse::<BuildpackId>().unwrap();
@@ -156,7 +156,6 @@ pub(crate) use libcnb_newtype;
 
 #[cfg(test)]
 mod tests {
-    use super::libcnb_newtype;
     use serde_test::{assert_de_tokens, assert_de_tokens_error, Token};
 
     libcnb_newtype!(
@@ -211,7 +210,7 @@ mod tests {
 
     #[test]
     fn join() {
-        let names = vec![capitalized_name!("A"), capitalized_name!("B")];
+        let names = [capitalized_name!("A"), capitalized_name!("B")];
         assert_eq!("A, B", names.join(", "));
     }
 
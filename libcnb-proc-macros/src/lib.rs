@@ -10,6 +10,8 @@ use syn::parse::{Parse, ParseStream};
 use syn::parse_macro_input;
 use syn::Token;
 
+mod platform;
+
 /// Compiles the given regex using the `fancy_regex` crate and tries to match the given value. If
 /// the value matches the regex, the macro will expand to the first expression. Otherwise it will
 /// expand to the second expression.
@@ -46,6 +48,19 @@ pub fn verify_regex(input: TokenStream) -> TokenStream {
     token_stream.into()
 }
 
+/// Derives `libcnb::Platform` for a struct with typed configuration read from environment
+/// variables of the platform.
+///
+/// See the documentation of `libcnb::Platform` for details.
+#[proc_macro_derive(Platform, attributes(platform))]
+pub fn derive_platform(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+
+    platform::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 struct VerifyRegexInput {
     regex: syn::LitStr,
     value: syn::LitStr,
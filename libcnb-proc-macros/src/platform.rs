@@ -0,0 +1,258 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+
+/// Expands `#[derive(Platform)]` for the given struct.
+pub(crate) fn expand(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "Platform can only be derived for structs with named fields",
+            ))
+        }
+    };
+
+    let mut env_field = None;
+    let mut bindings_field = None;
+    let mut config_fields = vec![];
+
+    for field in fields {
+        // Named fields always have an identifier.
+        let ident = field.ident.clone().unwrap();
+
+        match parse_field_attributes(field)? {
+            FieldKind::Env if env_field.is_some() => {
+                return Err(syn::Error::new(
+                    field.span(),
+                    "Only one field can be marked with #[platform(env)]",
+                ))
+            }
+            FieldKind::Env => env_field = Some(ident),
+            FieldKind::Bindings if bindings_field.is_some() => {
+                return Err(syn::Error::new(
+                    field.span(),
+                    "Only one field can be marked with #[platform(bindings)]",
+                ))
+            }
+            FieldKind::Bindings => bindings_field = Some(ident),
+            FieldKind::Config(config) => config_fields.push((ident, &field.ty, config)),
+        }
+    }
+
+    let env_field = env_field.ok_or_else(|| {
+        syn::Error::new(
+            input.ident.span(),
+            "Platform requires a field of type libcnb::Env marked with #[platform(env)]",
+        )
+    })?;
+
+    let config_field_initializers = config_fields
+        .iter()
+        .map(|(ident, ty, config)| config_field_initializer(ident, ty, config))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let bindings_field_initializer = bindings_field.as_ref().map(|ident| {
+        quote! { #ident: ::libcnb::bindings::Bindings::new(), }
+    });
+
+    let bindings_assignment = bindings_field.as_ref().map(|ident| {
+        quote! {
+            platform.#ident = ::libcnb::bindings::Bindings::read_from_platform_dir(platform_dir)?;
+        }
+    });
+
+    let name = &input.ident;
+    let visibility = &input.vis;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #name #type_generics #where_clause {
+            /// Creates the platform from the given environment variables.
+            ///
+            /// Service bindings, if any, are left empty.
+            #visibility fn from_env(
+                env: ::libcnb::Env,
+            ) -> ::std::result::Result<Self, ::libcnb::PlatformEnvError> {
+                ::std::result::Result::Ok(Self {
+                    #(#config_field_initializers)*
+                    #bindings_field_initializer
+                    #env_field: env,
+                })
+            }
+        }
+
+        impl #impl_generics ::libcnb::Platform for #name #type_generics #where_clause {
+            fn env(&self) -> &::libcnb::Env {
+                &self.#env_field
+            }
+
+            fn from_path(
+                platform_dir: impl ::std::convert::AsRef<::std::path::Path>,
+            ) -> ::std::io::Result<Self> {
+                let platform_dir = platform_dir.as_ref();
+                let env = ::libcnb::read_platform_env(platform_dir)?;
+
+                #[allow(unused_mut)]
+                let mut platform = Self::from_env(env).map_err(|error| {
+                    ::std::io::Error::new(::std::io::ErrorKind::InvalidData, error)
+                })?;
+
+                #bindings_assignment
+
+                ::std::result::Result::Ok(platform)
+            }
+        }
+    })
+}
+
+enum FieldKind {
+    Env,
+    Bindings,
+    Config(ConfigField),
+}
+
+struct ConfigField {
+    variable_name: Option<syn::LitStr>,
+    default: Option<syn::LitStr>,
+    separator: Option<syn::LitStr>,
+}
+
+fn parse_field_attributes(field: &syn::Field) -> syn::Result<FieldKind> {
+    let mut kind = None;
+    let mut config = ConfigField {
+        variable_name: None,
+        default: None,
+        separator: None,
+    };
+
+    for attribute in field
+        .attrs
+        .iter()
+        .filter(|attribute| attribute.path.is_ident("platform"))
+    {
+        let nested_metas = match attribute.parse_meta()? {
+            syn::Meta::List(list) => list.nested,
+            meta => {
+                return Err(syn::Error::new(
+                    meta.span(),
+                    "Expected #[platform(...)] attribute",
+                ))
+            }
+        };
+
+        for nested_meta in nested_metas {
+            match nested_meta {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("env") => {
+                    kind = Some(FieldKind::Env);
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("bindings") => {
+                    kind = Some(FieldKind::Bindings);
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(value),
+                    ..
+                })) => {
+                    if path.is_ident("var") {
+                        config.variable_name = Some(value);
+                    } else if path.is_ident("default") {
+                        config.default = Some(value);
+                    } else if path.is_ident("separator") {
+                        config.separator = Some(value);
+                    } else {
+                        return Err(syn::Error::new(
+                            path.span(),
+                            "Unknown platform attribute, expected `var`, `default` or `separator`",
+                        ));
+                    }
+                }
+                nested_meta => {
+                    return Err(syn::Error::new(
+                        nested_meta.span(),
+                        "Unknown platform attribute, expected `env`, `bindings`, `var = \"...\"`, `default = \"...\"` or `separator = \"...\"`",
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(kind.unwrap_or(FieldKind::Config(config)))
+}
+
+fn config_field_initializer(
+    ident: &syn::Ident,
+    ty: &syn::Type,
+    config: &ConfigField,
+) -> syn::Result<TokenStream> {
+    // Raw identifiers keep their `r#` prefix when converted to a string.
+    let variable_name = config.variable_name.as_ref().map_or_else(
+        || ident.to_string().trim_start_matches("r#").to_uppercase(),
+        syn::LitStr::value,
+    );
+
+    let default = config.default.as_ref().map_or_else(
+        || quote! { ::std::option::Option::None },
+        |default| quote! { ::std::option::Option::Some(#default) },
+    );
+
+    let value = match (wrapped_type(ty, "Option"), wrapped_type(ty, "Vec")) {
+        (Some(_), _) if config.default.is_some() => {
+            return Err(syn::Error::new(
+                ty.span(),
+                "Optional fields cannot have a default value",
+            ))
+        }
+        (Some(inner_ty), _) => quote! {
+            ::libcnb::internals::platform_env_value::<#inner_ty>(&env, #variable_name)?
+        },
+        (None, Some(inner_ty)) => {
+            let separator = config
+                .separator
+                .as_ref()
+                .map_or_else(|| String::from(","), syn::LitStr::value);
+
+            quote! {
+                ::libcnb::internals::platform_env_list::<#inner_ty>(&env, #variable_name, #separator, #default)?
+            }
+        }
+        (None, None) => quote! {
+            ::libcnb::internals::required_platform_env_value::<#ty>(&env, #variable_name, #default)?
+        },
+    };
+
+    if config.separator.is_some() && wrapped_type(ty, "Vec").is_none() {
+        return Err(syn::Error::new(
+            ty.span(),
+            "A separator can only be specified for Vec fields",
+        ));
+    }
+
+    Ok(quote! { #ident: #value, })
+}
+
+/// Returns `T` if the given type is `<wrapper_name><T>`, for example `Option<T>`.
+fn wrapped_type<'a>(ty: &'a syn::Type, wrapper_name: &str) -> Option<&'a syn::Type> {
+    let segment = match ty {
+        syn::Type::Path(syn::TypePath { qself: None, path }) => path.segments.last()?,
+        _ => return None,
+    };
+
+    if segment.ident != wrapper_name {
+        return None;
+    }
+
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(arguments) if arguments.args.len() == 1 => {
+            match arguments.args.first()? {
+                syn::GenericArgument::Type(inner_ty) => Some(inner_ty),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,382 @@
+//! Service bindings provided by the platform.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Service bindings from the `bindings` directory of the platform directory.
+///
+/// Platforms provide credentials and configuration for services, such as private package
+/// registries or custom CA certificates, as bindings. Each binding is a directory that follows the
+/// [Kubernetes Service Binding specification](https://github.com/servicebinding/spec#workload-projection)
+/// adopted by the [platform specification](https://github.com/buildpacks/spec/blob/main/extensions/bindings.md).
+///
+/// Platform implementations read bindings with [`Bindings::read_from_platform_dir`]. They are
+/// available on [`crate::generic::GenericPlatform`] via its `bindings` method.
+///
+/// # Examples
+/// ```no_run
+/// use libcnb::bindings::Bindings;
+///
+/// let bindings = Bindings::read_from_platform_dir("/platform").unwrap();
+///
+/// for binding in bindings.of_type("ca-certificates") {
+///     println!("Found CA certificates in binding {}", binding.name());
+/// }
+///
+/// let registry_password = bindings
+///     .of_type_and_provider("maven", "artifactory")
+///     .next()
+///     .and_then(|binding| binding.get_as_string("password"));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Bindings {
+    bindings: Vec<Binding>,
+}
+
+impl Bindings {
+    /// Creates an empty `Bindings` struct.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads all bindings from the `bindings` directory of the given platform directory.
+    ///
+    /// A missing `bindings` directory results in no bindings. Hidden entries, such as the
+    /// `..data` symlinks of Kubernetes volume mounts, are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a binding could not be read or a binding has no `type`.
+    pub fn read_from_platform_dir(platform_dir: impl AsRef<Path>) -> io::Result<Self> {
+        let bindings_dir = platform_dir.as_ref().join("bindings");
+
+        let entries = match fs::read_dir(&bindings_dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(error) => return Err(error),
+        };
+
+        let mut bindings = vec![];
+        for entry in entries {
+            let path = entry?.path();
+
+            // k8s volume mounts will mount a directory symlink in, so we need to check that it's
+            // actually a directory
+            if path.is_dir() && !is_hidden(&path) {
+                bindings.push(Binding::read_from_dir(path)?);
+            }
+        }
+
+        bindings.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self { bindings })
+    }
+
+    /// Returns the binding with the given name.
+    #[must_use]
+    pub fn get(&self, name: impl AsRef<str>) -> Option<&Binding> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.name == name.as_ref())
+    }
+
+    /// Returns all bindings of the given type. Types are compared case-insensitively.
+    pub fn of_type<'a>(&'a self, binding_type: &'a str) -> impl Iterator<Item = &'a Binding> {
+        self.bindings
+            .iter()
+            .filter(move |binding| binding.type_name.eq_ignore_ascii_case(binding_type))
+    }
+
+    /// Returns all bindings of the given type and provider. Types and providers are compared
+    /// case-insensitively.
+    pub fn of_type_and_provider<'a>(
+        &'a self,
+        binding_type: &'a str,
+        provider: &'a str,
+    ) -> impl Iterator<Item = &'a Binding> {
+        self.of_type(binding_type).filter(move |binding| {
+            binding.provider.as_ref().map_or(false, |binding_provider| {
+                binding_provider.eq_ignore_ascii_case(provider)
+            })
+        })
+    }
+
+    /// Returns an iterator over all bindings, ordered by name.
+    pub fn iter(&self) -> std::slice::Iter<'_, Binding> {
+        self.bindings.iter()
+    }
+
+    /// Returns the number of bindings.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    /// Returns true if there are no bindings.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a Bindings {
+    type Item = &'a Binding;
+    type IntoIter = std::slice::Iter<'a, Binding>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A single service binding.
+///
+/// Besides its `type` and optional `provider`, a binding consists of entries such as `username`,
+/// `password` or `ca.crt`. Each entry is a file in the binding directory.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Binding {
+    name: String,
+    path: PathBuf,
+    type_name: String,
+    provider: Option<String>,
+    entries: BTreeMap<String, Vec<u8>>,
+}
+
+impl Binding {
+    /// Reads the binding from the given binding directory. The name of the binding is the name of
+    /// the directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the binding could not be read or has no `type`.
+    pub fn read_from_dir(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        let name = path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid binding directory: {}", path.display()),
+                )
+            })?;
+
+        let mut entries = BTreeMap::new();
+        for entry in fs::read_dir(path)? {
+            let entry_path = entry?.path();
+
+            if entry_path.is_file() && !is_hidden(&entry_path) {
+                if let Some(file_name) = entry_path.file_name() {
+                    entries.insert(
+                        file_name.to_string_lossy().into_owned(),
+                        fs::read(&entry_path)?,
+                    );
+                }
+            }
+        }
+
+        let type_name = entries
+            .remove(TYPE_ENTRY)
+            .map(|contents| String::from_utf8_lossy(&contents).trim().to_string())
+            .filter(|type_name| !type_name.is_empty())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Binding {} has no type", name),
+                )
+            })?;
+
+        let provider = entries
+            .remove(PROVIDER_ENTRY)
+            .map(|contents| String::from_utf8_lossy(&contents).trim().to_string())
+            .filter(|provider| !provider.is_empty());
+
+        Ok(Self {
+            name,
+            path: path.to_path_buf(),
+            type_name,
+            provider,
+            entries,
+        })
+    }
+
+    /// The name of the binding.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The path to the binding directory. Some tools expect the path to an entry, for example to a
+    /// certificate file, instead of its contents.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The type of the binding, for example `ca-certificates` or `maven`.
+    #[must_use]
+    pub fn binding_type(&self) -> &str {
+        &self.type_name
+    }
+
+    /// The provider of the binding, if any.
+    #[must_use]
+    pub fn provider(&self) -> Option<&str> {
+        self.provider.as_deref()
+    }
+
+    /// Returns the contents of the entry with the given name.
+    #[must_use]
+    pub fn get(&self, key: impl AsRef<str>) -> Option<&[u8]> {
+        self.entries.get(key.as_ref()).map(Vec::as_slice)
+    }
+
+    /// Returns the contents of the entry with the given name as a string, if it is valid UTF-8.
+    #[must_use]
+    pub fn get_as_string(&self, key: impl AsRef<str>) -> Option<String> {
+        self.get(key)
+            .and_then(|contents| String::from_utf8(contents.to_vec()).ok())
+    }
+
+    /// Returns the names of all entries, excluding `type` and `provider`.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+}
+
+const TYPE_ENTRY: &str = "type";
+const PROVIDER_ENTRY: &str = "provider";
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name().map_or(false, |file_name| {
+        file_name.to_string_lossy().starts_with('.')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_binding(platform_dir: &Path, name: &str, entries: &[(&str, &str)]) {
+        let binding_dir = platform_dir.join("bindings").join(name);
+        fs::create_dir_all(&binding_dir).unwrap();
+
+        for (key, value) in entries {
+            fs::write(binding_dir.join(key), value).unwrap();
+        }
+    }
+
+    #[test]
+    fn read_from_platform_dir_reads_bindings() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        write_binding(
+            tmpdir.path(),
+            "registry",
+            &[
+                ("type", "maven\n"),
+                ("provider", "artifactory"),
+                ("username", "user"),
+                ("password", "secret"),
+            ],
+        );
+        write_binding(tmpdir.path(), "certs", &[("type", "ca-certificates")]);
+
+        let bindings = Bindings::read_from_platform_dir(tmpdir.path()).unwrap();
+        assert_eq!(
+            bindings.iter().map(Binding::name).collect::<Vec<_>>(),
+            vec!["certs", "registry"]
+        );
+
+        let binding = bindings.get("registry").unwrap();
+        assert_eq!(binding.binding_type(), "maven");
+        assert_eq!(binding.provider(), Some("artifactory"));
+        assert_eq!(
+            binding.get_as_string("password"),
+            Some(String::from("secret"))
+        );
+        assert_eq!(
+            binding.keys().collect::<Vec<_>>(),
+            vec!["password", "username"]
+        );
+        assert_eq!(
+            binding.path(),
+            tmpdir.path().join("bindings").join("registry")
+        );
+
+        assert_eq!(bindings.get("certs").unwrap().provider(), None);
+    }
+
+    #[test]
+    fn lookup_by_type_and_provider() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        write_binding(
+            tmpdir.path(),
+            "a",
+            &[("type", "Maven"), ("provider", "nexus")],
+        );
+        write_binding(
+            tmpdir.path(),
+            "b",
+            &[("type", "maven"), ("provider", "artifactory")],
+        );
+        write_binding(tmpdir.path(), "c", &[("type", "npm")]);
+
+        let bindings = Bindings::read_from_platform_dir(tmpdir.path()).unwrap();
+
+        assert_eq!(
+            bindings
+                .of_type("maven")
+                .map(Binding::name)
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            bindings
+                .of_type_and_provider("maven", "Artifactory")
+                .map(Binding::name)
+                .collect::<Vec<_>>(),
+            vec!["b"]
+        );
+        assert_eq!(bindings.of_type_and_provider("npm", "nexus").count(), 0);
+    }
+
+    #[test]
+    fn read_from_platform_dir_fails_for_binding_without_type() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        write_binding(tmpdir.path(), "broken", &[("username", "user")]);
+
+        let error = Bindings::read_from_platform_dir(tmpdir.path()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_from_platform_dir_does_not_blow_up_if_bindings_are_missing() {
+        let tmpdir = tempfile::tempdir().unwrap();
+
+        let bindings = Bindings::read_from_platform_dir(tmpdir.path()).unwrap();
+        assert!(bindings.is_empty());
+    }
+
+    // this symlink is only supported on unix
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn read_from_platform_dir_handles_kubernetes_volume_mounts() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let binding_dir = tmpdir.path().join("bindings").join("certs");
+        let data_dir = binding_dir.join("..2022_01_01");
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::write(data_dir.join("type"), "ca-certificates").unwrap();
+        fs::write(data_dir.join("ca.crt"), "CERTIFICATE").unwrap();
+        std::os::unix::fs::symlink(&data_dir, binding_dir.join("..data")).unwrap();
+        std::os::unix::fs::symlink("..data/type", binding_dir.join("type")).unwrap();
+        std::os::unix::fs::symlink("..data/ca.crt", binding_dir.join("ca.crt")).unwrap();
+
+        let bindings = Bindings::read_from_platform_dir(tmpdir.path()).unwrap();
+        let binding = bindings.get("certs").unwrap();
+        assert_eq!(binding.binding_type(), "ca-certificates");
+        assert_eq!(binding.keys().collect::<Vec<_>>(), vec!["ca.crt"]);
+    }
+}
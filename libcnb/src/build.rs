@@ -1,15 +1,24 @@
 //! Provides build phase specific types and helpers.
 
 use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+
+use crossbeam_utils::thread::Scope;
 
 use crate::buildpack::Buildpack;
-use crate::data::buildpack::StackId;
+use crate::data::buildpack::{BuildpackApi, StackId};
 use crate::data::layer::LayerName;
 use crate::data::store::Store;
 use crate::data::{
-    buildpack::SingleBuildpackDescriptor, buildpack_plan::BuildpackPlan, launch::Launch,
+    bom,
+    build::{Build, Unmet},
+    buildpack::SingleBuildpackDescriptor,
+    buildpack_plan::BuildpackPlan,
+    launch::Launch,
 };
 use crate::layer::{HandleLayerErrorOrBuildpackError, Layer, LayerData};
+use crate::layer_env::{LayerEnv, TargetLifecycle};
+use crate::{Env, Platform};
 
 /// Context for the build phase execution.
 pub struct BuildContext<B: Buildpack + ?Sized> {
@@ -20,6 +29,10 @@ pub struct BuildContext<B: Buildpack + ?Sized> {
     pub platform: B::Platform,
     pub buildpack_plan: BuildpackPlan,
     pub buildpack_descriptor: SingleBuildpackDescriptor<B::Metadata>,
+    /// The effective build environment, see [`BuildContext::build_env`].
+    pub(crate) build_env: Mutex<Env>,
+    /// The layers handled so far, see [`BuildContext::prune_unhandled_layers`].
+    pub(crate) handled_layers: Mutex<Vec<LayerName>>,
 }
 
 impl<B: Buildpack + ?Sized> BuildContext<B> {
@@ -30,7 +43,8 @@ impl<B: Buildpack + ?Sized> BuildContext<B> {
     /// layer will exist on disk or an error has been returned by this method.
     ///
     /// Use the returned [`LayerData`] to access the layers metadata and environment variables for
-    /// subsequent logic or layers.
+    /// subsequent logic or layers. The build-time environment variables of build layers are also
+    /// applied to the effective build environment, see [`BuildContext::build_env`].
     ///
     /// # Example:
     /// ```
@@ -98,6 +112,274 @@ impl<B: Buildpack + ?Sized> BuildContext<B> {
         &self,
         layer_name: LayerName,
         layer: L,
+    ) -> crate::Result<LayerData<L::Metadata>, B::Error> {
+        self.register_handled_layer(&layer_name);
+
+        let is_build_layer = layer.types().build;
+        let layer_data = self.handle_layer_without_build_env(layer_name, layer)?;
+
+        if is_build_layer {
+            self.apply_to_build_env(&layer_data.env);
+        }
+
+        Ok(layer_data)
+    }
+
+    /// Handles multiple independent [`Layer`] implementations concurrently, each on its own
+    /// thread.
+    ///
+    /// Layers are handled with [`LayerScope::handle_layer`] in the given function, which works like
+    /// [`BuildContext::handle_layer`]. It returns a [`LayerHandle`] for each layer. Once all layers
+    /// have been handled, this method returns the value returned by the given function, typically
+    /// the layer handles. Use [`LayerHandle::into_layer_data`] to get the [`LayerData`] of each
+    /// layer from them.
+    ///
+    /// Since the layers are handled at the same time, they must not depend on each other. They
+    /// all see the effective build environment from before this method was called. Afterwards, the
+    /// build-time environment variables of the build layers are applied to it in the order in which
+    /// the layers were started, see [`BuildContext::build_env`].
+    ///
+    /// All layers are handled even if some of them fail. In that case, the errors of all failed
+    /// layers are returned together in [`crate::Error::ConcurrentLayerErrors`].
+    ///
+    /// # Example:
+    /// ```
+    /// # use libcnb::build::{BuildContext, BuildResult, BuildResultBuilder};
+    /// # use libcnb::data::layer_name;
+    /// # use libcnb::data::layer_content_metadata::LayerTypes;
+    /// # use libcnb::detect::{DetectContext, DetectResult};
+    /// # use libcnb::generic::{GenericError, GenericMetadata, GenericPlatform};
+    /// # use libcnb::layer::{Layer, LayerResult, LayerResultBuilder};
+    /// # use libcnb::Buildpack;
+    /// # use std::path::Path;
+    /// #
+    /// # struct ExampleBuildpack;
+    /// #
+    /// impl Buildpack for ExampleBuildpack {
+    /// #   type Platform = GenericPlatform;
+    /// #   type Metadata = GenericMetadata;
+    /// #   type Error = GenericError;
+    /// #
+    /// #    fn detect(&self, context: DetectContext<Self>) -> libcnb::Result<DetectResult, Self::Error> {
+    /// #        unimplemented!()
+    /// #    }
+    /// #
+    ///     fn build(&self, context: BuildContext<Self>) -> libcnb::Result<BuildResult, Self::Error> {
+    ///         let (jdk_layer, maven_layer) = context.handle_layers_concurrently(|layers| {
+    ///             (
+    ///                 layers.handle_layer(layer_name!("jdk"), JdkLayer),
+    ///                 layers.handle_layer(layer_name!("maven"), MavenLayer),
+    ///             )
+    ///         })?;
+    ///
+    ///         if let Some(jdk_layer_data) = jdk_layer.into_layer_data() {
+    ///             println!("JDK installed at {}", jdk_layer_data.path.display());
+    ///         }
+    ///
+    ///         if let Some(maven_layer_data) = maven_layer.into_layer_data() {
+    ///             println!("Maven installed at {}", maven_layer_data.path.display());
+    ///         }
+    ///
+    ///         BuildResultBuilder::new().build()
+    ///     }
+    /// }
+    /// #
+    /// # struct JdkLayer;
+    /// # struct MavenLayer;
+    /// #
+    /// # impl Layer for JdkLayer {
+    /// #     type Buildpack = ExampleBuildpack;
+    /// #     type Metadata = GenericMetadata;
+    /// #
+    /// #     fn types(&self) -> LayerTypes {
+    /// #         unimplemented!()
+    /// #     }
+    /// #
+    /// #     fn create(
+    /// #         &self,
+    /// #         context: &BuildContext<Self::Buildpack>,
+    /// #         layer_path: &Path,
+    /// #     ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
+    /// #         unimplemented!()
+    /// #     }
+    /// # }
+    /// #
+    /// # impl Layer for MavenLayer {
+    /// #     type Buildpack = ExampleBuildpack;
+    /// #     type Metadata = GenericMetadata;
+    /// #
+    /// #     fn types(&self) -> LayerTypes {
+    /// #         unimplemented!()
+    /// #     }
+    /// #
+    /// #     fn create(
+    /// #         &self,
+    /// #         context: &BuildContext<Self::Buildpack>,
+    /// #         layer_path: &Path,
+    /// #     ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
+    /// #         unimplemented!()
+    /// #     }
+    /// # }
+    /// ```
+    pub fn handle_layers_concurrently<'env, F, T>(&'env self, f: F) -> crate::Result<T, B::Error>
+    where
+        Self: Sync,
+        B::Error: Send,
+        F: for<'scope> FnOnce(&LayerScope<'scope, 'env, B>) -> T,
+    {
+        let layer_results = Arc::new(Mutex::new(vec![]));
+
+        let value = crossbeam_utils::thread::scope(|scope| {
+            let layer_scope = LayerScope {
+                scope,
+                context: self,
+                layer_results: Arc::clone(&layer_results),
+                layer_names: Mutex::new(vec![]),
+            };
+
+            f(&layer_scope)
+        })
+        // Propagates panics of layers to the caller, as if they had been handled on this thread.
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+
+        let mut layer_results =
+            std::mem::take(&mut *layer_results.lock().unwrap_or_else(PoisonError::into_inner));
+
+        // Threads finish in any order, results are processed in the order the layers were started.
+        layer_results.sort_by_key(|(index, _, _)| *index);
+
+        let mut errors = vec![];
+        for (_, layer_name, layer_result) in layer_results {
+            match layer_result {
+                Ok(Some(layer_env)) => self.apply_to_build_env(&layer_env),
+                Ok(None) => {}
+                Err(error) => errors.push((layer_name, error)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(crate::Error::ConcurrentLayerErrors(errors))
+        }
+    }
+
+    /// Returns the names of all layers that currently exist, sorted by name.
+    ///
+    /// At the start of the build, these are the layers restored by the lifecycle from the cache or
+    /// the previous image. Layers restored from the previous image might only consist of their
+    /// layer content metadata, see
+    /// [`Layer::existing_metadata_only_layer_strategy`](crate::layer::Layer::existing_metadata_only_layer_strategy).
+    pub fn existing_layers(&self) -> crate::Result<Vec<LayerName>, B::Error> {
+        crate::layer::existing_layers(&self.layers_dir).map_err(crate::Error::CannotReadLayers)
+    }
+
+    /// Deletes the layer with the given name, including its layer content metadata.
+    ///
+    /// Use this to drop layers that are no longer used, for example after renaming a layer in a new
+    /// version of the buildpack. Deleting a layer that does not exist is not an error.
+    pub fn delete_layer(&self, layer_name: &LayerName) -> crate::Result<(), B::Error> {
+        crate::layer::delete_layer(&self.layers_dir, layer_name)
+            .map_err(crate::Error::CannotDeleteLayer)
+    }
+
+    /// Deletes all existing layers that have not been handled with [`BuildContext::handle_layer`]
+    /// during the current build and returns their names.
+    ///
+    /// Call this at the end of the build to stop layers of previous buildpack versions from
+    /// accumulating in the cache. Layers that are not handled by the buildpack in every build
+    /// must not be pruned, use [`BuildContext::delete_layer`] for individual layers instead.
+    ///
+    /// ```no_run
+    /// # use libcnb::build::{BuildContext, BuildResult, BuildResultBuilder};
+    /// # use libcnb::Buildpack;
+    /// # fn example<B: Buildpack>(context: BuildContext<B>) -> libcnb::Result<BuildResult, B::Error> {
+    /// // Handle all layers of the buildpack first.
+    /// for layer_name in context.prune_unhandled_layers()? {
+    ///     println!("Removed unused layer {}", layer_name);
+    /// }
+    ///
+    /// BuildResultBuilder::new().build()
+    /// # }
+    /// ```
+    pub fn prune_unhandled_layers(&self) -> crate::Result<Vec<LayerName>, B::Error> {
+        let handled_layers = self
+            .handled_layers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+
+        let unhandled_layers: Vec<LayerName> = self
+            .existing_layers()?
+            .into_iter()
+            .filter(|layer_name| !handled_layers.contains(layer_name))
+            .collect();
+
+        for layer_name in &unhandled_layers {
+            self.delete_layer(layer_name)?;
+        }
+
+        Ok(unhandled_layers)
+    }
+
+    /// The effective environment for the remaining build.
+    ///
+    /// Starts with the environment of the buildpack process, or an empty environment if the
+    /// buildpack sets `clear-env` in its `buildpack.toml`. The user-provided environment variables
+    /// of the platform are added on top. Afterwards, the build-time environment variables of every
+    /// layer with [`LayerTypes::build`](crate::data::layer_content_metadata::LayerTypes::build)
+    /// set that is handled with [`BuildContext::handle_layer`] are applied, in order. Like the CNB
+    /// lifecycle, libcnb ignores the environment of layers that are not available at build time.
+    ///
+    /// Use it for subprocesses that need the tools installed by earlier layers:
+    /// ```no_run
+    /// # use libcnb::build::BuildContext;
+    /// # use libcnb::generic::{GenericError, GenericMetadata, GenericPlatform};
+    /// # use libcnb::Buildpack;
+    /// # use std::process::Command;
+    /// # fn example<B: Buildpack>(context: &BuildContext<B>) {
+    /// Command::new("bundle")
+    ///     .arg("install")
+    ///     .env_clear()
+    ///     .envs(&context.build_env())
+    ///     .status()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn build_env(&self) -> Env {
+        self.build_env
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// The Buildpack API version this buildpack targets, as declared in its `buildpack.toml`.
+    ///
+    /// libcnb only runs buildpacks that target a Buildpack API version it supports. Use this to
+    /// implement behaviour that differs between Buildpack API versions.
+    #[must_use]
+    pub fn buildpack_api(&self) -> BuildpackApi {
+        self.buildpack_descriptor.api
+    }
+}
+
+impl<B: Buildpack + ?Sized> BuildContext<B> {
+    fn register_handled_layer(&self, layer_name: &LayerName) {
+        let mut handled_layers = self
+            .handled_layers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if !handled_layers.contains(layer_name) {
+            handled_layers.push(layer_name.clone());
+        }
+    }
+
+    fn handle_layer_without_build_env<L: Layer<Buildpack = B>>(
+        &self,
+        layer_name: LayerName,
+        layer: L,
     ) -> crate::Result<LayerData<L::Metadata>, B::Error> {
         crate::layer::handle_layer(self, layer_name, layer).map_err(|error| match error {
             HandleLayerErrorOrBuildpackError::HandleLayerError(e) => {
@@ -106,6 +388,131 @@ impl<B: Buildpack + ?Sized> BuildContext<B> {
             HandleLayerErrorOrBuildpackError::BuildpackError(e) => crate::Error::BuildpackError(e),
         })
     }
+
+    fn apply_to_build_env(&self, layer_env: &LayerEnv) {
+        let mut build_env = self
+            .build_env
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *build_env = layer_env.apply(TargetLifecycle::Build, &build_env);
+    }
+}
+
+/// Handles layers concurrently, see [`BuildContext::handle_layers_concurrently`].
+pub struct LayerScope<'scope, 'env: 'scope, B: Buildpack + ?Sized> {
+    scope: &'scope Scope<'env>,
+    context: &'env BuildContext<B>,
+    layer_results: Arc<Mutex<Vec<ConcurrentLayerResult<B::Error>>>>,
+    layer_names: Mutex<Vec<LayerName>>,
+}
+
+/// The result of a concurrently handled layer: its index, its name and either its build-time
+/// environment, if it is a build layer, or the error that occurred while handling it.
+type ConcurrentLayerResult<E> = (usize, LayerName, crate::Result<Option<LayerEnv>, E>);
+
+impl<'env, B: Buildpack + ?Sized> LayerScope<'_, 'env, B>
+where
+    BuildContext<B>: Sync,
+    B::Error: Send,
+{
+    /// Starts handling the given [`Layer`] implementation on a new thread, see
+    /// [`BuildContext::handle_layer`].
+    ///
+    /// Each layer can only be handled once per [`BuildContext::handle_layers_concurrently`] call.
+    /// Handling a layer with the same name again results in a
+    /// [`crate::Error::DuplicateConcurrentLayer`] error for that layer.
+    pub fn handle_layer<L>(&self, layer_name: LayerName, layer: L) -> LayerHandle<L::Metadata>
+    where
+        L: Layer<Buildpack = B> + Send + 'env,
+        L::Metadata: Send,
+    {
+        let (sender, receiver) = mpsc::sync_channel(1);
+
+        let (index, is_duplicate) = {
+            let mut layer_names = self
+                .layer_names
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            let is_duplicate = layer_names.contains(&layer_name);
+            layer_names.push(layer_name.clone());
+
+            (layer_names.len() - 1, is_duplicate)
+        };
+
+        if is_duplicate {
+            self.layer_results
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push((
+                    index,
+                    layer_name.clone(),
+                    Err(crate::Error::DuplicateConcurrentLayer(layer_name)),
+                ));
+        } else {
+            self.context.register_handled_layer(&layer_name);
+
+            let context = self.context;
+            let layer_results = Arc::clone(&self.layer_results);
+
+            self.scope.spawn(move |_| {
+                let is_build_layer = layer.types().build;
+                let layer_result = context
+                    .handle_layer_without_build_env(layer_name.clone(), layer)
+                    .map(|layer_data| {
+                        let layer_env = if is_build_layer {
+                            Some(layer_data.env.clone())
+                        } else {
+                            None
+                        };
+                        // Sending only fails if the layer handle has been dropped, in which case
+                        // the layer data is not needed.
+                        sender.send(layer_data).unwrap_or_default();
+                        layer_env
+                    });
+
+                layer_results
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push((index, layer_name, layer_result));
+            });
+        }
+
+        LayerHandle { receiver }
+    }
+}
+
+/// A layer that is handled concurrently, see [`LayerScope::handle_layer`].
+#[must_use]
+pub struct LayerHandle<M> {
+    receiver: mpsc::Receiver<LayerData<M>>,
+}
+
+impl<M> LayerHandle<M> {
+    /// Returns the [`LayerData`] of the layer, waiting for the layer to be handled if necessary.
+    ///
+    /// Returns `None` if the layer could not be handled, in which case the error is part of the
+    /// [`crate::Error::ConcurrentLayerErrors`] returned by
+    /// [`BuildContext::handle_layers_concurrently`].
+    #[must_use]
+    pub fn into_layer_data(self) -> Option<LayerData<M>> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// Creates the initial effective build environment for the given `clear-env` setting and platform.
+pub(crate) fn initial_build_env<P: Platform>(clear_env: bool, platform: &P) -> Env {
+    let mut build_env = if clear_env {
+        Env::new()
+    } else {
+        Env::from_current()
+    };
+
+    for (key, value) in platform.env() {
+        build_env.insert(key, value);
+    }
+
+    build_env
 }
 
 /// Describes the result of the build phase.
@@ -125,6 +532,7 @@ pub(crate) enum InnerBuildResult {
     Pass {
         launch: Option<Launch>,
         store: Option<Store>,
+        build: Option<Build>,
     },
 }
 
@@ -141,10 +549,15 @@ pub(crate) enum InnerBuildResult {
 /// let with_launch: Result<BuildResult, ()> = BuildResultBuilder::new()
 ///    .launch(Launch::new().process(Process::new(process_type!("type"), "command", Some(vec!["-v"]), Some(false), Some(false))))
 ///    .build();
+///
+/// let with_unmet: Result<BuildResult, ()> = BuildResultBuilder::new()
+///    .unmet("ruby")
+///    .build();
 /// ```
 pub struct BuildResultBuilder {
     launch: Option<Launch>,
     store: Option<Store>,
+    build: Option<Build>,
 }
 
 impl BuildResultBuilder {
@@ -153,6 +566,7 @@ impl BuildResultBuilder {
         Self {
             launch: None,
             store: None,
+            build: None,
         }
     }
 }
@@ -174,6 +588,7 @@ impl BuildResultBuilder {
         BuildResult(InnerBuildResult::Pass {
             launch: self.launch,
             store: self.store,
+            build: self.build,
         })
     }
 
@@ -188,6 +603,27 @@ impl BuildResultBuilder {
         self.store = Some(store);
         self
     }
+
+    /// Marks the buildpack plan entry with the given name as unmet.
+    ///
+    /// Unmet entries are passed on to subsequent buildpacks in the build group that provide an
+    /// entry with the same name. The name must match an entry of the current
+    /// [buildpack plan](BuildContext::buildpack_plan), otherwise libcnb will fail the build.
+    #[must_use]
+    pub fn unmet(mut self, name: impl Into<String>) -> Self {
+        self.build
+            .get_or_insert_with(Build::new)
+            .unmet
+            .push(Unmet { name: name.into() });
+        self
+    }
+
+    /// Adds a Bill-of-Materials entry that only applies to the build environment.
+    #[must_use]
+    pub fn build_bom(mut self, entry: bom::Entry) -> Self {
+        self.build.get_or_insert_with(Build::new).bom.push(entry);
+        self
+    }
 }
 
 impl Default for BuildResultBuilder {
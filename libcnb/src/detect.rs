@@ -4,7 +4,7 @@ use std::fmt::Debug;
 use std::path::PathBuf;
 
 use crate::buildpack::Buildpack;
-use crate::data::buildpack::StackId;
+use crate::data::buildpack::{BuildpackApi, StackId};
 use crate::{data::build_plan::BuildPlan, data::buildpack::SingleBuildpackDescriptor};
 
 /// Context for the detect phase execution.
@@ -16,6 +16,17 @@ pub struct DetectContext<B: Buildpack + ?Sized> {
     pub buildpack_descriptor: SingleBuildpackDescriptor<B::Metadata>,
 }
 
+impl<B: Buildpack + ?Sized> DetectContext<B> {
+    /// The Buildpack API version this buildpack targets, as declared in its `buildpack.toml`.
+    ///
+    /// libcnb only runs buildpacks that target a Buildpack API version it supports. Use this to
+    /// implement behaviour that differs between Buildpack API versions.
+    #[must_use]
+    pub fn buildpack_api(&self) -> BuildpackApi {
+        self.buildpack_descriptor.api
+    }
+}
+
 /// Describes the result of the detect phase.
 ///
 /// Besides indicating passing or failing detection, it also contains detect phase output such as
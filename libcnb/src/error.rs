@@ -1,5 +1,7 @@
-use crate::data::buildpack::StackIdError;
+use crate::data::buildpack::{StackId, StackIdError};
 use crate::data::launch::ProcessTypeError;
+use crate::data::layer::LayerName;
+use crate::exec_d::WriteExecDProgramOutputError;
 use crate::layer::HandleLayerError;
 use crate::toml_file::TomlFileError;
 use std::fmt::Debug;
@@ -30,6 +32,30 @@ pub enum Error<E: Debug> {
     #[error("Could not determine stack id: {0}")]
     CannotDetermineStackId(std::env::VarError),
 
+    #[error("Stack `{0}` is not supported by this buildpack")]
+    UnsupportedStack(StackId),
+
+    #[error("Stack `{stack_id}` does not provide the mixins required by this buildpack: `{}`", .mixins.join("`, `"))]
+    MissingStackMixins {
+        stack_id: StackId,
+        mixins: Vec<String>,
+    },
+
+    #[error("Cannot read stack mixins from CNB_STACK_MIXINS: {0}")]
+    CannotReadStackMixins(serde_json::Error),
+
+    #[error("Cannot handle layers concurrently: {}", format_concurrent_layer_errors(.0))]
+    ConcurrentLayerErrors(Vec<(LayerName, Error<E>)>),
+
+    #[error("Layer `{0}` was handled more than once concurrently")]
+    DuplicateConcurrentLayer(LayerName),
+
+    #[error("Cannot read layers directory: {0}")]
+    CannotReadLayers(std::io::Error),
+
+    #[error("Cannot delete layer: {0}")]
+    CannotDeleteLayer(std::io::Error),
+
     #[error("Cannot create platform from platform path: {0}")]
     CannotCreatePlatformFromPath(std::io::Error),
 
@@ -48,10 +74,27 @@ pub enum Error<E: Debug> {
     #[error("Cannot write store.toml: {0}")]
     CannotWriteStore(TomlFileError),
 
+    #[error("Cannot write build.toml: {0}")]
+    CannotWriteBuild(TomlFileError),
+
+    #[error("Cannot write exec.d program output: {0}")]
+    CannotWriteExecDProgramOutput(WriteExecDProgramOutputError),
+
+    #[error("Unmet entry `{0}` is not part of the buildpack plan")]
+    UnmetEntryNotInBuildpackPlan(String),
+
     #[error("Buildpack error: {0:?}")]
     BuildpackError(E),
 }
 
+fn format_concurrent_layer_errors<E: Debug>(errors: &[(LayerName, Error<E>)]) -> String {
+    errors
+        .iter()
+        .map(|(layer_name, error)| format!("layer `{}`: {}", layer_name, error))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 #[cfg(feature = "anyhow")]
 impl From<anyhow::Error> for Error<anyhow::Error> {
     fn from(error: anyhow::Error) -> Self {
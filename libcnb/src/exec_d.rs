@@ -0,0 +1,72 @@
+//! Provides types and helpers for implementing exec.d programs.
+//!
+//! exec.d programs are executables that are run by the CNB launcher before the application
+//! process starts. They can compute environment variables at launch time, for example based on
+//! the resources of the container.
+//!
+//! Use [`exec_d_main`](crate::exec_d_main) to create the main function of such a program and
+//! [`LayerResultBuilder::exec_d_program`](crate::layer::LayerResultBuilder::exec_d_program) to add
+//! the compiled binary to a layer.
+
+use crate::data::exec_d::ExecDProgramOutput;
+use crate::Env;
+use std::fmt::Debug;
+use std::io::Write;
+
+/// Represents an exec.d program written with the libcnb framework.
+pub trait ExecDProgram {
+    /// The error type of this program.
+    type Error: Debug;
+
+    /// Computes the environment variables that will be set for the launched process.
+    ///
+    /// `env` is the environment the launcher will run the process with.
+    fn exec_d(&self, env: &Env) -> Result<ExecDProgramOutput, Self::Error>;
+
+    /// If an unhandled error occurred, this method is called to print the error and to determine
+    /// the exit code of the program.
+    fn handle_error(&self, error: crate::Error<Self::Error>) -> i32 {
+        eprintln!("Unhandled error:");
+        eprintln!("> {:?}", error);
+        eprintln!("exec.d program will exit!");
+        1
+    }
+}
+
+/// Writes the given output as TOML to the given writer.
+///
+/// Usually, this does not need to be called directly as
+/// [`exec_d_main`](crate::exec_d_main) will write the output to the correct file descriptor.
+pub fn write_exec_d_program_output<W: Write>(
+    output: &ExecDProgramOutput,
+    mut writer: W,
+) -> Result<(), WriteExecDProgramOutputError> {
+    let serialized_output = toml::to_string(output)?;
+    writer.write_all(serialized_output.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum WriteExecDProgramOutputError {
+    #[error("Could not serialize exec.d program output: {0}")]
+    TomlSerializationError(#[from] toml::ser::Error),
+
+    #[error("Could not write exec.d program output: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_output() {
+        let output: ExecDProgramOutput = vec![("FOO", "bar")].into_iter().collect();
+        let mut buffer = Vec::new();
+
+        write_exec_d_program_output(&output, &mut buffer).unwrap();
+
+        assert_eq!(String::from_utf8(buffer).unwrap(), "FOO = \"bar\"\n");
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::path::Path;
 
+use crate::bindings::Bindings;
 use crate::platform::Platform;
 use crate::{read_platform_env, Env};
 use std::fmt::{Debug, Display, Formatter};
@@ -18,15 +19,32 @@ impl Display for GenericError {
     }
 }
 
-/// A generic platform that only provides access to environment variables.
+/// A generic platform that provides access to environment variables and service bindings.
 pub struct GenericPlatform {
     env: Env,
+    bindings: Bindings,
 }
 
 impl GenericPlatform {
     #[must_use]
     pub fn new(env: Env) -> Self {
-        Self { env }
+        Self {
+            env,
+            bindings: Bindings::new(),
+        }
+    }
+
+    /// Adds the given service bindings to the platform.
+    #[must_use]
+    pub fn with_bindings(mut self, bindings: Bindings) -> Self {
+        self.bindings = bindings;
+        self
+    }
+
+    /// Service bindings from the `bindings` directory of the platform directory.
+    #[must_use]
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
     }
 }
 
@@ -36,6 +54,11 @@ impl Platform for GenericPlatform {
     }
 
     fn from_path(platform_dir: impl AsRef<Path>) -> std::io::Result<Self> {
-        read_platform_env(platform_dir.as_ref()).map(|env| GenericPlatform { env })
+        let platform_dir = platform_dir.as_ref();
+
+        Ok(GenericPlatform {
+            env: read_platform_env(platform_dir)?,
+            bindings: Bindings::read_from_platform_dir(platform_dir)?,
+        })
     }
 }
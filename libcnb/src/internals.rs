@@ -0,0 +1,73 @@
+// These items are used by the code that `#[derive(Platform)]` expands to and by `libcnb-cargo`.
+// They are not intended to be used outside of the libcnb.rs project. But since the code that macros
+// expand to is just regular code, we need to expose them to users of this crate.
+
+use crate::{Env, PlatformEnvError, PlatformEnvValue};
+
+/// The version of this crate. Projects generated by `cargo libcnb new` depend on this version.
+pub const LIBCNB_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Reads and parses the value of the given variable.
+pub fn platform_env_value<T: PlatformEnvValue>(
+    env: &Env,
+    name: &str,
+) -> Result<Option<T>, PlatformEnvError> {
+    platform_env_string(env, name)?
+        .map(|value| parse_platform_env_value(name, &value))
+        .transpose()
+}
+
+/// Reads and parses the value of the given variable, falling back to the given default value.
+pub fn required_platform_env_value<T: PlatformEnvValue>(
+    env: &Env,
+    name: &str,
+    default: Option<&str>,
+) -> Result<T, PlatformEnvError> {
+    match platform_env_string(env, name)? {
+        Some(value) => parse_platform_env_value(name, &value),
+        None => default
+            .ok_or_else(|| PlatformEnvError::MissingVariable(String::from(name)))
+            .and_then(|default| parse_platform_env_value(name, default)),
+    }
+}
+
+/// Reads and parses the separated values of the given variable, falling back to the given default
+/// value.
+pub fn platform_env_list<T: PlatformEnvValue>(
+    env: &Env,
+    name: &str,
+    separator: &str,
+    default: Option<&str>,
+) -> Result<Vec<T>, PlatformEnvError> {
+    let value = platform_env_string(env, name)?
+        .or_else(|| default.map(String::from))
+        .unwrap_or_default();
+
+    value
+        .split(separator)
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| parse_platform_env_value(name, item))
+        .collect()
+}
+
+fn platform_env_string(env: &Env, name: &str) -> Result<Option<String>, PlatformEnvError> {
+    env.get(name)
+        .map(|value| {
+            value
+                .into_string()
+                .map_err(|_| PlatformEnvError::InvalidUnicode(String::from(name)))
+        })
+        .transpose()
+}
+
+fn parse_platform_env_value<T: PlatformEnvValue>(
+    name: &str,
+    value: &str,
+) -> Result<T, PlatformEnvError> {
+    T::from_platform_env_value(value).map_err(|reason| PlatformEnvError::InvalidValue {
+        name: String::from(name),
+        value: String::from(value),
+        reason,
+    })
+}
@@ -0,0 +1,270 @@
+use crate::build::BuildContext;
+use crate::Buildpack;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// Declares the inputs a layer depends on, see [`Layer::cache_key`](crate::layer::Layer::cache_key).
+///
+/// libcnb computes a digest of all inputs and stores it with the layer. If the digest of an
+/// existing layer matches, the layer is kept. Otherwise, it is recreated.
+///
+/// # Example
+/// ```
+/// use libcnb::layer::CacheKey;
+///
+/// let cache_key = CacheKey::new()
+///     .app_files("Gemfile.lock")
+///     .app_files("vendor/**/*.gem")
+///     .env_var("BUNDLE_WITHOUT")
+///     .stack_id()
+///     .buildpack_version()
+///     .value("2.7.4");
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[must_use]
+pub struct CacheKey {
+    inputs: Vec<CacheKeyInput>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum CacheKeyInput {
+    AppFiles(String),
+    EnvVar(String),
+    StackId,
+    BuildpackVersion,
+    Value(Vec<u8>),
+}
+
+impl CacheKey {
+    /// Creates a cache key without any inputs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the paths and contents of the files in the app directory that match the given glob
+    /// pattern. Matched directories are included with all files they contain. Patterns that do not
+    /// match any files are valid.
+    pub fn app_files(mut self, pattern: impl Into<String>) -> Self {
+        self.inputs.push(CacheKeyInput::AppFiles(pattern.into()));
+        self
+    }
+
+    /// Adds the value of the given environment variable in the effective build environment, see
+    /// [`BuildContext::build_env`]. Whether the variable is set at all is part of the key as well.
+    pub fn env_var(mut self, name: impl Into<String>) -> Self {
+        self.inputs.push(CacheKeyInput::EnvVar(name.into()));
+        self
+    }
+
+    /// Adds the ID of the current stack.
+    pub fn stack_id(mut self) -> Self {
+        self.inputs.push(CacheKeyInput::StackId);
+        self
+    }
+
+    /// Adds the version of the buildpack, as declared in its `buildpack.toml`.
+    pub fn buildpack_version(mut self) -> Self {
+        self.inputs.push(CacheKeyInput::BuildpackVersion);
+        self
+    }
+
+    /// Adds an arbitrary value, for example the version of a tool the layer installs.
+    ///
+    /// The value is hashed immediately. Since the digest is compared across builds, only use types
+    /// with a [`Hash`] implementation that does not depend on the process, such as strings,
+    /// integers and collections of them.
+    pub fn value(mut self, value: impl Hash) -> Self {
+        let mut hasher = Sha256Hasher(Sha256::new());
+        value.hash(&mut hasher);
+
+        self.inputs
+            .push(CacheKeyInput::Value(hasher.0.finalize().to_vec()));
+        self
+    }
+
+    /// Computes the digest of all inputs in the given context, as a hex string.
+    pub(crate) fn digest<B: Buildpack + ?Sized>(
+        &self,
+        context: &BuildContext<B>,
+    ) -> Result<String, CacheKeyError> {
+        let mut digest = Sha256::new();
+
+        for input in &self.inputs {
+            match input {
+                CacheKeyInput::AppFiles(pattern) => {
+                    update_labeled(&mut digest, "app-files", pattern.as_bytes());
+
+                    for file_path in app_files(&context.app_dir, pattern)? {
+                        let relative_path = file_path
+                            .strip_prefix(&context.app_dir)
+                            .unwrap_or(&file_path);
+
+                        update_labeled(
+                            &mut digest,
+                            "path",
+                            relative_path.to_string_lossy().as_bytes(),
+                        );
+                        update_labeled(&mut digest, "contents", &fs::read(&file_path)?);
+                    }
+                }
+                CacheKeyInput::EnvVar(name) => {
+                    update_labeled(&mut digest, "env-var", name.as_bytes());
+
+                    match context.build_env().get(name) {
+                        Some(value) => {
+                            update_labeled(&mut digest, "set", value.to_string_lossy().as_bytes());
+                        }
+                        None => update_labeled(&mut digest, "unset", &[]),
+                    }
+                }
+                CacheKeyInput::StackId => {
+                    update_labeled(
+                        &mut digest,
+                        "stack-id",
+                        context.stack_id.as_str().as_bytes(),
+                    );
+                }
+                CacheKeyInput::BuildpackVersion => update_labeled(
+                    &mut digest,
+                    "buildpack-version",
+                    context
+                        .buildpack_descriptor
+                        .buildpack
+                        .version
+                        .to_string()
+                        .as_bytes(),
+                ),
+                CacheKeyInput::Value(value_digest) => {
+                    update_labeled(&mut digest, "value", value_digest);
+                }
+            }
+        }
+
+        Ok(format!("{:x}", digest.finalize()))
+    }
+}
+
+/// An error that occurred while computing the digest of a [`CacheKey`].
+#[derive(thiserror::Error, Debug)]
+pub enum CacheKeyError {
+    #[error("Invalid app files pattern `{0}`: {1}")]
+    InvalidPattern(String, glob::PatternError),
+
+    #[error("IO error while reading app files: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Adds the given label and data to the digest, prefixed with their lengths to keep inputs
+/// unambiguous.
+fn update_labeled(digest: &mut Sha256, label: &str, data: &[u8]) {
+    for part in [label.as_bytes(), data] {
+        digest.update((part.len() as u64).to_be_bytes());
+        digest.update(part);
+    }
+}
+
+/// Returns the files in the app directory that match the given pattern, sorted by path.
+fn app_files(app_dir: &Path, pattern: &str) -> Result<Vec<PathBuf>, CacheKeyError> {
+    let absolute_pattern = format!(
+        "{}/{}",
+        glob::Pattern::escape(&app_dir.to_string_lossy()),
+        pattern
+    );
+
+    let mut file_paths = vec![];
+    for path in glob::glob(&absolute_pattern)
+        .map_err(|error| CacheKeyError::InvalidPattern(String::from(pattern), error))?
+    {
+        collect_files(&path.map_err(std::io::Error::from)?, &mut file_paths)?;
+    }
+
+    file_paths.sort();
+    file_paths.dedup();
+
+    Ok(file_paths)
+}
+
+fn collect_files(path: &Path, file_paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_files(&entry?.path(), file_paths)?;
+        }
+    } else {
+        file_paths.push(path.to_path_buf());
+    }
+
+    Ok(())
+}
+
+/// Feeds the data of [`Hash`] implementations into a SHA-256 digest.
+struct Sha256Hasher(Sha256);
+
+/// Implements the given [`Hasher`] methods by writing the little-endian bytes of the integer.
+macro_rules! write_le_bytes {
+    ($($method:ident: $integer:ty),*) => {
+        $(
+            fn $method(&mut self, i: $integer) {
+                self.write(&i.to_le_bytes());
+            }
+        )*
+    };
+}
+
+impl Hasher for Sha256Hasher {
+    fn finish(&self) -> u64 {
+        // Not used, `CacheKey::value` uses the SHA-256 digest instead.
+        0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    // The default implementations use the native byte order, and the pointer width for usize and
+    // isize, which would make digests differ between platforms.
+    write_le_bytes!(
+        write_u8: u8,
+        write_u16: u16,
+        write_u32: u32,
+        write_u64: u64,
+        write_u128: u128,
+        write_i8: i8,
+        write_i16: i16,
+        write_i32: i32,
+        write_i64: i64,
+        write_i128: i128
+    );
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_hasher_uses_little_endian_integers() {
+        fn digest(value: impl Hash) -> Vec<u8> {
+            let mut hasher = Sha256Hasher(Sha256::new());
+            value.hash(&mut hasher);
+            hasher.0.finalize().to_vec()
+        }
+
+        assert_eq!(digest(0x0102_u16), Sha256::digest([0x02, 0x01]).to_vec());
+        assert_eq!(
+            digest(0x0102_0304_u32),
+            Sha256::digest([0x04, 0x03, 0x02, 0x01]).to_vec()
+        );
+        assert_eq!(digest(-2_i16), Sha256::digest([0xfe, 0xff]).to_vec());
+        assert_eq!(digest(1_usize), digest(1_u64));
+        assert_eq!(digest(-1_isize), digest(-1_i64));
+    }
+}
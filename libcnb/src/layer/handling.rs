@@ -2,32 +2,78 @@ use crate::build::BuildContext;
 use crate::data::layer::LayerName;
 use crate::data::layer_content_metadata::LayerContentMetadata;
 
+use crate::data::layer_content_metadata::LayerTypes;
 use crate::generic::GenericMetadata;
-use crate::layer::{ExistingLayerStrategy, Layer, LayerData, MetadataMigration};
+use crate::layer::{
+    CacheKeyError, ExistingLayerStrategy, Layer, LayerData, MetadataMigration,
+    MetadataOnlyLayerStrategy,
+};
 use crate::layer_env::LayerEnv;
-use crate::util::default_on_not_found;
+use crate::util::{copy_dir_recursively, default_on_not_found, symlink_dir};
 use crate::Buildpack;
-use crate::{write_toml_file, TomlFileError};
+use crate::{read_toml_file, write_toml_file, TomlFileError};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
 pub(crate) fn handle_layer<B: Buildpack + ?Sized, L: Layer<Buildpack = B>>(
     context: &BuildContext<B>,
     layer_name: LayerName,
     layer: L,
 ) -> Result<LayerData<L::Metadata>, HandleLayerErrorOrBuildpackError<B::Error>> {
+    recover_layer_transaction(&context.layers_dir, &layer_name)?;
+
+    let cache_key_digest = layer
+        .cache_key(context)
+        .map(|cache_key| cache_key.digest(context))
+        .transpose()
+        .map_err(HandleLayerError::CacheKeyError)?;
+
+    let layer_data = handle_layer_with_cache_key_digest(
+        context,
+        layer_name,
+        layer,
+        cache_key_digest.as_deref(),
+    )?;
+
+    if let Some(cache_key_digest) = cache_key_digest {
+        write_cache_key_digest(&context.layers_dir, &layer_data.name, &cache_key_digest)?;
+    }
+
+    Ok(layer_data)
+}
+
+fn handle_layer_with_cache_key_digest<B: Buildpack + ?Sized, L: Layer<Buildpack = B>>(
+    context: &BuildContext<B>,
+    layer_name: LayerName,
+    layer: L,
+    cache_key_digest: Option<&str>,
+) -> Result<LayerData<L::Metadata>, HandleLayerErrorOrBuildpackError<B::Error>> {
+    if is_metadata_only_layer(&context.layers_dir, &layer_name) {
+        return handle_metadata_only_layer(context, layer_name, &layer, cache_key_digest);
+    }
+
     match read_layer(&context.layers_dir, &layer_name) {
         Ok(None) => handle_create_layer(context, &layer_name, &layer),
         Ok(Some(layer_data)) => {
-            let existing_layer_strategy = layer
-                .existing_layer_strategy(context, &layer_data)
-                .map_err(HandleLayerErrorOrBuildpackError::BuildpackError)?;
+            let existing_layer_strategy = if let Some(cache_key_digest) = cache_key_digest {
+                if read_cache_key_digest(&context.layers_dir, &layer_name)?.as_deref()
+                    == Some(cache_key_digest)
+                {
+                    ExistingLayerStrategy::Keep
+                } else {
+                    ExistingLayerStrategy::Recreate
+                }
+            } else {
+                layer
+                    .existing_layer_strategy(context, &layer_data)
+                    .map_err(HandleLayerErrorOrBuildpackError::BuildpackError)?
+            };
 
             match existing_layer_strategy {
                 ExistingLayerStrategy::Recreate => {
-                    delete_layer(&context.layers_dir, &layer_name)?;
                     handle_create_layer(context, &layer_name, &layer)
                 }
                 ExistingLayerStrategy::Update => handle_update_layer(context, &layer_data, &layer),
@@ -67,7 +113,7 @@ pub(crate) fn handle_layer<B: Buildpack + ?Sized, L: Layer<Buildpack = B>>(
 
                     match metadata_migration_strategy {
                         MetadataMigration::RecreateLayer => {
-                            delete_layer(&context.layers_dir, &layer_name)?;
+                            handle_create_layer(context, &layer_name, &layer)
                         }
                         MetadataMigration::ReplaceMetadata(migrated_metadata) => {
                             write_layer(
@@ -79,10 +125,15 @@ pub(crate) fn handle_layer<B: Buildpack + ?Sized, L: Layer<Buildpack = B>>(
                                     metadata: migrated_metadata,
                                 },
                             )?;
+
+                            handle_layer_with_cache_key_digest(
+                                context,
+                                layer_name,
+                                layer,
+                                cache_key_digest,
+                            )
                         }
                     }
-
-                    handle_layer(context, layer_name, layer)
                 }
                 Ok(None) => Err(HandleLayerError::UnexpectedMissingLayer.into()),
                 Err(read_layer_error) => {
@@ -94,60 +145,371 @@ pub(crate) fn handle_layer<B: Buildpack + ?Sized, L: Layer<Buildpack = B>>(
     }
 }
 
-fn handle_create_layer<B: Buildpack + ?Sized, L: Layer<Buildpack = B>>(
+/// Handles a layer of which only the layer content metadata has been restored, see
+/// [`Layer::existing_metadata_only_layer_strategy`].
+fn handle_metadata_only_layer<B: Buildpack + ?Sized, L: Layer<Buildpack = B>>(
     context: &BuildContext<B>,
-    layer_name: &LayerName,
+    layer_name: LayerName,
     layer: &L,
+    cache_key_digest: Option<&str>,
 ) -> Result<LayerData<L::Metadata>, HandleLayerErrorOrBuildpackError<B::Error>> {
-    let layer_dir = context.layers_dir.join(layer_name.as_str());
+    let layer_content_metadata_path = context
+        .layers_dir
+        .join(format!("{}.toml", layer_name.as_str()));
+
+    let layer_content_metadata =
+        match read_layer_content_metadata::<L::Metadata>(&layer_content_metadata_path) {
+            Ok(layer_content_metadata) => Some(layer_content_metadata),
+            // Metadata that cannot be parsed cannot be kept, the layer is recreated instead.
+            Err(ReadLayerError::LayerContentMetadataParseError(_)) => None,
+            Err(read_layer_error) => return Err(read_layer_error.into()),
+        };
+
+    let metadata_only_layer_strategy = |layer_content_metadata| {
+        if let Some(cache_key_digest) = cache_key_digest {
+            if read_cache_key_digest(&context.layers_dir, &layer_name)?.as_deref()
+                == Some(cache_key_digest)
+            {
+                Ok(MetadataOnlyLayerStrategy::Keep)
+            } else {
+                Ok(MetadataOnlyLayerStrategy::Recreate)
+            }
+        } else {
+            layer
+                .existing_metadata_only_layer_strategy(context, layer_content_metadata)
+                .map_err(HandleLayerErrorOrBuildpackError::BuildpackError)
+        }
+    };
+
+    match layer_content_metadata {
+        Some(layer_content_metadata)
+            if layer.types() == METADATA_ONLY_LAYER_TYPES
+                && metadata_only_layer_strategy(&layer_content_metadata)?
+                    == MetadataOnlyLayerStrategy::Keep =>
+        {
+            // Restored layer content metadata does not contain the layer types. They must be
+            // written again, otherwise the lifecycle will not keep the layer.
+            let layer_content_metadata = LayerContentMetadata {
+                types: Some(layer.types()),
+                metadata: layer_content_metadata.metadata,
+            };
+
+            write_toml_file(&layer_content_metadata, &layer_content_metadata_path)
+                .map_err(WriteLayerError::from)?;
+
+            Ok(LayerData {
+                path: context.layers_dir.join(layer_name.as_str()),
+                name: layer_name,
+                env: LayerEnv::new(),
+                content_metadata: layer_content_metadata,
+            })
+        }
+        _ => handle_create_layer(context, &layer_name, layer),
+    }
+}
+
+/// The only layer types for which the lifecycle restores the layer content metadata without the
+/// layer directory.
+const METADATA_ONLY_LAYER_TYPES: LayerTypes = LayerTypes {
+    launch: true,
+    build: false,
+    cache: false,
+};
 
-    fs::create_dir_all(&layer_dir)
-        .map_err(HandleLayerError::IoError)
-        .map_err(HandleLayerErrorOrBuildpackError::HandleLayerError)?;
+/// Returns true if only the layer content metadata of the layer exists, without its directory.
+fn is_metadata_only_layer(layers_dir: impl AsRef<Path>, layer_name: &LayerName) -> bool {
+    let layers_dir = layers_dir.as_ref();
 
-    let layer_result = layer
-        .create(context, &layer_dir)
-        .map_err(HandleLayerErrorOrBuildpackError::BuildpackError)?;
+    !layers_dir.join(layer_name.as_str()).exists()
+        && layers_dir.join(format!("{}.toml", layer_name)).exists()
+}
 
-    write_layer(
+/// Creates the layer, replacing any existing layer with the same name.
+///
+/// The layer is created transactionally: if creating it fails, the existing layer is restored, or
+/// no layer is left behind if there was none.
+fn handle_create_layer<B: Buildpack + ?Sized, L: Layer<Buildpack = B>>(
+    context: &BuildContext<B>,
+    layer_name: &LayerName,
+    layer: &L,
+) -> Result<LayerData<L::Metadata>, HandleLayerErrorOrBuildpackError<B::Error>> {
+    handle_layer_transaction(
         &context.layers_dir,
         layer_name,
-        &layer_result.env.unwrap_or_default(),
-        &LayerContentMetadata {
-            types: Some(layer.types()),
-            metadata: layer_result.metadata,
-        },
-    )?;
+        LayerTransactionKind::Create,
+        || {
+            let layer_dir = context.layers_dir.join(layer_name.as_str());
 
-    read_layer(&context.layers_dir, layer_name)?
-        .ok_or(HandleLayerError::UnexpectedMissingLayer)
-        .map_err(HandleLayerErrorOrBuildpackError::HandleLayerError)
+            fs::create_dir_all(&layer_dir)
+                .map_err(HandleLayerError::IoError)
+                .map_err(HandleLayerErrorOrBuildpackError::HandleLayerError)?;
+
+            let layer_result = layer
+                .create(context, &layer_dir)
+                .map_err(HandleLayerErrorOrBuildpackError::BuildpackError)?;
+
+            write_exec_d_programs(&layer_dir, &layer_result.exec_d_programs)?;
+
+            write_layer(
+                &context.layers_dir,
+                layer_name,
+                &layer_result.env.unwrap_or_default(),
+                &LayerContentMetadata {
+                    types: Some(layer.types()),
+                    metadata: layer_result.metadata,
+                },
+            )?;
+
+            read_layer(&context.layers_dir, layer_name)?
+                .ok_or(HandleLayerError::UnexpectedMissingLayer)
+                .map_err(HandleLayerErrorOrBuildpackError::HandleLayerError)
+        },
+    )
 }
 
+/// Updates the existing layer.
+///
+/// The layer is updated transactionally: the update runs against a copy of the existing layer
+/// and if updating it fails, the existing layer is restored.
 fn handle_update_layer<B: Buildpack + ?Sized, L: Layer<Buildpack = B>>(
     context: &BuildContext<B>,
     layer_data: &LayerData<L::Metadata>,
     layer: &L,
 ) -> Result<LayerData<L::Metadata>, HandleLayerErrorOrBuildpackError<B::Error>> {
-    let layer_result = layer
-        .update(context, layer_data)
-        .map_err(HandleLayerErrorOrBuildpackError::BuildpackError)?;
-
-    write_layer(
+    handle_layer_transaction(
         &context.layers_dir,
         &layer_data.name,
-        &layer_result.env.unwrap_or_default(),
-        &LayerContentMetadata {
-            types: Some(layer.types()),
-            metadata: layer_result.metadata,
+        LayerTransactionKind::Update,
+        || {
+            let layer_result = layer
+                .update(context, layer_data)
+                .map_err(HandleLayerErrorOrBuildpackError::BuildpackError)?;
+
+            write_exec_d_programs(&layer_data.path, &layer_result.exec_d_programs)?;
+
+            write_layer(
+                &context.layers_dir,
+                &layer_data.name,
+                &layer_result.env.unwrap_or_default(),
+                &LayerContentMetadata {
+                    types: Some(layer.types()),
+                    metadata: layer_result.metadata,
+                },
+            )?;
+
+            read_layer(&context.layers_dir, &layer_data.name)?
+                .ok_or(HandleLayerError::UnexpectedMissingLayer)
+                .map_err(HandleLayerErrorOrBuildpackError::HandleLayerError)
         },
-    )?;
+    )
+}
+
+/// Runs the given function, which creates or updates a layer, as a transaction.
+///
+/// If the function succeeds, the transaction is committed. Otherwise, it is rolled back, see
+/// [`LayerTransaction`]. Since the layer is left in an undefined state if rolling back fails,
+/// that error is returned instead of the original one in that case.
+fn handle_layer_transaction<T, E>(
+    layers_dir: &Path,
+    layer_name: &LayerName,
+    kind: LayerTransactionKind,
+    f: impl FnOnce() -> Result<T, HandleLayerErrorOrBuildpackError<E>>,
+) -> Result<T, HandleLayerErrorOrBuildpackError<E>> {
+    let transaction = LayerTransaction::begin(layers_dir, layer_name, kind)?;
+
+    match f() {
+        Ok(value) => {
+            transaction.commit()?;
+            Ok(value)
+        }
+        Err(error) => {
+            transaction.rollback()?;
+            Err(error)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum LayerTransactionKind {
+    /// The layer is created from scratch in an empty build directory.
+    Create,
+    /// The layer is updated in a build directory that starts out as a copy of the existing layer.
+    Update,
+}
 
-    read_layer(&context.layers_dir, &layer_data.name)?
-        .ok_or(HandleLayerError::UnexpectedMissingLayer)
-        .map_err(HandleLayerErrorOrBuildpackError::HandleLayerError)
+/// A transaction that creates or updates a layer in a staging directory within the layers
+/// directory.
+///
+/// The existing layer is moved to the staging directory as a backup. Once it has been moved, a
+/// marker file is written to the staging directory, signalling that the layer at its final path
+/// is being modified. The layer is then built in a build directory next to the backup. Since
+/// buildpacks usually embed the path of a layer in the layer, for example in environment
+/// variables, the final path of the layer is a symbolic link to the build directory while the
+/// layer is built.
+///
+/// The transaction is committed by replacing the symbolic link with the build directory and
+/// renaming the staging directory, which atomically discards the backup. If the transaction is
+/// neither committed nor rolled back, for example because the buildpack panicked, it is rolled
+/// back when dropped. If the process was killed instead, it is rolled back by
+/// [`recover_layer_transaction`] the next time the layer is handled.
+struct LayerTransaction {
+    paths: LayerTransactionPaths,
+    finished: bool,
 }
 
+impl LayerTransaction {
+    fn begin(
+        layers_dir: &Path,
+        layer_name: &LayerName,
+        kind: LayerTransactionKind,
+    ) -> Result<Self, std::io::Error> {
+        let paths = LayerTransactionPaths::new(layers_dir, layer_name);
+
+        fs::create_dir(&paths.staging_dir)?;
+
+        let transaction = LayerTransaction {
+            paths,
+            finished: false,
+        };
+
+        default_on_not_found(fs::rename(
+            &transaction.paths.layer_dir,
+            transaction.paths.backup_layer_dir(),
+        ))?;
+        default_on_not_found(fs::rename(
+            &transaction.paths.layer_toml_path,
+            transaction.paths.backup_layer_toml_path(),
+        ))?;
+
+        fs::write(transaction.paths.marker_path(), "")?;
+
+        match kind {
+            LayerTransactionKind::Create => fs::create_dir(transaction.paths.build_layer_dir())?,
+            LayerTransactionKind::Update => copy_dir_recursively(
+                transaction.paths.backup_layer_dir(),
+                transaction.paths.build_layer_dir(),
+            )?,
+        }
+
+        symlink_dir(
+            transaction.paths.build_layer_dir(),
+            &transaction.paths.layer_dir,
+        )?;
+
+        Ok(transaction)
+    }
+
+    fn commit(mut self) -> Result<(), std::io::Error> {
+        self.finished = true;
+
+        // The layer might have replaced the symbolic link with a directory of its own, in which
+        // case that directory already is the final layer.
+        if fs::symlink_metadata(&self.paths.layer_dir)?
+            .file_type()
+            .is_symlink()
+        {
+            fs::remove_dir_all(&self.paths.layer_dir)?;
+            fs::rename(self.paths.build_layer_dir(), &self.paths.layer_dir)?;
+        }
+
+        fs::rename(&self.paths.staging_dir, &self.paths.discarded_dir)?;
+        fs::remove_dir_all(&self.paths.discarded_dir)
+    }
+
+    fn rollback(mut self) -> Result<(), std::io::Error> {
+        self.finished = true;
+        self.paths.roll_back()
+    }
+}
+
+impl Drop for LayerTransaction {
+    fn drop(&mut self) {
+        if !self.finished {
+            // Errors cannot be reported here. The transaction will be recovered the next time the
+            // layer is handled if rolling back fails.
+            let _ = self.paths.roll_back();
+        }
+    }
+}
+
+/// Rolls back a transaction of a previous build that was aborted before it could be committed or
+/// rolled back, see [`LayerTransaction`].
+fn recover_layer_transaction(
+    layers_dir: &Path,
+    layer_name: &LayerName,
+) -> Result<(), std::io::Error> {
+    let paths = LayerTransactionPaths::new(layers_dir, layer_name);
+
+    default_on_not_found(fs::remove_dir_all(&paths.discarded_dir))?;
+
+    if paths.staging_dir.exists() {
+        paths.roll_back()?;
+    }
+
+    Ok(())
+}
+
+struct LayerTransactionPaths {
+    layer_dir: PathBuf,
+    layer_toml_path: PathBuf,
+    staging_dir: PathBuf,
+    discarded_dir: PathBuf,
+}
+
+impl LayerTransactionPaths {
+    fn new(layers_dir: &Path, layer_name: &LayerName) -> Self {
+        LayerTransactionPaths {
+            layer_dir: layers_dir.join(layer_name.as_str()),
+            layer_toml_path: layers_dir.join(format!("{}.toml", layer_name)),
+            staging_dir: layers_dir.join(format!("{}{}", STAGING_DIR_PREFIX, layer_name)),
+            discarded_dir: layers_dir.join(format!("{}{}", DISCARDED_DIR_PREFIX, layer_name)),
+        }
+    }
+
+    /// Deletes the layer, or the symbolic link to the layer that is built, at its final path if it
+    /// might have been modified, and moves the backup of the layer back in place. Each step can be repeated if a previous rollback was
+    /// aborted.
+    fn roll_back(&self) -> Result<(), std::io::Error> {
+        if self.marker_path().exists() {
+            default_on_not_found(fs::remove_dir_all(&self.layer_dir))?;
+            default_on_not_found(fs::remove_file(&self.layer_toml_path))?;
+            fs::remove_file(self.marker_path())?;
+        }
+
+        default_on_not_found(fs::rename(self.backup_layer_dir(), &self.layer_dir))?;
+        default_on_not_found(fs::rename(
+            self.backup_layer_toml_path(),
+            &self.layer_toml_path,
+        ))?;
+
+        fs::remove_dir_all(&self.staging_dir)
+    }
+
+    fn backup_layer_dir(&self) -> PathBuf {
+        self.staging_dir.join("layer")
+    }
+
+    fn backup_layer_toml_path(&self) -> PathBuf {
+        self.staging_dir.join("layer.toml")
+    }
+
+    fn build_layer_dir(&self) -> PathBuf {
+        self.staging_dir.join("build")
+    }
+
+    fn marker_path(&self) -> PathBuf {
+        self.staging_dir.join("in-progress")
+    }
+}
+
+/// The prefix of the directories within the layers directory that hold layer backups and the
+/// layer that is built while a layer is created or updated.
+const STAGING_DIR_PREFIX: &str = ".libcnb-staging-";
+
+/// The prefix of the directories within the layers directory that hold layer backups while they
+/// are discarded after a layer has been created or updated.
+const DISCARDED_DIR_PREFIX: &str = ".libcnb-discarded-";
+
 #[derive(Debug)]
 pub(crate) enum HandleLayerErrorOrBuildpackError<E> {
     HandleLayerError(HandleLayerError),
@@ -191,6 +553,12 @@ pub enum HandleLayerError {
 
     #[error("Expected layer to be present, but it was missing")]
     UnexpectedMissingLayer,
+
+    #[error("Cannot compute cache key of layer: {0}")]
+    CacheKeyError(CacheKeyError),
+
+    #[error("Invalid exec.d program name `{0}`, it must be a single file name")]
+    InvalidExecDProgramName(String),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -212,7 +580,7 @@ pub enum WriteLayerError {
 }
 
 /// Does not error if the layer doesn't exist.
-fn delete_layer<P: AsRef<Path>>(
+pub(crate) fn delete_layer<P: AsRef<Path>>(
     layers_dir: P,
     layer_name: &LayerName,
 ) -> Result<(), std::io::Error> {
@@ -227,6 +595,45 @@ fn delete_layer<P: AsRef<Path>>(
     Ok(())
 }
 
+/// Returns the names of all layers in the given layers directory, sorted by name.
+///
+/// A layer exists if its directory or its layer content metadata exists. Staging directories and
+/// files that do not belong to a layer are skipped.
+pub(crate) fn existing_layers(
+    layers_dir: impl AsRef<Path>,
+) -> Result<Vec<LayerName>, std::io::Error> {
+    let mut layer_names = vec![];
+
+    for dir_entry in fs::read_dir(layers_dir)? {
+        let dir_entry = dir_entry?;
+        let file_name = dir_entry.file_name();
+
+        let layer_name = match file_name.to_str() {
+            Some(file_name)
+                if file_name.starts_with(STAGING_DIR_PREFIX)
+                    || file_name.starts_with(DISCARDED_DIR_PREFIX) =>
+            {
+                None
+            }
+            Some(file_name) if dir_entry.file_type()?.is_dir() => Some(file_name),
+            Some(file_name) => file_name.strip_suffix(".toml"),
+            None => None,
+        };
+
+        // Names that are not valid layer names, such as `launch` for `launch.toml`, do not
+        // belong to a layer.
+        if let Some(layer_name) = layer_name.and_then(|name| name.parse::<LayerName>().ok()) {
+            if !layer_names.contains(&layer_name) {
+                layer_names.push(layer_name);
+            }
+        }
+    }
+
+    layer_names.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+    Ok(layer_names)
+}
+
 /// Updates layer metadata on disk
 fn write_layer<M: Serialize, P: AsRef<Path>>(
     layers_dir: P,
@@ -246,27 +653,57 @@ fn write_layer<M: Serialize, P: AsRef<Path>>(
     Ok(())
 }
 
-fn read_layer<M: DeserializeOwned, P: AsRef<Path>>(
+/// Copies the given exec.d programs into the `exec.d` directory of the given layer.
+fn write_exec_d_programs<P: AsRef<Path>>(
+    layer_dir: P,
+    exec_d_programs: &HashMap<String, PathBuf>,
+) -> Result<(), HandleLayerError> {
+    if exec_d_programs.is_empty() {
+        return Ok(());
+    }
+
+    // Program names must not escape the exec.d directory, for example with "../foo".
+    if let Some(name) = exec_d_programs
+        .keys()
+        .find(|name| !is_single_path_component(name))
+    {
+        return Err(HandleLayerError::InvalidExecDProgramName(name.clone()));
+    }
+
+    let exec_d_dir = layer_dir.as_ref().join("exec.d");
+    fs::create_dir_all(&exec_d_dir)?;
+
+    for (name, path) in exec_d_programs {
+        // fs::copy also copies the permission bits, keeping the program executable.
+        fs::copy(path, exec_d_dir.join(name))?;
+    }
+
+    Ok(())
+}
+
+fn is_single_path_component(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(component)), None) if component == name
+    )
+}
+
+pub(crate) fn read_layer<M: DeserializeOwned, P: AsRef<Path>>(
     layers_dir: P,
     layer_name: &LayerName,
 ) -> Result<Option<LayerData<M>>, ReadLayerError> {
     let layer_dir_path = layers_dir.as_ref().join(layer_name.as_str());
     let layer_toml_path = layers_dir.as_ref().join(format!("{}.toml", layer_name));
 
-    if !layer_dir_path.exists() && !layer_toml_path.exists() {
-        return Ok(None);
-    } else if !layer_dir_path.exists() && layer_toml_path.exists() {
-        // This is a valid case according to the spec:
+    if !layer_dir_path.exists() {
+        // When only the layer TOML file exists, this is a valid case according to the spec:
         // https://github.com/buildpacks/spec/blob/7b20dfa070ed428c013e61a3cefea29030af1732/buildpack.md#layer-types
         //
         // When launch = true, build = false, cache = false, the layer metadata will be restored but
-        // not the layer itself. However, we choose to not support this case as of now. It would
-        // complicate the API we need to expose to the user of libcnb as this case is very different
-        // compared to all other combinations of launch, build and cache. It's the only case where
-        // a cache = false layer restores some of its data between builds.
-        //
-        // To normalize, we remove the layer TOML file and treat the layer as non-existent.
-        fs::remove_file(&layer_toml_path)?;
+        // not the layer itself. Such layers are handled separately, see `handle_metadata_only_layer`.
+        // Since there is no layer directory to read, they are treated as non-existent here.
         return Ok(None);
     }
 
@@ -278,9 +715,7 @@ fn read_layer<M: DeserializeOwned, P: AsRef<Path>>(
         fs::write(&layer_toml_path, "")?;
     }
 
-    let layer_toml_contents = fs::read_to_string(&layer_toml_path)?;
-    let layer_content_metadata = toml::from_str::<LayerContentMetadata<M>>(&layer_toml_contents)
-        .map_err(ReadLayerError::LayerContentMetadataParseError)?;
+    let layer_content_metadata = read_layer_content_metadata(&layer_toml_path)?;
 
     let layer_env = LayerEnv::read_from_layer_dir(&layer_dir_path)?;
 
@@ -292,6 +727,80 @@ fn read_layer<M: DeserializeOwned, P: AsRef<Path>>(
     }))
 }
 
+/// Reads the layer content metadata from the given layer TOML file, without the cache key digest.
+fn read_layer_content_metadata<M: DeserializeOwned>(
+    layer_toml_path: &Path,
+) -> Result<LayerContentMetadata<M>, ReadLayerError> {
+    let layer_toml_contents = fs::read_to_string(layer_toml_path)?;
+    let mut layer_toml: toml::Value = toml::from_str(&layer_toml_contents)
+        .map_err(ReadLayerError::LayerContentMetadataParseError)?;
+
+    let removed_cache_key_digest = match layer_toml.get_mut("metadata") {
+        Some(toml::Value::Table(metadata)) => metadata.remove(CACHE_KEY_DIGEST_METADATA_KEY),
+        _ => None,
+    };
+
+    if removed_cache_key_digest.is_some() {
+        layer_toml.try_into()
+    } else {
+        // Deserializing from the original contents keeps the position in parse errors.
+        toml::from_str(&layer_toml_contents)
+    }
+    .map_err(ReadLayerError::LayerContentMetadataParseError)
+}
+
+/// Reads the cache key digest that is stored in the layer content metadata of the given layer.
+fn read_cache_key_digest(
+    layers_dir: impl AsRef<Path>,
+    layer_name: &LayerName,
+) -> Result<Option<String>, ReadLayerError> {
+    let layer_toml_path = layers_dir.as_ref().join(format!("{}.toml", layer_name));
+
+    let layer_toml: toml::Value = toml::from_str(&fs::read_to_string(layer_toml_path)?)
+        .map_err(ReadLayerError::LayerContentMetadataParseError)?;
+
+    Ok(layer_toml
+        .get("metadata")
+        .and_then(|metadata| metadata.get(CACHE_KEY_DIGEST_METADATA_KEY))
+        .and_then(toml::Value::as_str)
+        .map(String::from))
+}
+
+/// Stores the given cache key digest in the layer content metadata of the given layer.
+///
+/// The digest is stored within the metadata table since the lifecycle only restores that table.
+fn write_cache_key_digest(
+    layers_dir: impl AsRef<Path>,
+    layer_name: &LayerName,
+    cache_key_digest: &str,
+) -> Result<(), WriteLayerError> {
+    let layer_toml_path = layers_dir.as_ref().join(format!("{}.toml", layer_name));
+
+    let mut layer_toml: toml::Value = read_toml_file(&layer_toml_path)?;
+
+    if let toml::Value::Table(layer_toml_table) = &mut layer_toml {
+        let metadata = layer_toml_table
+            .entry("metadata")
+            .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+
+        // Metadata that is not a table cannot store the digest. The layer will be recreated in
+        // the next build instead.
+        if let toml::Value::Table(metadata) = metadata {
+            metadata.insert(
+                String::from(CACHE_KEY_DIGEST_METADATA_KEY),
+                toml::Value::String(String::from(cache_key_digest)),
+            );
+        }
+    }
+
+    write_toml_file(&layer_toml, layer_toml_path)?;
+
+    Ok(())
+}
+
+/// The key within the layer content metadata that is reserved for the cache key digest.
+const CACHE_KEY_DIGEST_METADATA_KEY: &str = "libcnb-cache-key";
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -316,21 +825,169 @@ mod tests {
         fs::create_dir_all(&layer_dir).unwrap();
         fs::write(
             layers_dir.join(format!("{}.toml", &layer_name)),
-            r#"
+            r"
             [types]
             launch = true
             build = false
             cache = true
-            "#,
+            ",
         )
         .unwrap();
 
-        super::delete_layer(&layers_dir, &layer_name).unwrap();
+        super::delete_layer(layers_dir, &layer_name).unwrap();
 
         assert!(!layer_dir.exists());
         assert!(!layers_dir.join(format!("{}.toml", &layer_name)).exists());
     }
 
+    #[test]
+    fn layer_transaction_builds_layer_in_staging_dir() {
+        let layer_name = layer_name!("foo");
+        let temp_dir = tempdir().unwrap();
+        let layers_dir = temp_dir.path();
+        let layer_dir = layers_dir.join(layer_name.as_str());
+
+        fs::create_dir_all(&layer_dir).unwrap();
+        fs::write(layer_dir.join("old"), "").unwrap();
+
+        let transaction =
+            LayerTransaction::begin(layers_dir, &layer_name, LayerTransactionKind::Create).unwrap();
+
+        // The final path of the layer points to the empty build directory in the staging
+        // directory while the layer is built.
+        assert!(fs::symlink_metadata(&layer_dir)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(
+            fs::read_link(&layer_dir).unwrap(),
+            transaction.paths.build_layer_dir()
+        );
+        assert_eq!(fs::read_dir(&layer_dir).unwrap().count(), 0);
+
+        fs::write(layer_dir.join("new"), "").unwrap();
+        transaction.commit().unwrap();
+
+        assert!(fs::symlink_metadata(&layer_dir).unwrap().is_dir());
+        assert!(layer_dir.join("new").exists());
+        assert!(!layer_dir.join("old").exists());
+        assert_eq!(fs::read_dir(layers_dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn layer_transaction_updates_copy_of_layer() {
+        let layer_name = layer_name!("foo");
+        let temp_dir = tempdir().unwrap();
+        let layers_dir = temp_dir.path();
+        let layer_dir = layers_dir.join(layer_name.as_str());
+        let layer_toml_path = layers_dir.join("foo.toml");
+
+        fs::create_dir_all(&layer_dir).unwrap();
+        fs::write(layer_dir.join("old"), "old").unwrap();
+        fs::write(&layer_toml_path, "[metadata]\nversion = \"old\"").unwrap();
+
+        let transaction =
+            LayerTransaction::begin(layers_dir, &layer_name, LayerTransactionKind::Update).unwrap();
+
+        assert_eq!(fs::read_to_string(layer_dir.join("old")).unwrap(), "old");
+
+        fs::write(layer_dir.join("old"), "modified").unwrap();
+        fs::write(layer_dir.join("new"), "").unwrap();
+        fs::write(&layer_toml_path, "[metadata]\nversion = \"new\"").unwrap();
+        transaction.rollback().unwrap();
+
+        assert_eq!(fs::read_to_string(layer_dir.join("old")).unwrap(), "old");
+        assert!(!layer_dir.join("new").exists());
+        assert_eq!(
+            fs::read_to_string(&layer_toml_path).unwrap(),
+            "[metadata]\nversion = \"old\""
+        );
+        assert_eq!(fs::read_dir(layers_dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn recover_aborted_layer_transaction() {
+        let layer_name = layer_name!("foo");
+        let temp_dir = tempdir().unwrap();
+        let layers_dir = temp_dir.path();
+        let layer_dir = layers_dir.join(layer_name.as_str());
+        let layer_toml_path = layers_dir.join("foo.toml");
+
+        fs::create_dir_all(&layer_dir).unwrap();
+        fs::write(layer_dir.join("old"), "").unwrap();
+        fs::write(&layer_toml_path, "[metadata]\nversion = \"old\"").unwrap();
+
+        // Simulates a build that was killed while the layer was recreated.
+        let transaction =
+            LayerTransaction::begin(layers_dir, &layer_name, LayerTransactionKind::Create).unwrap();
+        fs::create_dir_all(&layer_dir).unwrap();
+        fs::write(layer_dir.join("new"), "").unwrap();
+        std::mem::forget(transaction);
+
+        recover_layer_transaction(layers_dir, &layer_name).unwrap();
+
+        assert!(layer_dir.join("old").exists());
+        assert!(!layer_dir.join("new").exists());
+        assert_eq!(
+            fs::read_to_string(&layer_toml_path).unwrap(),
+            "[metadata]\nversion = \"old\""
+        );
+        assert_eq!(fs::read_dir(layers_dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn recover_aborted_layer_transaction_commit() {
+        let layer_name = layer_name!("foo");
+        let temp_dir = tempdir().unwrap();
+        let layers_dir = temp_dir.path();
+        let layer_dir = layers_dir.join(layer_name.as_str());
+
+        fs::create_dir_all(&layer_dir).unwrap();
+        fs::write(layer_dir.join("old"), "").unwrap();
+
+        // Simulates a build that was killed while the backup of a committed transaction was
+        // discarded.
+        let transaction =
+            LayerTransaction::begin(layers_dir, &layer_name, LayerTransactionKind::Create).unwrap();
+        fs::create_dir_all(&layer_dir).unwrap();
+        fs::write(layer_dir.join("new"), "").unwrap();
+        fs::remove_dir_all(&layer_dir).unwrap();
+        fs::rename(transaction.paths.build_layer_dir(), &layer_dir).unwrap();
+        fs::rename(
+            &transaction.paths.staging_dir,
+            &transaction.paths.discarded_dir,
+        )
+        .unwrap();
+        std::mem::forget(transaction);
+
+        recover_layer_transaction(layers_dir, &layer_name).unwrap();
+
+        assert!(layer_dir.join("new").exists());
+        assert!(!layer_dir.join("old").exists());
+        assert_eq!(fs::read_dir(layers_dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn existing_layers() {
+        let temp_dir = tempdir().unwrap();
+        let layers_dir = temp_dir.path();
+
+        fs::create_dir_all(layers_dir.join("foo")).unwrap();
+        fs::write(layers_dir.join("foo.toml"), "").unwrap();
+        fs::write(layers_dir.join("bar.toml"), "").unwrap();
+        fs::create_dir_all(layers_dir.join("baz")).unwrap();
+        fs::create_dir_all(layers_dir.join(format!("{}qux", STAGING_DIR_PREFIX))).unwrap();
+        fs::create_dir_all(layers_dir.join(format!("{}quux", DISCARDED_DIR_PREFIX))).unwrap();
+        fs::write(layers_dir.join("launch.toml"), "").unwrap();
+        fs::write(layers_dir.join("store.toml"), "").unwrap();
+        fs::write(layers_dir.join("foo.sbom.cdx.json"), "").unwrap();
+
+        assert_eq!(
+            super::existing_layers(layers_dir).unwrap(),
+            vec![layer_name!("bar"), layer_name!("baz"), layer_name!("foo")]
+        );
+    }
+
     #[test]
     fn delete_disjointed_layer() {
         let layer_name = layer_name!("foo");
@@ -340,16 +997,16 @@ mod tests {
 
         fs::write(
             layers_dir.join(format!("{}.toml", &layer_name)),
-            r#"
+            r"
             [types]
             launch = true
             build = false
             cache = true
-            "#,
+            ",
         )
         .unwrap();
 
-        super::delete_layer(&layers_dir, &layer_name).unwrap();
+        super::delete_layer(layers_dir, &layer_name).unwrap();
 
         assert!(!layer_dir.exists());
         assert!(!layers_dir.join(format!("{}.toml", &layer_name)).exists());
@@ -361,7 +1018,7 @@ mod tests {
         let temp_dir = tempdir().unwrap();
         let layers_dir = temp_dir.path();
 
-        super::delete_layer(&layers_dir, &layer_name).unwrap();
+        super::delete_layer(layers_dir, &layer_name).unwrap();
     }
 
     #[test]
@@ -372,7 +1029,7 @@ mod tests {
         let layer_dir = layers_dir.join(layer_name.as_str());
 
         super::write_layer(
-            &layers_dir,
+            layers_dir,
             &layer_name,
             &LayerEnv::new().chainable_insert(
                 TargetLifecycle::All,
@@ -419,7 +1076,7 @@ mod tests {
         let layer_dir = layers_dir.join(layer_name.as_str());
 
         super::write_layer(
-            &layers_dir,
+            layers_dir,
             &layer_name,
             &LayerEnv::new()
                 .chainable_insert(
@@ -448,7 +1105,7 @@ mod tests {
         fs::write(layer_dir.join("content.txt"), "Hello World!").unwrap();
 
         super::write_layer(
-            &layers_dir,
+            layers_dir,
             &layer_name,
             &LayerEnv::new().chainable_insert(
                 TargetLifecycle::All,
@@ -579,12 +1236,12 @@ mod tests {
         fs::create_dir_all(&layer_dir).unwrap();
         fs::write(
             layers_dir.join(format!("{}.toml", &layer_name)),
-            r#"
+            r"
             [types
             build = true
             launch = true
             cache = true
-            "#,
+            ",
         )
         .unwrap();
 
@@ -680,4 +1337,77 @@ mod tests {
             _ => panic!("Expected Ok(None)!"),
         }
     }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn write_exec_d_programs() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let program_path = temp_dir.path().join("program");
+        let layer_dir = temp_dir.path().join("layer");
+
+        fs::write(&program_path, "#!/usr/bin/env bash").unwrap();
+        fs::set_permissions(&program_path, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::create_dir_all(&layer_dir).unwrap();
+
+        let mut exec_d_programs = HashMap::new();
+        exec_d_programs.insert(String::from("my-program"), program_path);
+
+        super::write_exec_d_programs(&layer_dir, &exec_d_programs).unwrap();
+
+        let exec_d_program_path = layer_dir.join("exec.d").join("my-program");
+        assert_eq!(
+            fs::read_to_string(&exec_d_program_path).unwrap(),
+            "#!/usr/bin/env bash"
+        );
+        assert_eq!(
+            fs::metadata(&exec_d_program_path)
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777,
+            0o755
+        );
+    }
+
+    #[test]
+    fn write_no_exec_d_programs() {
+        let temp_dir = tempdir().unwrap();
+
+        super::write_exec_d_programs(temp_dir.path(), &HashMap::new()).unwrap();
+
+        assert!(!temp_dir.path().join("exec.d").exists());
+    }
+
+    #[test]
+    fn write_exec_d_programs_invalid_name() {
+        let temp_dir = tempdir().unwrap();
+        let program_path = temp_dir.path().join("program");
+        let layer_dir = temp_dir.path().join("layer");
+
+        fs::write(&program_path, "#!/usr/bin/env bash").unwrap();
+
+        for name in [
+            "../my-program",
+            "bin/my-program",
+            "/my-program",
+            "..",
+            ".",
+            "",
+        ] {
+            let mut exec_d_programs = HashMap::new();
+            exec_d_programs.insert(String::from(name), program_path.clone());
+
+            match super::write_exec_d_programs(&layer_dir, &exec_d_programs) {
+                Err(HandleLayerError::InvalidExecDProgramName(invalid_name)) => {
+                    assert_eq!(invalid_name, name);
+                }
+                other => panic!("Unexpected result for name {:?}: {:?}", name, other),
+            }
+        }
+
+        assert!(!temp_dir.path().join("my-program").exists());
+        assert!(!layer_dir.exists());
+    }
 }
@@ -1,10 +1,12 @@
 //! Provides types and helpers to work with layers.
 
+mod cache_key;
 mod handling;
 mod public_interface;
 
 #[cfg(test)]
 mod test;
 
+pub use cache_key::*;
 pub(crate) use handling::*;
 pub use public_interface::*;
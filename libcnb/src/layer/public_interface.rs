@@ -2,10 +2,12 @@ use crate::build::BuildContext;
 use crate::data::layer::LayerName;
 use crate::data::layer_content_metadata::{LayerContentMetadata, LayerTypes};
 use crate::generic::GenericMetadata;
+use crate::layer::CacheKey;
 use crate::layer_env::LayerEnv;
 use crate::Buildpack;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 /// Represents a buildpack layer written with the libcnb framework.
@@ -48,6 +50,75 @@ pub trait Layer {
         layer_path: &Path,
     ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error>;
 
+    /// Declares the inputs this layer depends on.
+    ///
+    /// If a cache key is returned, libcnb stores a digest of its inputs with the layer. When the
+    /// layer already exists, libcnb keeps it if the digest is unchanged and recreates it
+    /// otherwise. [`existing_layer_strategy`](Layer::existing_layer_strategy) and
+    /// [`existing_metadata_only_layer_strategy`](Layer::existing_metadata_only_layer_strategy)
+    /// are not called in that case. This also means that a layer with a cache key is never
+    /// [updated](Layer::update): changed inputs always recreate it. Return `None` and implement
+    /// [`existing_layer_strategy`](Layer::existing_layer_strategy) instead for layers that should
+    /// be updated in place.
+    ///
+    /// The digest is stored under a reserved key in the layer content metadata. It is not part of
+    /// [`Self::Metadata`].
+    ///
+    /// The default implementation returns `None`, leaving the decision to
+    /// [`existing_layer_strategy`](Layer::existing_layer_strategy).
+    ///
+    /// # Example
+    /// ```
+    /// # use libcnb::build::BuildContext;
+    /// # use libcnb::data::layer_content_metadata::LayerTypes;
+    /// # use libcnb::generic::{GenericMetadata, GenericError, GenericPlatform};
+    /// # use libcnb::layer::{CacheKey, Layer, LayerResult, LayerResultBuilder};
+    /// # use libcnb::Buildpack;
+    /// # use std::path::Path;
+    /// #
+    /// # struct ExampleBuildpack;
+    /// #
+    /// # impl Buildpack for ExampleBuildpack {
+    /// #     type Platform = GenericPlatform;
+    /// #     type Metadata = GenericMetadata;
+    /// #     type Error = GenericError;
+    /// #
+    /// #     fn detect(&self, context: libcnb::detect::DetectContext<Self>) -> libcnb::Result<libcnb::detect::DetectResult, Self::Error> {
+    /// #         unimplemented!()
+    /// #     }
+    /// #
+    /// #     fn build(&self, context: BuildContext<Self>) -> libcnb::Result<libcnb::build::BuildResult, Self::Error> {
+    /// #         unimplemented!()
+    /// #     }
+    /// # }
+    /// #
+    /// struct DependenciesLayer;
+    ///
+    /// impl Layer for DependenciesLayer {
+    /// #   type Buildpack = ExampleBuildpack;
+    /// #   type Metadata = GenericMetadata;
+    /// #
+    /// #   fn types(&self) -> LayerTypes {
+    /// #       unimplemented!()
+    /// #   }
+    /// #
+    /// #   fn create(
+    /// #       &self,
+    /// #       context: &BuildContext<Self::Buildpack>,
+    /// #       layer_path: &Path,
+    /// #   ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
+    /// #       unimplemented!()
+    /// #   }
+    /// #
+    ///     fn cache_key(&self, context: &BuildContext<Self::Buildpack>) -> Option<CacheKey> {
+    ///         Some(CacheKey::new().app_files("package-lock.json").stack_id())
+    ///     }
+    /// }
+    /// ```
+    fn cache_key(&self, context: &BuildContext<Self::Buildpack>) -> Option<CacheKey> {
+        None
+    }
+
     /// This method will be called by libcnb when the layer already exists to determine the strategy
     /// used to deal with it. Implementations of this method can use the current layer contents and
     /// metadata to make that decision.
@@ -79,6 +150,30 @@ pub trait Layer {
         Ok(ExistingLayerStrategy::Recreate)
     }
 
+    /// This method will be called by libcnb when only the metadata of the layer was restored,
+    /// without the layer directory. The lifecycle restores launch layers that are not cached this
+    /// way, allowing buildpacks to reuse the layer of the previous image.
+    ///
+    /// Implementations can compare the metadata of the previous layer with the current state. If it
+    /// is still up-to-date, returning [`MetadataOnlyLayerStrategy::Keep`] keeps the layer of the
+    /// previous image without recreating its contents. This is only possible if the layer is still
+    /// a launch layer that is not used during build and not cached. For other layer types, libcnb
+    /// always recreates the layer.
+    ///
+    /// When not implemented, the default implementation will return
+    /// [`MetadataOnlyLayerStrategy::Recreate`], causing the layer to be recreated from scratch.
+    /// The same happens if the metadata cannot be parsed into [`Self::Metadata`].
+    ///
+    /// # Implementation Requirements
+    /// Implementations **MUST NOT** modify the file-system.
+    fn existing_metadata_only_layer_strategy(
+        &self,
+        context: &BuildContext<Self::Buildpack>,
+        layer_content_metadata: &LayerContentMetadata<Self::Metadata>,
+    ) -> Result<MetadataOnlyLayerStrategy, <Self::Buildpack as Buildpack>::Error> {
+        Ok(MetadataOnlyLayerStrategy::Recreate)
+    }
+
     /// Updates the layer contents and metadata based on the cached version of a previous run.
     ///
     /// `layer_path` will be a directory with the data from a previous run. This method can modify
@@ -142,6 +237,16 @@ pub enum ExistingLayerStrategy {
     Update,
 }
 
+/// The result of a [`Layer::existing_metadata_only_layer_strategy`] call.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum MetadataOnlyLayerStrategy {
+    /// The layer of the previous image should be kept. The layer directory will not exist during
+    /// the build and the layer's environment variables are not available to the buildpack.
+    Keep,
+    /// The layer should be recreated from scratch.
+    Recreate,
+}
+
 /// The result of a [`Layer::migrate_incompatible_metadata`] call.
 pub enum MetadataMigration<M> {
     /// The layer should be recreated entirely.
@@ -151,6 +256,7 @@ pub enum MetadataMigration<M> {
 }
 
 /// Information about an existing CNB layer.
+#[derive(Debug)]
 pub struct LayerData<M> {
     pub name: LayerName,
     /// The layer's path, should not be modified outside of a [`Layer`] implementation.
@@ -166,12 +272,16 @@ pub struct LayerData<M> {
 pub struct LayerResult<M> {
     pub metadata: M,
     pub env: Option<LayerEnv>,
+    /// exec.d programs that libcnb will copy into the layer's `exec.d` directory, keyed by the
+    /// file name they should have in that directory.
+    pub exec_d_programs: HashMap<String, PathBuf>,
 }
 
 /// A builder that simplifies the creation of [`LayerResult`] values.
 pub struct LayerResultBuilder<M> {
     metadata: M,
     env: Option<LayerEnv>,
+    exec_d_programs: HashMap<String, PathBuf>,
 }
 
 impl<M> LayerResultBuilder<M> {
@@ -180,6 +290,7 @@ impl<M> LayerResultBuilder<M> {
         Self {
             metadata,
             env: None,
+            exec_d_programs: HashMap::new(),
         }
     }
 
@@ -189,6 +300,20 @@ impl<M> LayerResultBuilder<M> {
         self
     }
 
+    /// Adds an exec.d program to the layer.
+    ///
+    /// The file at `path` will be copied into the layer's `exec.d` directory as `name` after the
+    /// layer has been created or updated. Existing exec.d programs of a cached layer are kept.
+    /// `name` must be a single file name such as `memory-limit`, otherwise handling the layer
+    /// fails.
+    /// Programs written with libcnb are usually additional binaries of the buildpack, see
+    /// [`exec_d_main`](crate::exec_d_main).
+    #[must_use]
+    pub fn exec_d_program(mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.exec_d_programs.insert(name.into(), path.into());
+        self
+    }
+
     /// Builds the final [`LayerResult`].
     ///
     /// This method returns the [`LayerResult`] wrapped in a [`Result`] even though its technically
@@ -206,6 +331,7 @@ impl<M> LayerResultBuilder<M> {
         LayerResult {
             metadata: self.metadata,
             env: self.env,
+            exec_d_programs: self.exec_d_programs,
         }
     }
 }
@@ -10,19 +10,19 @@
 //! Look for the `TestLayer` type and it's [`Layer`] implementation.
 
 use crate::build::{BuildContext, BuildResult, BuildResultBuilder};
-use crate::data::buildpack_id;
 use crate::data::layer_content_metadata::LayerTypes;
+use crate::data::layer_name;
 use crate::data::stack_id;
 use crate::detect::{DetectContext, DetectResult, DetectResultBuilder};
 use crate::generic::{GenericMetadata, GenericPlatform};
 use crate::layer::{
-    handle_layer, ExistingLayerStrategy, Layer, LayerData, LayerResult, LayerResultBuilder,
-    MetadataMigration,
+    handle_layer, CacheKey, CacheKeyError, ExistingLayerStrategy, HandleLayerError,
+    HandleLayerErrorOrBuildpackError, Layer, LayerData, LayerResult, LayerResultBuilder,
+    MetadataMigration, MetadataOnlyLayerStrategy,
 };
 use crate::layer_env::{LayerEnv, ModificationBehavior, TargetLifecycle};
-use crate::{read_toml_file, Buildpack, Env, LIBCNB_SUPPORTED_BUILDPACK_API};
-use libcnb_data::buildpack::{BuildpackVersion, SingleBuildpackDescriptor, Stack};
-use libcnb_data::buildpack_plan::BuildpackPlan;
+use crate::testing::BuildContextBuilder;
+use crate::{read_toml_file, Buildpack, LIBCNB_SUPPORTED_BUILDPACK_APIS};
 use libcnb_data::layer::LayerName;
 use libcnb_data::layer_content_metadata::LayerContentMetadata;
 use rand::Rng;
@@ -30,6 +30,7 @@ use serde::Deserialize;
 use serde::Serialize;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Barrier;
 use tempfile::{tempdir, TempDir};
 
 const TEST_LAYER_LAUNCH: bool = true;
@@ -40,6 +41,25 @@ const TEST_LAYER_UPDATE_FILE_CONTENTS: &str = "ran";
 const TEST_LAYER_CREATE_FILE_NAME: &str = "create";
 const TEST_LAYER_UPDATE_FILE_NAME: &str = "update";
 
+/// Writes the file that marks a layer as created by one of the test layers.
+fn write_create_file(layer_path: &Path) -> Result<(), TestBuildpackError> {
+    fs::write(
+        layer_path.join(TEST_LAYER_CREATE_FILE_NAME),
+        TEST_LAYER_CREATE_FILE_CONTENTS,
+    )
+    .map_err(TestBuildpackError::IoError)
+}
+
+/// Writes an empty file to the given path and then fails with the given message.
+fn write_file_then_fail<M>(path: &Path, message: &str) -> Result<M, TestBuildpackError> {
+    fs::write(path, "").map_err(TestBuildpackError::IoError)?;
+
+    Err(TestBuildpackError::IoError(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        message,
+    )))
+}
+
 #[derive(Clone)]
 struct TestLayer {
     existing_layer_strategy: ExistingLayerStrategy,
@@ -64,11 +84,7 @@ impl Layer for TestLayer {
         _context: &BuildContext<Self::Buildpack>,
         layer_path: &Path,
     ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
-        fs::write(
-            layer_path.join(TEST_LAYER_CREATE_FILE_NAME),
-            TEST_LAYER_CREATE_FILE_CONTENTS,
-        )
-        .map_err(TestBuildpackError::IoError)?;
+        write_create_file(layer_path)?;
 
         LayerResultBuilder::new(TestLayerMetadata {
             version: self.write_version.clone(),
@@ -130,6 +146,216 @@ impl Default for TestLayer {
     }
 }
 
+/// A layer that is restored as metadata-only by the lifecycle, unless `launch_only` is false.
+struct MetadataOnlyTestLayer {
+    launch_only: bool,
+    metadata_only_layer_strategy: MetadataOnlyLayerStrategy,
+}
+
+impl Layer for MetadataOnlyTestLayer {
+    type Buildpack = TestBuildpack;
+    type Metadata = TestLayerMetadata;
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: !self.launch_only,
+        }
+    }
+
+    fn create(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_path: &Path,
+    ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
+        write_create_file(layer_path)?;
+
+        LayerResultBuilder::new(TestLayerMetadata {
+            version: String::from("2.0.0"),
+        })
+        .build()
+    }
+
+    fn existing_metadata_only_layer_strategy(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_content_metadata: &LayerContentMetadata<Self::Metadata>,
+    ) -> Result<MetadataOnlyLayerStrategy, <Self::Buildpack as Buildpack>::Error> {
+        assert_eq!(layer_content_metadata.metadata.version, "1.0.0");
+        Ok(self.metadata_only_layer_strategy)
+    }
+}
+
+/// A layer that uses a cache key of the given app files instead of an existing layer strategy.
+#[derive(Clone, Copy)]
+struct CacheKeyTestLayer {
+    app_files: &'static str,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct CacheKeyTestLayerMetadata {
+    version: String,
+}
+
+impl Layer for CacheKeyTestLayer {
+    type Buildpack = TestBuildpack;
+    type Metadata = CacheKeyTestLayerMetadata;
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: TEST_LAYER_LAUNCH,
+            build: TEST_LAYER_BUILD,
+            cache: TEST_LAYER_CACHE,
+        }
+    }
+
+    fn create(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_path: &Path,
+    ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
+        write_create_file(layer_path)?;
+
+        LayerResultBuilder::new(CacheKeyTestLayerMetadata {
+            version: String::from("1.0.0"),
+        })
+        .build()
+    }
+
+    fn cache_key(&self, _context: &BuildContext<Self::Buildpack>) -> Option<CacheKey> {
+        Some(CacheKey::new().app_files(self.app_files).stack_id())
+    }
+
+    fn existing_layer_strategy(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        _layer_data: &LayerData<Self::Metadata>,
+    ) -> Result<ExistingLayerStrategy, <Self::Buildpack as Buildpack>::Error> {
+        panic!("existing_layer_strategy must not be called for layers with a cache key")
+    }
+}
+
+/// A layer that writes a file, but then fails to create or update the layer.
+struct FailingTestLayer {
+    existing_layer_strategy: ExistingLayerStrategy,
+}
+
+impl Layer for FailingTestLayer {
+    type Buildpack = TestBuildpack;
+    type Metadata = TestLayerMetadata;
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: TEST_LAYER_LAUNCH,
+            build: TEST_LAYER_BUILD,
+            cache: TEST_LAYER_CACHE,
+        }
+    }
+
+    fn create(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_path: &Path,
+    ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
+        write_file_then_fail(&layer_path.join("failed-create"), "create failed")
+    }
+
+    fn existing_layer_strategy(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        _layer_data: &LayerData<Self::Metadata>,
+    ) -> Result<ExistingLayerStrategy, <Self::Buildpack as Buildpack>::Error> {
+        Ok(self.existing_layer_strategy)
+    }
+
+    fn update(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_data: &LayerData<Self::Metadata>,
+    ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
+        write_file_then_fail(&layer_data.path.join("failed-update"), "update failed")
+    }
+}
+
+/// A layer that panics while it is created or updated, after writing a file to the layer
+/// directory.
+struct PanickingTestLayer {
+    existing_layer_strategy: ExistingLayerStrategy,
+}
+
+impl Layer for PanickingTestLayer {
+    type Buildpack = TestBuildpack;
+    type Metadata = TestLayerMetadata;
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: TEST_LAYER_LAUNCH,
+            build: TEST_LAYER_BUILD,
+            cache: TEST_LAYER_CACHE,
+        }
+    }
+
+    fn create(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_path: &Path,
+    ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
+        fs::write(layer_path.join("failed-create"), "").unwrap();
+        panic!("create panicked");
+    }
+
+    fn existing_layer_strategy(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        _layer_data: &LayerData<Self::Metadata>,
+    ) -> Result<ExistingLayerStrategy, <Self::Buildpack as Buildpack>::Error> {
+        Ok(self.existing_layer_strategy)
+    }
+
+    fn update(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_data: &LayerData<Self::Metadata>,
+    ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
+        fs::write(layer_data.path.join("failed-update"), "").unwrap();
+        panic!("update panicked");
+    }
+}
+
+/// A layer that waits on the given barrier while it is created.
+struct BarrierTestLayer<'a> {
+    barrier: &'a Barrier,
+}
+
+impl Layer for BarrierTestLayer<'_> {
+    type Buildpack = TestBuildpack;
+    type Metadata = TestLayerMetadata;
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: TEST_LAYER_LAUNCH,
+            build: TEST_LAYER_BUILD,
+            cache: TEST_LAYER_CACHE,
+        }
+    }
+
+    fn create(
+        &self,
+        _context: &BuildContext<Self::Buildpack>,
+        layer_path: &Path,
+    ) -> Result<LayerResult<Self::Metadata>, <Self::Buildpack as Buildpack>::Error> {
+        self.barrier.wait();
+        write_create_file(layer_path)?;
+
+        LayerResultBuilder::new(TestLayerMetadata {
+            version: String::from("1.0.0"),
+        })
+        .build()
+    }
+}
+
 #[test]
 fn create() {
     let temp_dir = tempdir().unwrap();
@@ -290,7 +516,7 @@ fn create_then_recreate() {
     // Add a random file to the layer directory between handle_layer calls to assess if the layer is
     // actually recreated without any residue left in the directory
     fs::write(
-        handle_layer_result.path.join(&residue_file_name),
+        handle_layer_result.path.join(residue_file_name),
         "RESIDUE DATA",
     )
     .unwrap();
@@ -352,7 +578,7 @@ fn create_then_recreate() {
         fs::read_to_string(handle_layer_result.path.join(TEST_LAYER_UPDATE_FILE_NAME)).ok();
 
     let residue_file_contents =
-        fs::read_to_string(handle_layer_result.path.join(&residue_file_name)).ok();
+        fs::read_to_string(handle_layer_result.path.join(residue_file_name)).ok();
 
     assert_eq!(
         create_file_contents,
@@ -383,8 +609,8 @@ fn create_then_keep() {
     // Add a random file to the layer directory between handle_layer calls to assess if the layer is
     // kept as-is.
     fs::write(
-        handle_layer_result.path.join(&residue_file_name),
-        &residue_file_data,
+        handle_layer_result.path.join(residue_file_name),
+        residue_file_data,
     )
     .unwrap();
 
@@ -446,7 +672,7 @@ fn create_then_keep() {
         fs::read_to_string(handle_layer_result.path.join(TEST_LAYER_UPDATE_FILE_NAME)).ok();
 
     let residue_file_contents =
-        fs::read_to_string(handle_layer_result.path.join(&residue_file_name)).ok();
+        fs::read_to_string(handle_layer_result.path.join(residue_file_name)).ok();
 
     assert_eq!(
         create_file_contents,
@@ -731,6 +957,500 @@ fn write_layer_env() {
     assert_eq!(layer_env_from_disk, layer_env);
 }
 
+#[test]
+fn handle_layer_updates_build_env() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+
+    for value in ["foo", "bar"] {
+        let test_layer = TestLayer {
+            existing_layer_strategy: ExistingLayerStrategy::Recreate,
+            write_version: String::from("1.0.0"),
+            write_layer_env: Some(LayerEnv::new().chainable_insert(
+                TargetLifecycle::Build,
+                ModificationBehavior::Append,
+                "VALUES",
+                value,
+            )),
+        };
+
+        context
+            .handle_layer(random_layer_name(), test_layer)
+            .unwrap();
+    }
+
+    assert_eq!(context.build_env().get("VALUES").unwrap(), "foobar");
+}
+
+#[test]
+fn handle_layer_ignores_build_env_of_non_build_layers() {
+    struct LaunchLayer;
+
+    impl Layer for LaunchLayer {
+        type Buildpack = TestBuildpack;
+        type Metadata = GenericMetadata;
+
+        fn types(&self) -> LayerTypes {
+            LayerTypes {
+                launch: true,
+                build: false,
+                cache: false,
+            }
+        }
+
+        fn create(
+            &self,
+            _context: &BuildContext<Self::Buildpack>,
+            _layer_path: &Path,
+        ) -> Result<LayerResult<Self::Metadata>, TestBuildpackError> {
+            LayerResultBuilder::new(GenericMetadata::default())
+                .env(LayerEnv::new().chainable_insert(
+                    TargetLifecycle::Build,
+                    ModificationBehavior::Override,
+                    "LAUNCH_LAYER",
+                    "true",
+                ))
+                .build()
+        }
+    }
+
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+
+    context
+        .handle_layer(layer_name!("foo"), LaunchLayer)
+        .unwrap();
+
+    let bar_layer = context
+        .handle_layers_concurrently(|layers| layers.handle_layer(layer_name!("bar"), LaunchLayer))
+        .unwrap();
+    assert!(bar_layer.into_layer_data().is_some());
+
+    assert_eq!(context.build_env().get("LAUNCH_LAYER"), None);
+}
+
+#[test]
+fn metadata_only_layer_keep() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+    let layer_name = random_layer_name();
+    let layer_toml_path = context.layers_dir.join(format!("{}.toml", layer_name));
+
+    // The lifecycle restores the layer content metadata of launch-only layers without types.
+    fs::write(&layer_toml_path, "[metadata]\nversion = \"1.0.0\"\n").unwrap();
+
+    let layer_data = handle_layer(
+        &context,
+        layer_name.clone(),
+        MetadataOnlyTestLayer {
+            launch_only: true,
+            metadata_only_layer_strategy: MetadataOnlyLayerStrategy::Keep,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(layer_data.content_metadata.metadata.version, "1.0.0");
+    assert_eq!(layer_data.env, LayerEnv::new());
+    assert!(!layer_data.path.exists());
+
+    let layer_content_metadata: LayerContentMetadata<TestLayerMetadata> =
+        read_toml_file(&layer_toml_path).unwrap();
+
+    assert_eq!(
+        layer_content_metadata.types,
+        Some(LayerTypes {
+            launch: true,
+            build: false,
+            cache: false
+        })
+    );
+    assert_eq!(layer_content_metadata.metadata.version, "1.0.0");
+}
+
+#[test]
+fn metadata_only_layer_recreate() {
+    for (launch_only, metadata_only_layer_strategy) in [
+        (true, MetadataOnlyLayerStrategy::Recreate),
+        (false, MetadataOnlyLayerStrategy::Keep),
+    ] {
+        let temp_dir = tempdir().unwrap();
+        let context = build_context(&temp_dir);
+        let layer_name = random_layer_name();
+
+        fs::write(
+            context.layers_dir.join(format!("{}.toml", layer_name)),
+            "[metadata]\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+
+        let layer_data = handle_layer(
+            &context,
+            layer_name,
+            MetadataOnlyTestLayer {
+                launch_only,
+                metadata_only_layer_strategy,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(layer_data.content_metadata.metadata.version, "2.0.0");
+        assert!(layer_data.path.join(TEST_LAYER_CREATE_FILE_NAME).exists());
+    }
+}
+
+#[test]
+fn cache_key_keep_and_recreate() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+    let layer_name = random_layer_name();
+    let marker_file_name = "marker";
+
+    fs::write(context.app_dir.join("Gemfile.lock"), "rack (2.2.3)").unwrap();
+
+    let layer = CacheKeyTestLayer {
+        app_files: "Gemfile.lock",
+    };
+
+    let layer_data = handle_layer(&context, layer_name.clone(), layer).unwrap();
+    assert_eq!(layer_data.content_metadata.metadata.version, "1.0.0");
+    fs::write(layer_data.path.join(marker_file_name), "").unwrap();
+
+    let layer_toml: toml::Value =
+        read_toml_file(context.layers_dir.join(format!("{}.toml", layer_name))).unwrap();
+    assert!(layer_toml["metadata"]["libcnb-cache-key"].is_str());
+
+    // Unchanged inputs keep the layer.
+    let layer_data = handle_layer(&context, layer_name.clone(), layer).unwrap();
+    assert_eq!(layer_data.content_metadata.metadata.version, "1.0.0");
+    assert!(layer_data.path.join(marker_file_name).exists());
+
+    // Changed inputs recreate the layer.
+    fs::write(context.app_dir.join("Gemfile.lock"), "rack (2.2.4)").unwrap();
+
+    let layer_data = handle_layer(&context, layer_name, layer).unwrap();
+    assert!(!layer_data.path.join(marker_file_name).exists());
+    assert!(layer_data.path.join(TEST_LAYER_CREATE_FILE_NAME).exists());
+}
+
+#[test]
+fn cache_key_invalid_pattern() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+
+    let handle_layer_result = handle_layer(
+        &context,
+        random_layer_name(),
+        CacheKeyTestLayer { app_files: "[" },
+    );
+
+    match handle_layer_result {
+        Err(HandleLayerErrorOrBuildpackError::HandleLayerError(
+            HandleLayerError::CacheKeyError(CacheKeyError::InvalidPattern(pattern, _)),
+        )) => assert_eq!(pattern, "["),
+        _ => panic!("Expected a CacheKeyError::InvalidPattern error"),
+    }
+}
+
+#[test]
+fn failed_create_leaves_no_layer() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+    let layer_name = random_layer_name();
+
+    let handle_layer_result = handle_layer(
+        &context,
+        layer_name.clone(),
+        FailingTestLayer {
+            existing_layer_strategy: ExistingLayerStrategy::Recreate,
+        },
+    );
+
+    assert!(matches!(
+        handle_layer_result,
+        Err(HandleLayerErrorOrBuildpackError::BuildpackError(_))
+    ));
+    assert!(!context.layers_dir.join(layer_name.as_str()).exists());
+    assert!(!context
+        .layers_dir
+        .join(format!("{}.toml", layer_name))
+        .exists());
+    assert_eq!(fs::read_dir(&context.layers_dir).unwrap().count(), 0);
+}
+
+#[test]
+fn failed_recreate_restores_layer() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+    let layer_name = random_layer_name();
+
+    handle_layer(&context, layer_name.clone(), TestLayer::default()).unwrap();
+
+    let handle_layer_result = handle_layer(
+        &context,
+        layer_name.clone(),
+        FailingTestLayer {
+            existing_layer_strategy: ExistingLayerStrategy::Recreate,
+        },
+    );
+
+    assert!(matches!(
+        handle_layer_result,
+        Err(HandleLayerErrorOrBuildpackError::BuildpackError(_))
+    ));
+    assert_layer_restored(&context, &layer_name);
+}
+
+#[test]
+fn panicking_recreate_restores_layer() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+    let layer_name = random_layer_name();
+
+    handle_layer(&context, layer_name.clone(), TestLayer::default()).unwrap();
+
+    let handle_layer_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        handle_layer(
+            &context,
+            layer_name.clone(),
+            PanickingTestLayer {
+                existing_layer_strategy: ExistingLayerStrategy::Recreate,
+            },
+        )
+    }));
+
+    assert!(handle_layer_result.is_err());
+    assert_layer_restored(&context, &layer_name);
+}
+
+#[test]
+fn failed_update_restores_layer() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+    let layer_name = random_layer_name();
+
+    handle_layer(&context, layer_name.clone(), TestLayer::default()).unwrap();
+
+    let handle_layer_result = handle_layer(
+        &context,
+        layer_name.clone(),
+        FailingTestLayer {
+            existing_layer_strategy: ExistingLayerStrategy::Update,
+        },
+    );
+
+    assert!(matches!(
+        handle_layer_result,
+        Err(HandleLayerErrorOrBuildpackError::BuildpackError(_))
+    ));
+    assert_layer_restored(&context, &layer_name);
+}
+
+#[test]
+fn panicking_update_restores_layer() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+    let layer_name = random_layer_name();
+
+    handle_layer(&context, layer_name.clone(), TestLayer::default()).unwrap();
+
+    let handle_layer_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        handle_layer(
+            &context,
+            layer_name.clone(),
+            PanickingTestLayer {
+                existing_layer_strategy: ExistingLayerStrategy::Update,
+            },
+        )
+    }));
+
+    assert!(handle_layer_result.is_err());
+    assert_layer_restored(&context, &layer_name);
+}
+
+/// Asserts that the layer created by [`TestLayer`] is still in place, without any of the files
+/// written by a failed attempt to recreate or update it.
+fn assert_layer_restored(context: &BuildContext<TestBuildpack>, layer_name: &LayerName) {
+    let layer_dir = context.layers_dir.join(layer_name.as_str());
+
+    assert!(layer_dir.join(TEST_LAYER_CREATE_FILE_NAME).exists());
+    assert!(!layer_dir.join("failed-create").exists());
+    assert!(!layer_dir.join("failed-update").exists());
+
+    let layer_content_metadata: LayerContentMetadata<TestLayerMetadata> =
+        read_toml_file(context.layers_dir.join(format!("{}.toml", layer_name))).unwrap();
+    assert_eq!(layer_content_metadata.metadata.version, "1.0.0");
+
+    // Only the layer directory and its layer content metadata remain, no staging directory.
+    assert_eq!(fs::read_dir(&context.layers_dir).unwrap().count(), 2);
+}
+
+#[test]
+fn prune_unhandled_layers() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+
+    // Layers restored from a previous build with an older version of the buildpack.
+    fs::create_dir_all(context.layers_dir.join("old")).unwrap();
+    fs::write(context.layers_dir.join("old.toml"), "").unwrap();
+    fs::write(context.layers_dir.join("old-launch-only.toml"), "").unwrap();
+    fs::write(context.layers_dir.join("launch.toml"), "").unwrap();
+
+    context
+        .handle_layer(layer_name!("current"), TestLayer::default())
+        .unwrap();
+
+    assert_eq!(
+        context.existing_layers().unwrap(),
+        vec![
+            layer_name!("current"),
+            layer_name!("old"),
+            layer_name!("old-launch-only")
+        ]
+    );
+
+    assert_eq!(
+        context.prune_unhandled_layers().unwrap(),
+        vec![layer_name!("old"), layer_name!("old-launch-only")]
+    );
+
+    assert_eq!(
+        context.existing_layers().unwrap(),
+        vec![layer_name!("current")]
+    );
+    assert!(!context.layers_dir.join("old").exists());
+    assert!(context.layers_dir.join("launch.toml").exists());
+}
+
+#[test]
+fn handle_layers_concurrently() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+
+    let test_layer = |value: &str| TestLayer {
+        existing_layer_strategy: ExistingLayerStrategy::Recreate,
+        write_version: String::from(value),
+        write_layer_env: Some(LayerEnv::new().chainable_insert(
+            TargetLifecycle::Build,
+            ModificationBehavior::Append,
+            "VALUES",
+            value,
+        )),
+    };
+
+    let (foo_layer, bar_layer) = context
+        .handle_layers_concurrently(|layers| {
+            (
+                layers.handle_layer(layer_name!("foo"), test_layer("foo")),
+                layers.handle_layer(layer_name!("bar"), test_layer("bar")),
+            )
+        })
+        .unwrap();
+
+    let foo_layer_data = foo_layer.into_layer_data().unwrap();
+    assert_eq!(foo_layer_data.content_metadata.metadata.version, "foo");
+    assert!(foo_layer_data
+        .path
+        .join(TEST_LAYER_CREATE_FILE_NAME)
+        .exists());
+    assert_eq!(
+        bar_layer
+            .into_layer_data()
+            .unwrap()
+            .content_metadata
+            .metadata
+            .version,
+        "bar"
+    );
+
+    // Layer environments are applied in the order the layers were started.
+    assert_eq!(context.build_env().get("VALUES").unwrap(), "foobar");
+
+    assert_eq!(context.prune_unhandled_layers().unwrap(), vec![]);
+}
+
+#[test]
+fn handle_layers_concurrently_overlaps_layers() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+
+    // Each layer waits for the other one to be created at the same time, which never happens if
+    // the layers are handled one after another.
+    let barrier = Barrier::new(2);
+
+    let (foo_layer, bar_layer) = context
+        .handle_layers_concurrently(|layers| {
+            (
+                layers.handle_layer(layer_name!("foo"), BarrierTestLayer { barrier: &barrier }),
+                layers.handle_layer(layer_name!("bar"), BarrierTestLayer { barrier: &barrier }),
+            )
+        })
+        .unwrap();
+
+    assert!(foo_layer.into_layer_data().is_some());
+    assert!(bar_layer.into_layer_data().is_some());
+}
+
+#[test]
+fn handle_layers_concurrently_propagates_panics() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        context.handle_layers_concurrently(|layers| {
+            layers.handle_layer(
+                layer_name!("panicking"),
+                PanickingTestLayer {
+                    existing_layer_strategy: ExistingLayerStrategy::Recreate,
+                },
+            )
+        })
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(context.existing_layers().unwrap(), vec![]);
+}
+
+#[test]
+fn handle_layers_concurrently_errors() {
+    let temp_dir = tempdir().unwrap();
+    let context = build_context(&temp_dir);
+
+    let result = context.handle_layers_concurrently(|layers| {
+        (
+            layers.handle_layer(
+                layer_name!("failing"),
+                FailingTestLayer {
+                    existing_layer_strategy: ExistingLayerStrategy::Recreate,
+                },
+            ),
+            layers.handle_layer(layer_name!("succeeding"), TestLayer::default()),
+            layers.handle_layer(layer_name!("succeeding"), TestLayer::default()),
+        )
+    });
+
+    match result {
+        Err(crate::Error::ConcurrentLayerErrors(errors)) => {
+            assert_eq!(errors.len(), 2);
+
+            assert_eq!(errors[0].0, layer_name!("failing"));
+            assert!(matches!(errors[0].1, crate::Error::BuildpackError(_)));
+
+            assert_eq!(errors[1].0, layer_name!("succeeding"));
+            assert!(matches!(
+                &errors[1].1,
+                crate::Error::DuplicateConcurrentLayer(layer_name) if layer_name.as_str() == "succeeding"
+            ));
+        }
+        _ => panic!("Expected a ConcurrentLayerErrors error"),
+    }
+
+    // Layers that could be handled are kept, even if other layers failed.
+    assert_eq!(
+        context.existing_layers().unwrap(),
+        vec![layer_name!("succeeding")]
+    );
+}
+
 #[test]
 fn default_layer_method_implementations() {
     struct SimpleLayer;
@@ -808,6 +1528,15 @@ fn default_layer_method_implementations() {
         ExistingLayerStrategy::Recreate
     );
 
+    // Assert that the default existing_metadata_only_layer_strategy implementation always returns
+    // MetadataOnlyLayerStrategy::Recreate.
+    assert_eq!(
+        simple_layer
+            .existing_metadata_only_layer_strategy(&context, &layer_data.content_metadata)
+            .unwrap(),
+        MetadataOnlyLayerStrategy::Recreate
+    );
+
     // Assert that the default update implementation returns both the layer metadata and environment
     // they way they were.
     let update_result = simple_layer.update(&context, &layer_data).unwrap();
@@ -888,37 +1617,42 @@ fn layer_env_read_write() {
 }
 
 fn build_context(temp_dir: &TempDir) -> BuildContext<TestBuildpack> {
-    let layers_dir = temp_dir.path().join("layers");
     let app_dir = temp_dir.path().join("app");
     let buildpack_dir = temp_dir.path().join("buildpack");
 
-    fs::create_dir_all(&layers_dir).unwrap();
     fs::create_dir_all(&app_dir).unwrap();
     fs::create_dir_all(&buildpack_dir).unwrap();
+    fs::write(
+        buildpack_dir.join("buildpack.toml"),
+        format!(
+            r#"
+api = "{}"
+
+[buildpack]
+id = "libcnb/test"
+version = "1.0.0"
+clear-env = true
+
+[[stacks]]
+id = "*"
+"#,
+            LIBCNB_SUPPORTED_BUILDPACK_APIS.start()
+        ),
+    )
+    .unwrap();
 
-    BuildContext {
-        layers_dir,
-        app_dir,
-        buildpack_dir,
-        stack_id: stack_id!("heroku-20"),
-        platform: GenericPlatform::new(Env::new()),
-        buildpack_plan: BuildpackPlan { entries: vec![] },
-        buildpack_descriptor: SingleBuildpackDescriptor {
-            api: LIBCNB_SUPPORTED_BUILDPACK_API,
-            buildpack: crate::data::buildpack::Buildpack {
-                id: buildpack_id!("libcnb/test"),
-                name: None,
-                version: BuildpackVersion::new(1, 0, 0),
-                homepage: None,
-                clear_env: true,
-                description: None,
-                keywords: vec![],
-                licenses: vec![],
-            },
-            stacks: vec![Stack::Any],
-            metadata: GenericMetadata::default(),
-        },
-    }
+    // The temporary directory of the builder only contains the platform directory, which is not
+    // needed after the context has been built.
+    let (context, _) = BuildContextBuilder::new()
+        .layers_dir(temp_dir.path().join("layers"))
+        .app_dir(app_dir)
+        .buildpack_dir(buildpack_dir)
+        .stack_id(stack_id!("heroku-20"))
+        .build()
+        .unwrap()
+        .into_parts();
+
+    context
 }
 
 fn random_layer_name() -> LayerName {
@@ -949,5 +1683,6 @@ impl Buildpack for TestBuildpack {
 
 #[derive(Debug)]
 enum TestBuildpackError {
+    #[allow(dead_code)]
     IoError(std::io::Error),
 }
@@ -3,7 +3,7 @@
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
 use std::collections::{BTreeMap, HashMap};
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use std::fs;
 use std::path::Path;
 
@@ -127,6 +127,9 @@ impl LayerEnv {
 
     /// Applies this [`LayerEnv`] to the given [`Env`] for the given [target lifecycle](TargetLifecycle).
     ///
+    /// When targeting a specific process with [`TargetLifecycle::Process`], the process-specific
+    /// modifications are applied on top of the modifications for [`TargetLifecycle::Launch`].
+    ///
     /// # Example:
     ///```
     /// use libcnb::layer_env::{LayerEnv, TargetLifecycle, ModificationBehavior};
@@ -151,7 +154,9 @@ impl LayerEnv {
             TargetLifecycle::Build => vec![&self.all, &self.build, &self.layer_paths_build],
             TargetLifecycle::Launch => vec![&self.all, &self.launch, &self.layer_paths_launch],
             TargetLifecycle::Process(process) => {
-                let mut process_deltas = vec![&self.all];
+                // Process-specific modifications are applied on top of the launch modifications,
+                // the same way the CNB launcher does it.
+                let mut process_deltas = vec![&self.all, &self.launch, &self.layer_paths_launch];
                 if let Some(process_specific_delta) = self.process.get(&process) {
                     process_deltas.push(process_specific_delta);
                 }
@@ -315,8 +320,8 @@ impl LayerEnv {
                     _ => unreachable!("Unexpected TargetLifecycle in read_from_layer_dir implementation. This is a libcnb implementation error!"),
                 };
 
-                target_delta.insert(ModificationBehavior::Prepend, &name, &path);
-                target_delta.insert(ModificationBehavior::Delimiter, &name, PATH_LIST_SEPARATOR);
+                target_delta.insert(ModificationBehavior::Prepend, name, path);
+                target_delta.insert(ModificationBehavior::Delimiter, name, PATH_LIST_SEPARATOR);
             }
         }
 
@@ -332,7 +337,22 @@ impl LayerEnv {
 
         let env_launch_path = layer_dir.as_ref().join("env.launch");
         if env_launch_path.is_dir() {
-            result_layer_env.launch = LayerEnvDelta::read_from_env_dir(env_launch_path)?;
+            result_layer_env.launch = LayerEnvDelta::read_from_env_dir(&env_launch_path)?;
+
+            // Process-specific modifications are stored in sub-directories of `env.launch`, named
+            // after the process type.
+            for dir_entry in fs::read_dir(&env_launch_path)? {
+                let path = dir_entry?.path();
+
+                if let (true, Some(process_type_name)) =
+                    (path.is_dir(), path.file_name().and_then(OsStr::to_str))
+                {
+                    result_layer_env.process.insert(
+                        String::from(process_type_name),
+                        LayerEnvDelta::read_from_env_dir(&path)?,
+                    );
+                }
+            }
         }
 
         Ok(result_layer_env)
@@ -448,39 +468,39 @@ impl LayerEnvDelta {
         for ((modification_behavior, name), value) in &self.entries {
             match modification_behavior {
                 ModificationBehavior::Override => {
-                    result_env.insert(&name, &value);
+                    result_env.insert(name, value);
                 }
                 ModificationBehavior::Default => {
-                    if !result_env.contains_key(&name) {
-                        result_env.insert(&name, &value);
+                    if !result_env.contains_key(name) {
+                        result_env.insert(name, value);
                     }
                 }
                 ModificationBehavior::Append => {
-                    let mut previous_value = result_env.get(&name).unwrap_or_default();
+                    let mut previous_value = result_env.get(name).unwrap_or_default();
 
-                    if previous_value.len() > 0 {
-                        previous_value.push(self.delimiter_for(&name));
+                    if !previous_value.is_empty() {
+                        previous_value.push(self.delimiter_for(name));
                     }
 
-                    previous_value.push(&value);
+                    previous_value.push(value);
 
-                    result_env.insert(&name, previous_value);
+                    result_env.insert(name, previous_value);
                 }
                 ModificationBehavior::Prepend => {
-                    let previous_value = result_env.get(&name).unwrap_or_default();
+                    let previous_value = result_env.get(name).unwrap_or_default();
 
                     let mut new_value = OsString::new();
-                    new_value.push(&value);
+                    new_value.push(value);
 
                     if !previous_value.is_empty() {
-                        new_value.push(self.delimiter_for(&name));
+                        new_value.push(self.delimiter_for(name));
                         new_value.push(previous_value);
                     }
 
-                    result_env.insert(&name, new_value);
+                    result_env.insert(name, new_value);
                 }
                 ModificationBehavior::Delimiter => (),
-            };
+            }
         }
 
         result_env
@@ -510,6 +530,13 @@ impl LayerEnvDelta {
             // See: https://github.com/buildpacks/lifecycle/blob/a7428a55c2a14d8a37e84285b95dc63192e3264e/env/env.go#L73-L106
             use std::os::unix::ffi::OsStringExt;
             let path = dir_entry?.path();
+
+            // Directories are not part of this delta. The `env.launch` directory can contain
+            // process-specific sub-directories that are handled separately.
+            if path.is_dir() {
+                continue;
+            }
+
             let file_contents = OsString::from_vec(fs::read(&path)?);
 
             // Rely on the Rust standard library for splitting stem and extension. Since paths
@@ -571,7 +598,7 @@ impl LayerEnvDelta {
 
             let file_path = path.as_ref().join(file_name);
 
-            fs::write(file_path, &value.as_bytes())?;
+            fs::write(file_path, value.as_bytes())?;
         }
 
         Ok(())
@@ -590,6 +617,9 @@ impl LayerEnvDelta {
     }
 }
 
+#[cfg(target_family = "unix")]
+const PATH_LIST_SEPARATOR: &str = ":";
+
 #[cfg(test)]
 mod tests {
     use std::cmp::Ordering;
@@ -769,8 +799,8 @@ mod tests {
 
         let temp_dir = tempdir().unwrap();
 
-        original_delta.write_to_env_dir(&temp_dir.path()).unwrap();
-        let disk_delta = LayerEnvDelta::read_from_env_dir(&temp_dir.path()).unwrap();
+        original_delta.write_to_env_dir(temp_dir.path()).unwrap();
+        let disk_delta = LayerEnvDelta::read_from_env_dir(temp_dir.path()).unwrap();
 
         assert_eq!(original_delta, disk_delta);
     }
@@ -877,7 +907,7 @@ mod tests {
         fs::create_dir_all(layer_dir.join("include")).unwrap();
         fs::create_dir_all(layer_dir.join("pkgconfig")).unwrap();
 
-        let layer_env = LayerEnv::read_from_layer_dir(&layer_dir).unwrap();
+        let layer_env = LayerEnv::read_from_layer_dir(layer_dir).unwrap();
         let env = Env::new();
 
         let modified_env = layer_env.apply(TargetLifecycle::Launch, &env);
@@ -902,7 +932,7 @@ mod tests {
         fs::create_dir_all(layer_dir.join("include")).unwrap();
         fs::create_dir_all(layer_dir.join("pkgconfig")).unwrap();
 
-        let layer_env = LayerEnv::read_from_layer_dir(&layer_dir).unwrap();
+        let layer_env = LayerEnv::read_from_layer_dir(layer_dir).unwrap();
         let env = Env::new();
 
         let modified_env = layer_env.apply(TargetLifecycle::Build, &env);
@@ -925,6 +955,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn read_from_layer_dir_process_specific() {
+        let temp_dir = tempdir().unwrap();
+        let layer_dir = temp_dir.path();
+
+        let layer_env = LayerEnv::new()
+            .chainable_insert(
+                TargetLifecycle::Launch,
+                ModificationBehavior::Override,
+                "JAVA_OPTS",
+                "-Xmx1G",
+            )
+            .chainable_insert(
+                TargetLifecycle::Process(String::from("web")),
+                ModificationBehavior::Append,
+                "JAVA_OPTS",
+                " -Dweb=true",
+            )
+            .chainable_insert(
+                TargetLifecycle::Process(String::from("worker")),
+                ModificationBehavior::Override,
+                "WORKER",
+                "true",
+            );
+
+        layer_env.write_to_layer_dir(layer_dir).unwrap();
+        let read_layer_env = LayerEnv::read_from_layer_dir(layer_dir).unwrap();
+
+        assert_eq!(read_layer_env, layer_env);
+    }
+
+    #[test]
+    fn apply_process_specific_over_launch() {
+        let layer_env = LayerEnv::new()
+            .chainable_insert(
+                TargetLifecycle::Launch,
+                ModificationBehavior::Override,
+                "JAVA_OPTS",
+                "-Xmx1G",
+            )
+            .chainable_insert(
+                TargetLifecycle::Launch,
+                ModificationBehavior::Override,
+                "LAUNCH",
+                "true",
+            )
+            .chainable_insert(
+                TargetLifecycle::Process(String::from("web")),
+                ModificationBehavior::Append,
+                "JAVA_OPTS",
+                " -Dweb=true",
+            );
+
+        let web_env = layer_env.apply(TargetLifecycle::Process(String::from("web")), &Env::new());
+        assert_eq!(
+            environment_as_sorted_vector(&web_env),
+            vec![("JAVA_OPTS", "-Xmx1G -Dweb=true"), ("LAUNCH", "true")]
+        );
+
+        let worker_env = layer_env.apply(
+            TargetLifecycle::Process(String::from("worker")),
+            &Env::new(),
+        );
+        assert_eq!(
+            environment_as_sorted_vector(&worker_env),
+            vec![("JAVA_OPTS", "-Xmx1G"), ("LAUNCH", "true")]
+        );
+    }
+
     fn environment_as_sorted_vector(environment: &Env) -> Vec<(&str, &str)> {
         let mut result: Vec<(&str, &str)> = environment
             .iter()
@@ -936,8 +1035,5 @@ mod tests {
     }
 }
 
-#[cfg(target_family = "unix")]
-const PATH_LIST_SEPARATOR: &str = ":";
-
 #[cfg(target_family = "windows")]
 const PATH_LIST_SEPARATOR: &str = ";";
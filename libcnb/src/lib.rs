@@ -12,11 +12,17 @@
 // This lint triggers when both layer_dir and layers_dir are present which are quite common.
 #![allow(clippy::similar_names)]
 
+pub mod bindings;
 pub mod build;
 pub mod detect;
+pub mod exec_d;
 pub mod generic;
+#[doc(hidden)]
+pub mod internals;
 pub mod layer;
 pub mod layer_env;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 mod buildpack;
 mod env;
@@ -35,10 +41,25 @@ pub use platform::*;
 pub use toml_file::*;
 
 pub use buildpack::Buildpack;
-pub use runtime::libcnb_runtime;
+pub use libcnb_proc_macros::Platform;
+pub use runtime::{libcnb_exec_d_runtime, libcnb_runtime};
 
-const LIBCNB_SUPPORTED_BUILDPACK_API: data::buildpack::BuildpackApi =
-    data::buildpack::BuildpackApi { major: 0, minor: 6 };
+/// The range of Buildpack API versions supported by this version of libcnb.
+///
+/// Buildpacks declare the API version they target in `buildpack.toml`. libcnb will refuse to run
+/// buildpacks that target a version outside of this range.
+///
+/// Apart from how the phase inputs are read and the deprecation warning for BOM tables in
+/// `launch.toml`, libcnb behaves the same for all versions in this range: the files it writes
+/// follow Buildpack API 0.6.
+///
+/// The same range is declared in the `[package.metadata.libcnb]` table of the manifest of libcnb,
+/// where `cargo libcnb check` reads it. A test ensures that both stay in sync.
+const LIBCNB_SUPPORTED_BUILDPACK_APIS: std::ops::RangeInclusive<data::buildpack::BuildpackApi> =
+    data::buildpack::BuildpackApi { major: 0, minor: 6 }..=data::buildpack::BuildpackApi {
+        major: 0,
+        minor: 8,
+    };
 
 /// Generates a main function for the given buildpack.
 ///
@@ -78,8 +99,62 @@ macro_rules! buildpack_main {
     };
 }
 
+/// Generates a main function for the given exec.d program.
+///
+/// # Example:
+/// ```
+/// use libcnb::data::exec_d::ExecDProgramOutput;
+/// use libcnb::exec_d::ExecDProgram;
+/// use libcnb::generic::GenericError;
+/// use libcnb::{exec_d_main, Env};
+///
+/// struct MemoryLimitProgram;
+///
+/// impl ExecDProgram for MemoryLimitProgram {
+///     type Error = GenericError;
+///
+///     fn exec_d(&self, env: &Env) -> Result<ExecDProgramOutput, Self::Error> {
+///         Ok(vec![("JAVA_OPTS", "-Xmx512m")].into_iter().collect())
+///     }
+/// }
+///
+/// exec_d_main!(MemoryLimitProgram);
+/// ```
+#[macro_export]
+macro_rules! exec_d_main {
+    ($program:expr) => {
+        fn main() {
+            ::libcnb::libcnb_exec_d_runtime(&$program);
+        }
+    };
+}
+
+// Allows tests in this crate to use the derive macros, which refer to `::libcnb`.
+#[cfg(test)]
+extern crate self as libcnb;
+
 // This runs the README.md as a doctest, ensuring the code examples in it are valid.
 // It will not be part of the final crate.
 #[cfg(doctest)]
 #[doc = include_str!("../../README.md")]
 pub struct ReadmeDoctests;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supported_buildpack_apis_match_package_metadata() {
+        let manifest: toml::Value = toml::from_str(include_str!("../Cargo.toml")).unwrap();
+        let libcnb_metadata = &manifest["package"]["metadata"]["libcnb"];
+
+        assert_eq!(
+            libcnb_metadata["min-buildpack-api"].as_str(),
+            Some(LIBCNB_SUPPORTED_BUILDPACK_APIS.start().to_string().as_str())
+        );
+        assert_eq!(
+            libcnb_metadata["max-buildpack-api"].as_str(),
+            Some(LIBCNB_SUPPORTED_BUILDPACK_APIS.end().to_string().as_str())
+        );
+    }
+}
@@ -1,7 +1,9 @@
+use crate::data::buildpack::BuildpackVersion;
 use crate::Env;
+use std::convert::TryFrom;
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Represents a Cloud Native Buildpack platform.
 ///
@@ -10,6 +12,59 @@ use std::path::Path;
 /// [detection](https://github.com/buildpacks/spec/blob/main/buildpack.md#detection) and
 /// [build](https://github.com/buildpacks/spec/blob/main/buildpack.md#build) in the buildpack
 /// specification for details.
+///
+/// # Deriving
+/// Buildpacks that are configured with environment variables, such as `BP_*` variables, can derive
+/// this trait to get typed configuration. Each field is read from the environment variable with
+/// the upper-cased field name, unless another name is given with `var`. Field types must implement
+/// [`PlatformEnvValue`]:
+///
+/// - `Option<T>` fields are `None` when the variable is not set.
+/// - `Vec<T>` fields are split at `separator` (`,` by default) and are empty when the variable is not set.
+/// - All other fields are required, unless a `default` is given.
+///
+/// The [`Env`] field must be marked with `#[platform(env)]`. A [`crate::bindings::Bindings`] field
+/// marked with `#[platform(bindings)]` receives the service bindings of the platform. Deriving also
+/// adds a `from_env` function, that reports invalid configuration as a [`PlatformEnvError`] naming
+/// the variable. `from_path` returns the same error wrapped in an [`io::Error`].
+///
+/// ```
+/// use libcnb::bindings::Bindings;
+/// use libcnb::data::buildpack::BuildpackVersion;
+/// use libcnb::{Env, Platform, PlatformEnvError};
+///
+/// #[derive(Platform)]
+/// struct NodePlatform {
+///     #[platform(env)]
+///     env: Env,
+///     #[platform(bindings)]
+///     bindings: Bindings,
+///     #[platform(var = "BP_NODE_VERSION")]
+///     node_version: Option<BuildpackVersion>,
+///     #[platform(var = "BP_NODE_WORKERS", default = "4")]
+///     workers: u16,
+///     #[platform(var = "BP_NODE_RUN_SCRIPTS", separator = ",")]
+///     run_scripts: Vec<String>,
+///     #[platform(var = "BP_NODE_PRUNE")]
+///     prune: bool,
+/// }
+///
+/// let mut env = Env::new();
+/// env.insert("BP_NODE_VERSION", "16.13.1");
+/// env.insert("BP_NODE_RUN_SCRIPTS", "lint,build");
+/// env.insert("BP_NODE_PRUNE", "true");
+///
+/// let platform = NodePlatform::from_env(env).unwrap();
+/// assert_eq!(platform.node_version, Some(BuildpackVersion::new(16, 13, 1)));
+/// assert_eq!(platform.workers, 4);
+/// assert_eq!(platform.run_scripts, vec!["lint", "build"]);
+/// assert!(platform.prune);
+///
+/// assert!(matches!(
+///     NodePlatform::from_env(Env::new()),
+///     Err(PlatformEnvError::MissingVariable(name)) if name == "BP_NODE_PRUNE"
+/// ));
+/// ```
 pub trait Platform
 where
     Self: Sized,
@@ -29,8 +84,14 @@ where
     fn from_path(platform_dir: impl AsRef<Path>) -> io::Result<Self>;
 }
 
-/// Initializes a new `Env` based on the given platform directory.
-pub(crate) fn read_platform_env(platform_dir: impl AsRef<Path>) -> std::io::Result<Env> {
+/// Initializes a new `Env` based on the `env` directory of the given platform directory.
+///
+/// A missing `env` directory results in an empty `Env`.
+///
+/// # Errors
+///
+/// Will return `Err` if the `env` directory or one of its files could not be read.
+pub fn read_platform_env(platform_dir: impl AsRef<Path>) -> std::io::Result<Env> {
     let env_path = platform_dir.as_ref().join("env");
     let mut env_vars = Env::new();
 
@@ -61,6 +122,77 @@ pub(crate) fn read_platform_env(platform_dir: impl AsRef<Path>) -> std::io::Resu
     Ok(env_vars)
 }
 
+/// A type that can be parsed from the value of a platform environment variable.
+///
+/// Used by `#[derive(Platform)]` to parse field values. Implement this trait to use custom types
+/// as fields.
+pub trait PlatformEnvValue: Sized {
+    /// Parses the value, returning a human-readable reason if it is invalid.
+    fn from_platform_env_value(value: &str) -> Result<Self, String>;
+}
+
+impl PlatformEnvValue for bool {
+    /// Accepts `true`, `false`, `yes`, `no`, `1` and `0`, ignoring case.
+    fn from_platform_env_value(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "true" | "yes" | "1" => Ok(true),
+            "false" | "no" | "0" => Ok(false),
+            _ => Err(String::from("expected one of true, false, yes, no, 1 or 0")),
+        }
+    }
+}
+
+impl PlatformEnvValue for String {
+    fn from_platform_env_value(value: &str) -> Result<Self, String> {
+        Ok(String::from(value))
+    }
+}
+
+impl PlatformEnvValue for PathBuf {
+    fn from_platform_env_value(value: &str) -> Result<Self, String> {
+        Ok(PathBuf::from(value))
+    }
+}
+
+impl PlatformEnvValue for BuildpackVersion {
+    fn from_platform_env_value(value: &str) -> Result<Self, String> {
+        BuildpackVersion::try_from(value.trim()).map_err(|error| error.to_string())
+    }
+}
+
+macro_rules! impl_platform_env_value_from_str {
+    ($($ty:ty),*) => {
+        $(
+            impl PlatformEnvValue for $ty {
+                fn from_platform_env_value(value: &str) -> Result<Self, String> {
+                    value.trim().parse().map_err(|error| format!("{}", error))
+                }
+            }
+        )*
+    };
+}
+
+impl_platform_env_value_from_str!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+/// An error that occurred while reading a platform from environment variables.
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum PlatformEnvError {
+    #[error("Required environment variable {0} is not set")]
+    MissingVariable(String),
+
+    #[error("Environment variable {0} is not valid unicode")]
+    InvalidUnicode(String),
+
+    #[error("Invalid value `{value}` for environment variable {name}: {reason}")]
+    InvalidValue {
+        name: String,
+        value: String,
+        reason: String,
+    },
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,6 +243,88 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[derive(Debug, crate::Platform)]
+    struct TestPlatform {
+        #[platform(env)]
+        env: Env,
+        #[platform(bindings)]
+        bindings: crate::bindings::Bindings,
+        #[platform(var = "BP_DEBUG", default = "false")]
+        debug: bool,
+        #[platform(var = "BP_WORKERS")]
+        workers: u32,
+        #[platform(var = "BP_VERSION")]
+        version: Option<BuildpackVersion>,
+        #[platform(var = "BP_ARGS", separator = " ")]
+        args: Vec<String>,
+        log_level: Option<String>,
+    }
+
+    #[test]
+    fn derived_platform_parses_env_vars() {
+        let mut env = Env::new();
+        env.insert("BP_WORKERS", " 8 ");
+        env.insert("BP_VERSION", "1.2.3");
+        env.insert("BP_ARGS", "--foo  --bar");
+        env.insert("LOG_LEVEL", "debug");
+
+        let platform = TestPlatform::from_env(env).unwrap();
+        assert!(!platform.debug);
+        assert_eq!(platform.workers, 8);
+        assert_eq!(platform.version, Some(BuildpackVersion::new(1, 2, 3)));
+        assert_eq!(platform.args, vec!["--foo", "--bar"]);
+        assert_eq!(platform.log_level, Some(String::from("debug")));
+        assert!(platform.bindings.is_empty());
+        assert_eq!(
+            platform.env().get("BP_WORKERS"),
+            Some(OsString::from(" 8 "))
+        );
+    }
+
+    #[test]
+    fn derived_platform_reports_offending_variable() {
+        let mut env = Env::new();
+        env.insert("BP_WORKERS", "4");
+        env.insert("BP_DEBUG", "maybe");
+
+        assert_eq!(
+            TestPlatform::from_env(env).unwrap_err(),
+            PlatformEnvError::InvalidValue {
+                name: String::from("BP_DEBUG"),
+                value: String::from("maybe"),
+                reason: String::from("expected one of true, false, yes, no, 1 or 0")
+            }
+        );
+
+        assert_eq!(
+            TestPlatform::from_env(Env::new()).unwrap_err(),
+            PlatformEnvError::MissingVariable(String::from("BP_WORKERS"))
+        );
+    }
+
+    #[test]
+    fn derived_platform_from_path() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let env_dir = tmpdir.path().join("env");
+        fs::create_dir(&env_dir).unwrap();
+        fs::write(env_dir.join("BP_WORKERS"), "2").unwrap();
+        let binding_dir = tmpdir.path().join("bindings").join("certs");
+        fs::create_dir_all(&binding_dir).unwrap();
+        fs::write(binding_dir.join("type"), "ca-certificates").unwrap();
+
+        let platform = TestPlatform::from_path(tmpdir.path()).unwrap();
+        assert_eq!(platform.workers, 2);
+        assert!(platform.bindings.get("certs").is_some());
+
+        fs::write(env_dir.join("BP_WORKERS"), "many").unwrap();
+        let error = TestPlatform::from_path(tmpdir.path()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            error.get_ref().and_then(|error| error.downcast_ref::<PlatformEnvError>()),
+            Some(PlatformEnvError::InvalidValue { name, .. }) if name == "BP_WORKERS"
+        ));
+    }
+
     #[test]
     fn read_platform_env_does_not_blow_up_if_platform_env_is_missing() {
         let tmpdir = tempfile::tempdir().unwrap();
@@ -3,17 +3,21 @@ use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::process;
 use std::process::exit;
+use std::sync::Mutex;
 
 use serde::de::DeserializeOwned;
 
-use crate::build::{BuildContext, InnerBuildResult};
+use crate::build::{initial_build_env, BuildContext, InnerBuildResult};
 use crate::buildpack::Buildpack;
-use crate::data::buildpack::{SingleBuildpackDescriptor, StackId};
+use crate::data::buildpack::{BuildpackApi, SingleBuildpackDescriptor, Stack, StackId};
+use crate::data::buildpack_plan::BuildpackPlan;
 use crate::detect::{DetectContext, InnerDetectResult};
 use crate::error::Error;
+use crate::exec_d::ExecDProgram;
 use crate::platform::Platform;
 use crate::toml_file::{read_toml_file, write_toml_file};
-use crate::LIBCNB_SUPPORTED_BUILDPACK_API;
+use crate::Env;
+use crate::LIBCNB_SUPPORTED_BUILDPACK_APIS;
 use std::fmt::Debug;
 
 /// Main entry point for this framework.
@@ -29,9 +33,9 @@ use std::fmt::Debug;
 /// Don't implement this directly and use the [`buildpack_main`] macro instead!
 #[doc(hidden)]
 pub fn libcnb_runtime<B: Buildpack>(buildpack: &B) {
-    match read_buildpack_descriptor::<B::Metadata, B::Error>() {
+    let buildpack_descriptor = match read_buildpack_descriptor::<B::Metadata, B::Error>() {
         Ok(buildpack_descriptor) => {
-            if buildpack_descriptor.api != LIBCNB_SUPPORTED_BUILDPACK_API {
+            if !LIBCNB_SUPPORTED_BUILDPACK_APIS.contains(&buildpack_descriptor.api) {
                 eprintln!("Error: Cloud Native Buildpack API mismatch");
                 eprintln!(
                     "This buildpack ({}) uses Cloud Native Buildpacks API version {}.",
@@ -39,17 +43,20 @@ pub fn libcnb_runtime<B: Buildpack>(buildpack: &B) {
                 );
 
                 eprintln!(
-                    "But the underlying libcnb.rs library requires CNB API {}.",
-                    LIBCNB_SUPPORTED_BUILDPACK_API
+                    "But the underlying libcnb.rs library supports CNB API {} to {}.",
+                    LIBCNB_SUPPORTED_BUILDPACK_APIS.start(),
+                    LIBCNB_SUPPORTED_BUILDPACK_APIS.end()
                 );
 
                 exit(254)
             }
+
+            buildpack_descriptor
         }
         Err(lib_cnb_error) => {
             exit(buildpack.handle_error(lib_cnb_error));
         }
-    }
+    };
 
     // Using `std::env::args()` instead of `std::env::current_exe()` since the latter resolves
     // symlinks to their target on some platforms, whereas we need the original filename.
@@ -60,10 +67,10 @@ pub fn libcnb_runtime<B: Buildpack>(buildpack: &B) {
         .and_then(Path::file_name)
         .and_then(OsStr::to_str);
 
-    #[cfg(any(target_family = "unix"))]
+    #[cfg(target_family = "unix")]
     let result = match current_exe_file_name {
-        Some("detect") => libcnb_runtime_detect(buildpack),
-        Some("build") => libcnb_runtime_build(buildpack),
+        Some("detect") => libcnb_runtime_detect(buildpack, buildpack_descriptor),
+        Some("build") => libcnb_runtime_build(buildpack, buildpack_descriptor),
         other => {
             eprintln!(
                 "Error: Expected the name of this executable to be 'detect' or 'build', but it was '{}'",
@@ -81,8 +88,55 @@ pub fn libcnb_runtime<B: Buildpack>(buildpack: &B) {
     }
 }
 
-fn libcnb_runtime_detect<B: Buildpack>(buildpack: &B) -> crate::Result<(), B::Error> {
-    let args = parse_detect_args_or_exit();
+/// Main entry point for exec.d programs.
+///
+/// Runs the given [`ExecDProgram`] with the current environment and writes its output as TOML to
+/// file descriptor 3, as required by the CNB spec.
+///
+/// Don't implement this directly and use the [`exec_d_main`] macro instead!
+#[doc(hidden)]
+pub fn libcnb_exec_d_runtime<P: ExecDProgram>(program: &P) {
+    let result: crate::Result<(), P::Error> = program
+        .exec_d(&Env::from_current())
+        .map_err(Error::BuildpackError)
+        .and_then(|output| {
+            #[cfg(target_family = "unix")]
+            {
+                use crate::exec_d::write_exec_d_program_output;
+                use std::os::unix::io::FromRawFd;
+
+                // SAFETY: The CNB launcher opens file descriptor 3 for exec.d programs to write their
+                // output to. It is not used anywhere else in this process.
+                let output_file = unsafe { std::fs::File::from_raw_fd(3) };
+
+                write_exec_d_program_output(&output, output_file)
+                    .map_err(Error::CannotWriteExecDProgramOutput)
+            }
+
+            // The CNB spec only defines how exec.d programs pass their output on Unix.
+            #[cfg(not(target_family = "unix"))]
+            {
+                let _ = output;
+
+                Err(Error::CannotWriteExecDProgramOutput(
+                    crate::exec_d::WriteExecDProgramOutputError::IoError(std::io::Error::new(
+                        std::io::ErrorKind::Unsupported,
+                        "exec.d programs are only supported on Unix",
+                    )),
+                ))
+            }
+        });
+
+    if let Err(lib_cnb_error) = result {
+        exit(program.handle_error(lib_cnb_error));
+    }
+}
+
+fn libcnb_runtime_detect<B: Buildpack>(
+    buildpack: &B,
+    buildpack_descriptor: SingleBuildpackDescriptor<B::Metadata>,
+) -> crate::Result<(), B::Error> {
+    let args = parse_detect_args_or_exit(buildpack_descriptor.api);
 
     let app_dir = env::current_dir().map_err(Error::CannotDetermineAppDirectory)?;
 
@@ -90,8 +144,8 @@ fn libcnb_runtime_detect<B: Buildpack>(buildpack: &B) -> crate::Result<(), B::Er
         .map_err(Error::CannotDetermineStackId)
         .and_then(|stack_id_string| stack_id_string.parse().map_err(Error::StackIdError))?;
 
-    let platform = B::Platform::from_path(&args.platform_dir_path)
-        .map_err(Error::CannotCreatePlatformFromPath)?;
+    let platform =
+        B::Platform::from_path(&args.platform_dir).map_err(Error::CannotCreatePlatformFromPath)?;
 
     let build_plan_path = args.build_plan_path;
 
@@ -100,26 +154,61 @@ fn libcnb_runtime_detect<B: Buildpack>(buildpack: &B) -> crate::Result<(), B::Er
         stack_id,
         platform,
         buildpack_dir: read_buildpack_dir()?,
-        buildpack_descriptor: read_buildpack_descriptor()?,
+        buildpack_descriptor,
     };
 
-    match buildpack.detect(detect_context)?.0 {
-        InnerDetectResult::Fail => process::exit(100),
+    let stack_mixins = read_stack_mixins()?;
+
+    if run_detect(
+        buildpack,
+        detect_context,
+        stack_mixins.as_deref(),
+        &build_plan_path,
+    )? {
+        process::exit(0)
+    } else {
+        process::exit(100)
+    }
+}
+
+/// Runs the detect phase of the given buildpack with the given context and writes its outputs.
+///
+/// Before detection, the stack of the context is checked against the stacks the buildpack
+/// supports. Mixins are only checked if the mixins of the stack are known.
+///
+/// Returns if the buildpack passed detection.
+pub(crate) fn run_detect<B: Buildpack>(
+    buildpack: &B,
+    context: DetectContext<B>,
+    stack_mixins: Option<&[String]>,
+    build_plan_path: &Path,
+) -> crate::Result<bool, B::Error> {
+    check_stack_compatibility(
+        &context.buildpack_descriptor.stacks,
+        &context.stack_id,
+        stack_mixins,
+    )?;
+
+    match buildpack.detect(context)?.0 {
+        InnerDetectResult::Fail => Ok(false),
         InnerDetectResult::Pass { build_plan } => {
             if let Some(build_plan) = build_plan {
                 write_toml_file(&build_plan, build_plan_path)
                     .map_err(Error::CannotWriteBuildPlan)?;
             }
 
-            process::exit(0)
+            Ok(true)
         }
     }
 }
 
-fn libcnb_runtime_build<B: Buildpack>(buildpack: &B) -> crate::Result<(), B::Error> {
-    let args = parse_build_args_or_exit();
+fn libcnb_runtime_build<B: Buildpack>(
+    buildpack: &B,
+    buildpack_descriptor: SingleBuildpackDescriptor<B::Metadata>,
+) -> crate::Result<(), B::Error> {
+    let args = parse_build_args_or_exit(buildpack_descriptor.api);
 
-    let layers_dir = args.layers_dir_path;
+    let layers_dir = args.layers_dir;
 
     let app_dir = env::current_dir().map_err(Error::CannotDetermineAppDirectory)?;
 
@@ -127,55 +216,123 @@ fn libcnb_runtime_build<B: Buildpack>(buildpack: &B) -> crate::Result<(), B::Err
         .map_err(Error::CannotDetermineStackId)
         .and_then(|stack_id_string| stack_id_string.parse().map_err(Error::StackIdError))?;
 
-    let platform = B::Platform::from_path(&args.platform_dir_path)
-        .map_err(Error::CannotCreatePlatformFromPath)?;
+    let platform =
+        B::Platform::from_path(&args.platform_dir).map_err(Error::CannotCreatePlatformFromPath)?;
 
-    let buildpack_plan =
+    let buildpack_plan: BuildpackPlan =
         read_toml_file(&args.buildpack_plan_path).map_err(Error::CannotReadBuildpackPlan)?;
 
-    let build_result = buildpack.build(BuildContext {
-        layers_dir: layers_dir.clone(),
-        app_dir,
-        stack_id,
-        platform,
-        buildpack_plan,
-        buildpack_dir: read_buildpack_dir()?,
-        buildpack_descriptor: read_buildpack_descriptor()?,
-    })?;
+    let build_env = initial_build_env(buildpack_descriptor.buildpack.clear_env, &platform);
+
+    run_build(
+        buildpack,
+        BuildContext {
+            layers_dir,
+            app_dir,
+            stack_id,
+            platform,
+            buildpack_plan,
+            buildpack_dir: read_buildpack_dir()?,
+            buildpack_descriptor,
+            build_env: Mutex::new(build_env),
+            handled_layers: Mutex::new(vec![]),
+        },
+    )?;
+
+    process::exit(0)
+}
+
+/// Runs the build phase of the given buildpack with the given context and writes its outputs to
+/// the layers directory of the context.
+pub(crate) fn run_build<B: Buildpack>(
+    buildpack: &B,
+    context: BuildContext<B>,
+) -> crate::Result<(), B::Error> {
+    let buildpack_api = context.buildpack_api();
+    let layers_dir = context.layers_dir.clone();
+
+    let buildpack_plan_entry_names: Vec<String> = context
+        .buildpack_plan
+        .entries
+        .iter()
+        .map(|entry| entry.name.clone())
+        .collect();
+
+    match buildpack.build(context)?.0 {
+        InnerBuildResult::Pass {
+            launch,
+            store,
+            build,
+        } => {
+            if let Some(unmet) = build
+                .iter()
+                .flat_map(|build| &build.unmet)
+                .find(|unmet| !buildpack_plan_entry_names.contains(&unmet.name))
+            {
+                return Err(Error::UnmetEntryNotInBuildpackPlan(unmet.name.clone()));
+            }
 
-    match build_result.0 {
-        InnerBuildResult::Pass { launch, store } => {
             if let Some(launch) = launch {
+                if buildpack_api >= BUILDPACK_API_0_7 && !launch.bom.is_empty() {
+                    eprintln!("Warning: The BOM table in launch.toml is deprecated since Buildpack API 0.7.");
+                    eprintln!("Buildpacks should write BOM information to <layer>.sbom.<ext> or launch.sbom.<ext> instead.");
+                }
+
                 write_toml_file(&launch, layers_dir.join("launch.toml"))
                     .map_err(Error::CannotWriteLaunch)?;
-            };
+            }
 
             if let Some(store) = store {
                 write_toml_file(&store, layers_dir.join("store.toml"))
                     .map_err(Error::CannotWriteStore)?;
-            };
+            }
+
+            if let Some(build) = build {
+                write_toml_file(&build, layers_dir.join("build.toml"))
+                    .map_err(Error::CannotWriteBuild)?;
+            }
 
-            process::exit(0)
+            Ok(())
         }
     }
 }
 
+/// Starting with this Buildpack API version, the lifecycle also passes the paths of the
+/// `bin/detect` and `bin/build` inputs as `CNB_*` environment variables.
+const BUILDPACK_API_0_8: BuildpackApi = BuildpackApi { major: 0, minor: 8 };
+
+/// Starting with this Buildpack API version, the BOM table in `launch.toml` is deprecated in favour
+/// of SBOM files.
+const BUILDPACK_API_0_7: BuildpackApi = BuildpackApi { major: 0, minor: 7 };
+
 struct DetectArgs {
-    pub platform_dir_path: PathBuf,
+    pub platform_dir: PathBuf,
     pub build_plan_path: PathBuf,
 }
 
 struct BuildArgs {
-    pub layers_dir_path: PathBuf,
-    pub platform_dir_path: PathBuf,
+    pub layers_dir: PathBuf,
+    pub platform_dir: PathBuf,
     pub buildpack_plan_path: PathBuf,
 }
 
-fn parse_detect_args_or_exit() -> DetectArgs {
+fn parse_detect_args_or_exit(buildpack_api: BuildpackApi) -> DetectArgs {
+    if buildpack_api >= BUILDPACK_API_0_8 {
+        if let (Some(platform_dir), Some(build_plan_path)) = (
+            env::var_os("CNB_PLATFORM_DIR"),
+            env::var_os("CNB_BUILD_PLAN_PATH"),
+        ) {
+            return DetectArgs {
+                platform_dir: PathBuf::from(platform_dir),
+                build_plan_path: PathBuf::from(build_plan_path),
+            };
+        }
+    }
+
     let args: Vec<String> = env::args().collect();
-    if let [_, platform_dir_path, build_plan_path] = args.as_slice() {
+    if let [_, platform_dir, build_plan_path] = args.as_slice() {
         DetectArgs {
-            platform_dir_path: PathBuf::from(platform_dir_path),
+            platform_dir: PathBuf::from(platform_dir),
             build_plan_path: PathBuf::from(build_plan_path),
         }
     } else {
@@ -185,12 +342,26 @@ fn parse_detect_args_or_exit() -> DetectArgs {
     }
 }
 
-fn parse_build_args_or_exit() -> BuildArgs {
+fn parse_build_args_or_exit(buildpack_api: BuildpackApi) -> BuildArgs {
+    if buildpack_api >= BUILDPACK_API_0_8 {
+        if let (Some(layers_dir), Some(platform_dir), Some(buildpack_plan_path)) = (
+            env::var_os("CNB_LAYERS_DIR"),
+            env::var_os("CNB_PLATFORM_DIR"),
+            env::var_os("CNB_BP_PLAN_PATH"),
+        ) {
+            return BuildArgs {
+                layers_dir: PathBuf::from(layers_dir),
+                platform_dir: PathBuf::from(platform_dir),
+                buildpack_plan_path: PathBuf::from(buildpack_plan_path),
+            };
+        }
+    }
+
     let args: Vec<String> = env::args().collect();
-    if let [_, layers_dir_path, platform_dir_path, buildpack_plan_path] = args.as_slice() {
+    if let [_, layers_dir, platform_dir, buildpack_plan_path] = args.as_slice() {
         BuildArgs {
-            layers_dir_path: PathBuf::from(layers_dir_path),
-            platform_dir_path: PathBuf::from(platform_dir_path),
+            layers_dir: PathBuf::from(layers_dir),
+            platform_dir: PathBuf::from(platform_dir),
             buildpack_plan_path: PathBuf::from(buildpack_plan_path),
         }
     } else {
@@ -200,6 +371,67 @@ fn parse_build_args_or_exit() -> BuildArgs {
     }
 }
 
+/// Checks that the given stack is one of the given supported stacks and, if the mixins of the
+/// stack are known, that it provides all mixins the buildpack requires for it.
+///
+/// Mixins prefixed with `run:` are only required for the run image and are not checked.
+fn check_stack_compatibility<E: Debug>(
+    supported_stacks: &[Stack],
+    stack_id: &StackId,
+    stack_mixins: Option<&[String]>,
+) -> crate::Result<(), E> {
+    // A stack that is listed explicitly takes precedence over `*`, which requires no mixins.
+    let required_mixins = supported_stacks
+        .iter()
+        .find_map(|stack| match stack {
+            Stack::Specific { id, mixins } if id == stack_id => Some(mixins.as_slice()),
+            Stack::Specific { .. } | Stack::Any => None,
+        })
+        .or_else(|| supported_stacks.contains(&Stack::Any).then(|| &[][..]))
+        .ok_or_else(|| Error::UnsupportedStack(stack_id.clone()))?;
+
+    if let Some(stack_mixins) = stack_mixins {
+        let missing_mixins: Vec<String> = required_mixins
+            .iter()
+            .filter(|mixin| {
+                // Detection runs on the build image. Unprefixed mixins are provided by both the
+                // build and run image, so they satisfy `build:` requirements as well.
+                !mixin.starts_with("run:")
+                    && !stack_mixins.iter().any(|stack_mixin| {
+                        stack_mixin.trim_start_matches("build:")
+                            == mixin.trim_start_matches("build:")
+                    })
+            })
+            .cloned()
+            .collect();
+
+        if !missing_mixins.is_empty() {
+            return Err(Error::MissingStackMixins {
+                stack_id: stack_id.clone(),
+                mixins: missing_mixins,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the mixins of the current stack from `CNB_STACK_MIXINS`, if set.
+///
+/// The lifecycle does not pass mixins to buildpacks. Platforms that want libcnb to check mixins
+/// can provide them in the format of the `io.buildpacks.stack.mixins` image label, a JSON array of
+/// strings such as `["libpq-dev", "build:git"]`.
+fn read_stack_mixins<E: Debug>() -> crate::Result<Option<Vec<String>>, E> {
+    env::var("CNB_STACK_MIXINS")
+        .ok()
+        .map(|value| parse_stack_mixins(&value).map_err(Error::CannotReadStackMixins))
+        .transpose()
+}
+
+fn parse_stack_mixins(value: &str) -> Result<Vec<String>, serde_json::Error> {
+    serde_json::from_str(value)
+}
+
 fn read_buildpack_dir<E: Debug>() -> crate::Result<PathBuf, E> {
     env::var("CNB_BUILDPACK_DIR")
         .map_err(Error::CannotDetermineBuildpackDirectory)
@@ -213,3 +445,122 @@ fn read_buildpack_descriptor<BM: DeserializeOwned, E: Debug>(
             .map_err(Error::CannotReadBuildpackDescriptor)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::stack_id;
+    use crate::generic::GenericError;
+
+    fn heroku_20_with_mixins() -> Vec<Stack> {
+        vec![Stack::Specific {
+            id: stack_id!("heroku-20"),
+            mixins: vec![
+                String::from("libpq-dev"),
+                String::from("build:git"),
+                String::from("run:imagemagick"),
+            ],
+        }]
+    }
+
+    #[test]
+    fn check_stack_compatibility_matches_stacks() {
+        assert!(check_stack_compatibility::<GenericError>(
+            &[Stack::Any],
+            &stack_id!("io.buildpacks.stacks.bionic"),
+            None
+        )
+        .is_ok());
+
+        assert!(check_stack_compatibility::<GenericError>(
+            &heroku_20_with_mixins(),
+            &stack_id!("heroku-20"),
+            None
+        )
+        .is_ok());
+
+        assert!(matches!(
+            check_stack_compatibility::<GenericError>(
+                &heroku_20_with_mixins(),
+                &stack_id!("heroku-18"),
+                None
+            ),
+            Err(Error::UnsupportedStack(stack_id)) if stack_id == stack_id!("heroku-18")
+        ));
+    }
+
+    #[test]
+    fn check_stack_compatibility_checks_mixins() {
+        assert!(check_stack_compatibility::<GenericError>(
+            &heroku_20_with_mixins(),
+            &stack_id!("heroku-20"),
+            Some(&[String::from("libpq-dev"), String::from("build:git")])
+        )
+        .is_ok());
+
+        assert!(matches!(
+            check_stack_compatibility::<GenericError>(
+                &heroku_20_with_mixins(),
+                &stack_id!("heroku-20"),
+                Some(&[String::from("build:git")])
+            ),
+            Err(Error::MissingStackMixins { mixins, .. }) if mixins == vec![String::from("libpq-dev")]
+        ));
+    }
+
+    #[test]
+    fn check_stack_compatibility_accepts_unprefixed_mixins_for_build_requirements() {
+        assert!(check_stack_compatibility::<GenericError>(
+            &heroku_20_with_mixins(),
+            &stack_id!("heroku-20"),
+            Some(&[String::from("libpq-dev"), String::from("git")])
+        )
+        .is_ok());
+
+        assert!(matches!(
+            check_stack_compatibility::<GenericError>(
+                &heroku_20_with_mixins(),
+                &stack_id!("heroku-20"),
+                Some(&[String::from("libpq-dev"), String::from("run:git")])
+            ),
+            Err(Error::MissingStackMixins { mixins, .. }) if mixins == vec![String::from("build:git")]
+        ));
+    }
+
+    #[test]
+    fn check_stack_compatibility_prefers_specific_stacks() {
+        let mut stacks = vec![Stack::Any];
+        stacks.append(&mut heroku_20_with_mixins());
+
+        assert!(check_stack_compatibility::<GenericError>(
+            &stacks,
+            &stack_id!("io.buildpacks.stacks.bionic"),
+            Some(&[])
+        )
+        .is_ok());
+
+        assert!(matches!(
+            check_stack_compatibility::<GenericError>(
+                &stacks,
+                &stack_id!("heroku-20"),
+                Some(&[String::from("build:git")])
+            ),
+            Err(Error::MissingStackMixins { mixins, .. }) if mixins == vec![String::from("libpq-dev")]
+        ));
+    }
+
+    #[test]
+    fn parse_stack_mixins_label_format() {
+        assert_eq!(
+            parse_stack_mixins(r#"["libpq-dev", "build:git", "run:imagemagick"]"#).unwrap(),
+            vec![
+                String::from("libpq-dev"),
+                String::from("build:git"),
+                String::from("run:imagemagick")
+            ]
+        );
+        assert_eq!(parse_stack_mixins("[]").unwrap(), Vec::<String>::new());
+        assert!(parse_stack_mixins("libpq-dev,build:git").is_err());
+        assert!(parse_stack_mixins(r#"{"mixins": ["libpq-dev"]}"#).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use super::{ContextConfig, TestingError};
+use crate::build::BuildContext;
+use crate::data::buildpack_plan::Entry;
+use crate::detect::DetectContext;
+use crate::Buildpack;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+/// Constructs [`BuildContext`] values for unit tests.
+///
+/// The buildpack descriptor is read from the `buildpack.toml` in the directory of the crate that
+/// is currently tested (`CARGO_MANIFEST_DIR`). The layers, app and platform directories are
+/// temporary directories that are removed when the returned [`TestContext`] is dropped.
+///
+/// # Example:
+/// ```no_run
+/// # use libcnb::build::{BuildContext, BuildResult};
+/// # use libcnb::data::buildpack_plan::Entry;
+/// # use libcnb::data::layer_content_metadata::LayerTypes;
+/// # use libcnb::data::layer_name;
+/// # use libcnb::detect::{DetectContext, DetectResult};
+/// # use libcnb::generic::{GenericError, GenericMetadata, GenericPlatform};
+/// # use libcnb::layer::{Layer, LayerResult, LayerResultBuilder};
+/// # use libcnb::Buildpack;
+/// # use std::path::Path;
+/// use libcnb::testing::BuildContextBuilder;
+///
+/// # struct MyBuildpack;
+/// #
+/// # impl Buildpack for MyBuildpack {
+/// #     type Platform = GenericPlatform;
+/// #     type Metadata = GenericMetadata;
+/// #     type Error = GenericError;
+/// #
+/// #     fn detect(&self, context: DetectContext<Self>) -> libcnb::Result<DetectResult, Self::Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     fn build(&self, context: BuildContext<Self>) -> libcnb::Result<BuildResult, Self::Error> {
+/// #         unimplemented!()
+/// #     }
+/// # }
+/// #
+/// # struct MyLayer;
+/// #
+/// # impl Layer for MyLayer {
+/// #     type Buildpack = MyBuildpack;
+/// #     type Metadata = GenericMetadata;
+/// #
+/// #     fn types(&self) -> LayerTypes {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     fn create(&self, context: &BuildContext<MyBuildpack>, layer_path: &Path) -> Result<LayerResult<GenericMetadata>, GenericError> {
+/// #         unimplemented!()
+/// #     }
+/// # }
+/// #
+/// let context = BuildContextBuilder::<MyBuildpack>::new()
+///     .platform_env("BP_RUBY_VERSION", "3.0.2")
+///     .buildpack_plan_entry(Entry {
+///         name: String::from("ruby"),
+///         metadata: toml::value::Table::new(),
+///     })
+///     .build()
+///     .unwrap();
+///
+/// let layer_data = context.handle_layer(layer_name!("ruby"), MyLayer).unwrap();
+/// assert!(layer_data.path.join("bin/ruby").exists());
+/// ```
+pub struct BuildContextBuilder<B: Buildpack> {
+    config: ContextConfig,
+    layers_dir: Option<PathBuf>,
+    buildpack: PhantomData<B>,
+}
+
+impl<B: Buildpack> BuildContextBuilder<B> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            config: ContextConfig::default(),
+            layers_dir: None,
+            buildpack: PhantomData,
+        }
+    }
+
+    /// Sets the layers directory. Defaults to an empty temporary directory.
+    #[must_use]
+    pub fn layers_dir(mut self, layers_dir: impl Into<PathBuf>) -> Self {
+        self.layers_dir = Some(layers_dir.into());
+        self
+    }
+
+    context_config_setters!();
+
+    /// Adds an entry to the buildpack plan. Defaults to an empty buildpack plan.
+    #[must_use]
+    pub fn buildpack_plan_entry(mut self, entry: Entry) -> Self {
+        self.config.buildpack_plan.entries.push(entry);
+        self
+    }
+
+    pub fn build(self) -> Result<TestContext<BuildContext<B>>, TestingError<B::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let layers_dir = self
+            .layers_dir
+            .unwrap_or_else(|| temp_dir.path().join("layers"));
+
+        let context = self.config.build_context(temp_dir.path(), layers_dir)?;
+
+        Ok(TestContext { context, temp_dir })
+    }
+}
+
+impl<B: Buildpack> Default for BuildContextBuilder<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Constructs [`DetectContext`] values for unit tests.
+///
+/// See [`BuildContextBuilder`] for details about the defaults.
+pub struct DetectContextBuilder<B: Buildpack> {
+    config: ContextConfig,
+    buildpack: PhantomData<B>,
+}
+
+impl<B: Buildpack> DetectContextBuilder<B> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            config: ContextConfig::default(),
+            buildpack: PhantomData,
+        }
+    }
+
+    context_config_setters!();
+
+    pub fn build(self) -> Result<TestContext<DetectContext<B>>, TestingError<B::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let context = self.config.detect_context(temp_dir.path())?;
+
+        Ok(TestContext { context, temp_dir })
+    }
+}
+
+impl<B: Buildpack> Default for DetectContextBuilder<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A context created for tests, together with the temporary directory it uses.
+///
+/// Dereferences to the wrapped context. The temporary directory is removed when this value is
+/// dropped.
+pub struct TestContext<C> {
+    context: C,
+    temp_dir: TempDir,
+}
+
+impl<C> TestContext<C> {
+    /// Returns the wrapped context and the temporary directory. The directory is removed when the
+    /// returned [`TempDir`] is dropped.
+    pub fn into_parts(self) -> (C, TempDir) {
+        (self.context, self.temp_dir)
+    }
+}
+
+impl<C> Deref for TestContext<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.context
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::BuildResult;
+    use crate::data::stack_id;
+    use crate::detect::DetectResult;
+    use crate::generic::{GenericError, GenericMetadata, GenericPlatform};
+    use crate::testing::test_buildpack_dir;
+    use crate::Platform;
+
+    struct TestBuildpack;
+
+    impl Buildpack for TestBuildpack {
+        type Platform = GenericPlatform;
+        type Metadata = GenericMetadata;
+        type Error = GenericError;
+
+        fn detect(
+            &self,
+            _context: DetectContext<Self>,
+        ) -> crate::Result<DetectResult, Self::Error> {
+            unimplemented!()
+        }
+
+        fn build(&self, _context: BuildContext<Self>) -> crate::Result<BuildResult, Self::Error> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn build_context_defaults() {
+        let buildpack_dir = test_buildpack_dir("*", false);
+
+        let context = BuildContextBuilder::<TestBuildpack>::new()
+            .buildpack_dir(buildpack_dir.path())
+            .build()
+            .unwrap();
+
+        assert!(context.layers_dir.is_dir());
+        assert!(context.app_dir.is_dir());
+        assert_eq!(context.stack_id, stack_id!("heroku-20"));
+        assert_eq!(
+            context.buildpack_descriptor.buildpack.id.as_str(),
+            "libcnb/test"
+        );
+        assert!(context.buildpack_plan.entries.is_empty());
+        assert_eq!(context.platform.env().iter().count(), 0);
+    }
+
+    #[test]
+    fn build_context_overrides() {
+        let buildpack_dir = test_buildpack_dir("*", false);
+
+        let context = BuildContextBuilder::<TestBuildpack>::new()
+            .buildpack_dir(buildpack_dir.path())
+            .stack_id(stack_id!("io.buildpacks.stacks.bionic"))
+            .platform_env("FOO", "bar")
+            .buildpack_plan_entry(Entry {
+                name: String::from("ruby"),
+                metadata: toml::value::Table::new(),
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!(context.stack_id, stack_id!("io.buildpacks.stacks.bionic"));
+        assert_eq!(context.platform.env().get("FOO").unwrap(), "bar");
+        assert_eq!(context.buildpack_plan.entries[0].name, "ruby");
+    }
+
+    #[test]
+    fn build_context_build_env() {
+        let buildpack_dir = test_buildpack_dir("*", false);
+
+        let context = BuildContextBuilder::<TestBuildpack>::new()
+            .buildpack_dir(buildpack_dir.path())
+            .platform_env("FOO", "bar")
+            .build()
+            .unwrap();
+
+        let build_env = context.build_env();
+        assert_eq!(build_env.get("FOO").unwrap(), "bar");
+        assert_eq!(build_env.get("PATH"), std::env::var_os("PATH"));
+    }
+
+    #[test]
+    fn build_context_build_env_with_clear_env() {
+        let buildpack_dir = test_buildpack_dir("*", true);
+
+        let context = BuildContextBuilder::<TestBuildpack>::new()
+            .buildpack_dir(buildpack_dir.path())
+            .platform_env("FOO", "bar")
+            .build()
+            .unwrap();
+
+        let build_env = context.build_env();
+        assert_eq!(build_env.get("FOO").unwrap(), "bar");
+        assert_eq!(build_env.iter().count(), 1);
+    }
+
+    #[test]
+    fn temp_dir_removed_on_drop() {
+        let buildpack_dir = test_buildpack_dir("*", false);
+
+        let context = DetectContextBuilder::<TestBuildpack>::new()
+            .buildpack_dir(buildpack_dir.path())
+            .build()
+            .unwrap();
+
+        let app_dir = context.app_dir.clone();
+        assert!(app_dir.is_dir());
+
+        drop(context);
+        assert!(!app_dir.exists());
+    }
+}
@@ -0,0 +1,260 @@
+//! Utilities for testing buildpacks without a CNB lifecycle.
+//!
+//! This module is only available when the `testing` feature is enabled. It is meant to be used
+//! from the tests of a buildpack crate, usually by adding libcnb with the `testing` feature as a
+//! dev-dependency.
+//!
+//! Use [`TestRunner`] to run the detect and build phases of a buildpack and
+//! [`BuildContextBuilder`] or [`DetectContextBuilder`] to unit test code that requires a context,
+//! for example a single [`Layer`](crate::layer::Layer) implementation.
+
+/// Generates the setters for the [`ContextConfig`] inputs shared by [`TestRunner`],
+/// [`BuildContextBuilder`] and [`DetectContextBuilder`]. The setters write to the `config` field
+/// of the type they are generated for.
+macro_rules! context_config_setters {
+    () => {
+        /// Sets the app directory. Defaults to an empty temporary directory.
+        ///
+        /// **NOTE**: The buildpack runs directly against this directory. Any modifications the
+        /// buildpack makes to the app directory will not be reverted.
+        #[must_use]
+        pub fn app_dir(mut self, app_dir: impl Into<::std::path::PathBuf>) -> Self {
+            self.config.app_dir = Some(app_dir.into());
+            self
+        }
+
+        /// Sets the buildpack directory. Defaults to the directory of the crate that is currently
+        /// tested. The directory must contain the `buildpack.toml` of the buildpack.
+        #[must_use]
+        pub fn buildpack_dir(mut self, buildpack_dir: impl Into<::std::path::PathBuf>) -> Self {
+            self.config.buildpack_dir = Some(buildpack_dir.into());
+            self
+        }
+
+        /// Sets the stack id. Defaults to the first stack of the buildpack descriptor, or
+        /// `heroku-20` if the buildpack supports any stack.
+        #[must_use]
+        pub fn stack_id(mut self, stack_id: crate::data::buildpack::StackId) -> Self {
+            self.config.stack_id = Some(stack_id);
+            self
+        }
+
+        /// Adds a user-provided environment variable to the platform directory.
+        #[must_use]
+        pub fn platform_env(
+            mut self,
+            key: impl Into<::std::ffi::OsString>,
+            value: impl Into<::std::ffi::OsString>,
+        ) -> Self {
+            self.config.platform_env.insert(key, value);
+            self
+        }
+    };
+}
+
+mod context;
+mod runner;
+
+pub use context::*;
+pub use runner::*;
+
+use crate::build::{initial_build_env, BuildContext};
+use crate::data::buildpack::{SingleBuildpackDescriptor, Stack, StackId};
+use crate::data::buildpack_plan::BuildpackPlan;
+use crate::data::stack_id;
+use crate::detect::DetectContext;
+use crate::layer::ReadLayerError;
+use crate::platform::Platform;
+use crate::toml_file::{read_toml_file, TomlFileError};
+use crate::util::default_on_not_found;
+use crate::{Buildpack, Env};
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(thiserror::Error, Debug)]
+pub enum TestingError<E: Debug> {
+    #[error("libcnb error: {0}")]
+    LibcnbError(#[from] crate::Error<E>),
+
+    #[error("Could not determine buildpack directory: {0}")]
+    CannotDetermineBuildpackDirectory(std::env::VarError),
+
+    #[error("Could not read buildpack output: {0}")]
+    CannotReadBuildpackOutput(TomlFileError),
+
+    #[error("Could not read layer: {0}")]
+    CannotReadLayer(#[from] ReadLayerError),
+
+    #[error("Unexpected IoError: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Inputs shared by all contexts created by this module. Values that are not set explicitly are
+/// given sensible defaults when a context is created.
+#[derive(Default)]
+struct ContextConfig {
+    app_dir: Option<PathBuf>,
+    buildpack_dir: Option<PathBuf>,
+    stack_id: Option<StackId>,
+    platform_env: Env,
+    buildpack_plan: BuildpackPlan,
+}
+
+impl ContextConfig {
+    /// Creates a detect context. Directories that are not explicitly configured are created in
+    /// the given temporary directory.
+    fn detect_context<B: Buildpack>(
+        &self,
+        temp_dir: &Path,
+    ) -> Result<DetectContext<B>, TestingError<B::Error>> {
+        let buildpack_dir = self.buildpack_dir()?;
+        let buildpack_descriptor = read_buildpack_descriptor::<B>(&buildpack_dir)?;
+
+        Ok(DetectContext {
+            app_dir: self.app_dir(temp_dir)?,
+            buildpack_dir,
+            stack_id: self.stack_id(&buildpack_descriptor),
+            platform: self.platform::<B>(temp_dir)?,
+            buildpack_descriptor,
+        })
+    }
+
+    /// Creates a build context. Directories that are not explicitly configured are created in
+    /// the given temporary directory.
+    fn build_context<B: Buildpack>(
+        &self,
+        temp_dir: &Path,
+        layers_dir: PathBuf,
+    ) -> Result<BuildContext<B>, TestingError<B::Error>> {
+        let buildpack_dir = self.buildpack_dir()?;
+        let buildpack_descriptor = read_buildpack_descriptor::<B>(&buildpack_dir)?;
+
+        fs::create_dir_all(&layers_dir)?;
+
+        let platform = self.platform::<B>(temp_dir)?;
+        let build_env = initial_build_env(buildpack_descriptor.buildpack.clear_env, &platform);
+
+        Ok(BuildContext {
+            layers_dir,
+            app_dir: self.app_dir(temp_dir)?,
+            buildpack_dir,
+            stack_id: self.stack_id(&buildpack_descriptor),
+            platform,
+            buildpack_plan: self.buildpack_plan.clone(),
+            buildpack_descriptor,
+            build_env: Mutex::new(build_env),
+            handled_layers: Mutex::new(vec![]),
+        })
+    }
+
+    fn app_dir(&self, temp_dir: &Path) -> std::io::Result<PathBuf> {
+        if let Some(app_dir) = &self.app_dir {
+            Ok(app_dir.clone())
+        } else {
+            let app_dir = temp_dir.join("app");
+            fs::create_dir_all(&app_dir)?;
+            Ok(app_dir)
+        }
+    }
+
+    fn buildpack_dir<E: Debug>(&self) -> Result<PathBuf, TestingError<E>> {
+        match &self.buildpack_dir {
+            Some(buildpack_dir) => Ok(buildpack_dir.clone()),
+            None => std::env::var("CARGO_MANIFEST_DIR")
+                .map(PathBuf::from)
+                .map_err(TestingError::CannotDetermineBuildpackDirectory),
+        }
+    }
+
+    fn stack_id<BM>(&self, buildpack_descriptor: &SingleBuildpackDescriptor<BM>) -> StackId {
+        self.stack_id.clone().unwrap_or_else(|| {
+            buildpack_descriptor
+                .stacks
+                .iter()
+                .find_map(|stack| match stack {
+                    Stack::Specific { id, .. } => Some(id.clone()),
+                    Stack::Any => None,
+                })
+                .unwrap_or_else(|| stack_id!("heroku-20"))
+        })
+    }
+
+    fn platform<B: Buildpack>(
+        &self,
+        temp_dir: &Path,
+    ) -> Result<B::Platform, TestingError<B::Error>> {
+        let platform_dir = temp_dir.join("platform");
+        write_platform_env(&platform_dir, &self.platform_env)?;
+
+        B::Platform::from_path(&platform_dir)
+            .map_err(crate::Error::CannotCreatePlatformFromPath)
+            .map_err(TestingError::from)
+    }
+}
+
+fn read_buildpack_descriptor<B: Buildpack>(
+    buildpack_dir: &Path,
+) -> Result<SingleBuildpackDescriptor<B::Metadata>, TestingError<B::Error>> {
+    read_toml_file(buildpack_dir.join("buildpack.toml"))
+        .map_err(crate::Error::CannotReadBuildpackDescriptor)
+        .map_err(TestingError::from)
+}
+
+fn write_platform_env(platform_dir: &Path, env: &Env) -> std::io::Result<()> {
+    let env_dir = platform_dir.join("env");
+    default_on_not_found(fs::remove_dir_all(&env_dir))?;
+    fs::create_dir_all(&env_dir)?;
+
+    for (key, value) in env {
+        #[cfg(target_family = "unix")]
+        let value = {
+            use std::os::unix::ffi::OsStrExt;
+            value.as_bytes()
+        };
+
+        // Platform environment variables must be valid Unicode on other platforms.
+        #[cfg(not(target_family = "unix"))]
+        let value = value.to_str().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Platform environment variable {:?} is not valid Unicode",
+                    key
+                ),
+            )
+        })?;
+
+        fs::write(env_dir.join(key), value)?;
+    }
+
+    Ok(())
+}
+
+/// Creates a temporary buildpack directory with a minimal `buildpack.toml` for the tests of this
+/// module.
+#[cfg(test)]
+fn test_buildpack_dir(stack_id: &str, clear_env: bool) -> tempfile::TempDir {
+    let buildpack_dir = tempfile::tempdir().unwrap();
+    fs::write(
+        buildpack_dir.path().join("buildpack.toml"),
+        format!(
+            r#"
+api = "0.6"
+
+[buildpack]
+id = "libcnb/test"
+version = "0.1.0"
+clear-env = {}
+
+[[stacks]]
+id = "{}"
+"#,
+            clear_env, stack_id
+        ),
+    )
+    .unwrap();
+
+    buildpack_dir
+}
@@ -0,0 +1,318 @@
+use super::{ContextConfig, TestingError};
+use crate::data::build::Build;
+use crate::data::build_plan::BuildPlan;
+use crate::data::buildpack_plan::Entry;
+use crate::data::launch::Launch;
+use crate::data::layer::LayerName;
+use crate::data::store::Store;
+use crate::generic::GenericMetadata;
+use crate::layer::{existing_layers, read_layer, LayerData};
+use crate::runtime::{run_build, run_detect};
+use crate::toml_file::read_toml_file;
+use crate::util::default_on_not_found;
+use crate::Buildpack;
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Runs the detect and build phases of a buildpack in-process, without a CNB lifecycle.
+///
+/// Each runner owns a temporary directory that contains the layers and platform directories as
+/// well as an empty app directory. Subsequent builds with the same runner use the same layers
+/// directory, which allows testing how a buildpack deals with existing layers.
+///
+/// By default, the buildpack descriptor is read from the `buildpack.toml` in the directory of the
+/// crate that is currently tested (`CARGO_MANIFEST_DIR`).
+///
+/// # Example:
+/// ```no_run
+/// # use libcnb::build::{BuildContext, BuildResult, BuildResultBuilder};
+/// # use libcnb::detect::{DetectContext, DetectResult, DetectResultBuilder};
+/// # use libcnb::generic::{GenericError, GenericMetadata, GenericPlatform};
+/// # use libcnb::Buildpack;
+/// use libcnb::testing::{DetectOutcome, TestRunner};
+///
+/// # struct MyBuildpack;
+/// #
+/// # impl Buildpack for MyBuildpack {
+/// #     type Platform = GenericPlatform;
+/// #     type Metadata = GenericMetadata;
+/// #     type Error = GenericError;
+/// #
+/// #     fn detect(&self, context: DetectContext<Self>) -> libcnb::Result<DetectResult, Self::Error> {
+/// #         DetectResultBuilder::pass().build()
+/// #     }
+/// #
+/// #     fn build(&self, context: BuildContext<Self>) -> libcnb::Result<BuildResult, Self::Error> {
+/// #         BuildResultBuilder::new().build()
+/// #     }
+/// # }
+/// #
+/// let runner = TestRunner::new()
+///     .unwrap()
+///     .app_dir("tests/fixtures/app")
+///     .platform_env("BP_LOG_LEVEL", "DEBUG");
+///
+/// assert!(matches!(runner.detect(&MyBuildpack).unwrap(), DetectOutcome::Pass(_)));
+///
+/// let build_outcome = runner.build(&MyBuildpack).unwrap();
+/// assert!(build_outcome.launch.is_none());
+/// assert!(build_outcome.layers.is_empty());
+/// ```
+pub struct TestRunner {
+    temp_dir: TempDir,
+    config: ContextConfig,
+}
+
+impl TestRunner {
+    /// Creates a new runner with its own temporary directory.
+    pub fn new() -> std::io::Result<Self> {
+        let temp_dir = tempfile::tempdir()?;
+        fs::create_dir_all(temp_dir.path().join("layers"))?;
+
+        Ok(Self {
+            temp_dir,
+            config: ContextConfig::default(),
+        })
+    }
+
+    context_config_setters!();
+
+    /// Adds an entry to the buildpack plan that is passed to the build phase.
+    #[must_use]
+    pub fn buildpack_plan_entry(mut self, entry: Entry) -> Self {
+        self.config.buildpack_plan.entries.push(entry);
+        self
+    }
+
+    /// The layers directory used for builds of this runner.
+    #[must_use]
+    pub fn layers_dir(&self) -> PathBuf {
+        self.temp_dir.path().join("layers")
+    }
+
+    /// Runs the detect phase of the given buildpack.
+    pub fn detect<B: Buildpack>(
+        &self,
+        buildpack: &B,
+    ) -> Result<DetectOutcome, TestingError<B::Error>> {
+        let context = self.config.detect_context(self.temp_dir.path())?;
+        let build_plan_path = self.temp_dir.path().join("plan.toml");
+
+        default_on_not_found(fs::remove_file(&build_plan_path))?;
+
+        if run_detect(buildpack, context, None, &build_plan_path)? {
+            let build_plan = read_optional_toml_file(&build_plan_path)?;
+            Ok(DetectOutcome::Pass(build_plan))
+        } else {
+            Ok(DetectOutcome::Fail)
+        }
+    }
+
+    /// Runs the build phase of the given buildpack.
+    pub fn build<B: Buildpack>(
+        &self,
+        buildpack: &B,
+    ) -> Result<BuildOutcome, TestingError<B::Error>> {
+        let layers_dir = self.layers_dir();
+
+        for file_name in ["launch.toml", "store.toml", "build.toml"] {
+            default_on_not_found(fs::remove_file(layers_dir.join(file_name)))?;
+        }
+
+        let context = self
+            .config
+            .build_context(self.temp_dir.path(), layers_dir.clone())?;
+
+        run_build(buildpack, context)?;
+
+        Ok(BuildOutcome {
+            launch: read_optional_toml_file(layers_dir.join("launch.toml"))?,
+            store: read_optional_toml_file(layers_dir.join("store.toml"))?,
+            build: read_optional_toml_file(layers_dir.join("build.toml"))?,
+            layers: read_layers(&layers_dir)?,
+        })
+    }
+}
+
+/// The outcome of [`TestRunner::detect`].
+#[derive(Debug)]
+pub enum DetectOutcome {
+    /// The buildpack passed detection, with the build plan it wrote, if any.
+    Pass(Option<BuildPlan>),
+    /// The buildpack failed detection.
+    Fail,
+}
+
+/// The outcome of [`TestRunner::build`].
+#[derive(Debug)]
+pub struct BuildOutcome {
+    /// The contents of `launch.toml`, if written.
+    pub launch: Option<Launch>,
+    /// The contents of `store.toml`, if written.
+    pub store: Option<Store>,
+    /// The contents of `build.toml`, if written.
+    pub build: Option<Build>,
+    /// All layers in the layers directory after the build, ordered by name.
+    pub layers: Vec<LayerData<GenericMetadata>>,
+}
+
+impl BuildOutcome {
+    /// Returns the layer with the given name, if it exists.
+    #[must_use]
+    pub fn layer(&self, layer_name: &LayerName) -> Option<&LayerData<GenericMetadata>> {
+        self.layers.iter().find(|layer| &layer.name == layer_name)
+    }
+}
+
+fn read_optional_toml_file<A: DeserializeOwned, E: Debug>(
+    path: impl AsRef<Path>,
+) -> Result<Option<A>, TestingError<E>> {
+    if path.as_ref().exists() {
+        read_toml_file(path)
+            .map(Some)
+            .map_err(TestingError::CannotReadBuildpackOutput)
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_layers<E: Debug>(
+    layers_dir: &Path,
+) -> Result<Vec<LayerData<GenericMetadata>>, TestingError<E>> {
+    let mut layers = Vec::new();
+    for layer_name in existing_layers(layers_dir)? {
+        if let Some(layer_data) = read_layer(layers_dir, &layer_name)? {
+            layers.push(layer_data);
+        }
+    }
+
+    Ok(layers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::{BuildContext, BuildResult, BuildResultBuilder};
+    use crate::data::build_plan::BuildPlanBuilder;
+    use crate::data::launch::Process;
+    use crate::data::layer_content_metadata::LayerTypes;
+    use crate::data::{layer_name, process_type};
+    use crate::detect::{DetectContext, DetectResult, DetectResultBuilder};
+    use crate::generic::{GenericError, GenericPlatform};
+    use crate::layer::{Layer, LayerResult, LayerResultBuilder};
+    use crate::testing::test_buildpack_dir;
+    use crate::Platform;
+
+    struct TestBuildpack;
+
+    impl Buildpack for TestBuildpack {
+        type Platform = GenericPlatform;
+        type Metadata = GenericMetadata;
+        type Error = GenericError;
+
+        fn detect(&self, context: DetectContext<Self>) -> crate::Result<DetectResult, Self::Error> {
+            if context.platform.env().contains_key("FAIL_DETECT") {
+                DetectResultBuilder::fail().build()
+            } else {
+                DetectResultBuilder::pass()
+                    .build_plan(BuildPlanBuilder::new().provides("test").build())
+                    .build()
+            }
+        }
+
+        fn build(&self, context: BuildContext<Self>) -> crate::Result<BuildResult, Self::Error> {
+            context.handle_layer(layer_name!("test-layer"), TestLayer)?;
+
+            BuildResultBuilder::new()
+                .launch(Launch::new().process(Process::new(
+                    process_type!("web"),
+                    "web",
+                    None::<Vec<String>>,
+                    None,
+                    Some(true),
+                )))
+                .build()
+        }
+    }
+
+    struct TestLayer;
+
+    impl Layer for TestLayer {
+        type Buildpack = TestBuildpack;
+        type Metadata = GenericMetadata;
+
+        fn types(&self) -> LayerTypes {
+            LayerTypes {
+                launch: true,
+                build: false,
+                cache: true,
+            }
+        }
+
+        fn create(
+            &self,
+            context: &BuildContext<Self::Buildpack>,
+            layer_path: &Path,
+        ) -> Result<LayerResult<Self::Metadata>, GenericError> {
+            fs::write(layer_path.join("stack"), context.stack_id.as_str()).unwrap();
+            LayerResultBuilder::new(None).build()
+        }
+    }
+
+    fn test_runner() -> (TempDir, TestRunner) {
+        let buildpack_dir = test_buildpack_dir("io.buildpacks.stacks.bionic", false);
+
+        let test_runner = TestRunner::new()
+            .unwrap()
+            .buildpack_dir(buildpack_dir.path());
+
+        (buildpack_dir, test_runner)
+    }
+
+    #[test]
+    fn detect_pass() {
+        let (_buildpack_dir, test_runner) = test_runner();
+
+        match test_runner.detect(&TestBuildpack).unwrap() {
+            DetectOutcome::Pass(Some(build_plan)) => {
+                assert_eq!(build_plan.provides[0].name, "test");
+            }
+            other => panic!("Unexpected detect outcome: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn detect_fail_with_platform_env() {
+        let (_buildpack_dir, test_runner) = test_runner();
+        let test_runner = test_runner.platform_env("FAIL_DETECT", "true");
+
+        assert!(matches!(
+            test_runner.detect(&TestBuildpack).unwrap(),
+            DetectOutcome::Fail
+        ));
+    }
+
+    #[test]
+    fn build() {
+        let (_buildpack_dir, test_runner) = test_runner();
+
+        let build_outcome = test_runner.build(&TestBuildpack).unwrap();
+
+        assert_eq!(
+            build_outcome.launch.as_ref().unwrap().processes[0].r#type,
+            process_type!("web")
+        );
+        assert!(build_outcome.store.is_none());
+        assert!(build_outcome.build.is_none());
+        assert_eq!(build_outcome.layers.len(), 1);
+
+        let layer = build_outcome.layer(&layer_name!("test-layer")).unwrap();
+        assert_eq!(
+            fs::read_to_string(layer.path.join("stack")).unwrap(),
+            "io.buildpacks.stacks.bionic"
+        );
+    }
+}
@@ -1,3 +1,6 @@
+use std::fs;
+use std::path::Path;
+
 /// Removes [`std::io::Error`] values from a [`Result`] that have the
 /// [`std::io::ErrorKind::NotFound`] error kind by replacing them with the default value for `T`.
 pub(crate) fn default_on_not_found<T: Default>(
@@ -12,10 +15,74 @@ pub(crate) fn default_on_not_found<T: Default>(
     }
 }
 
+/// Recursively copies the given directory, preserving permissions and symbolic links.
+pub(crate) fn copy_dir_recursively(
+    from: impl AsRef<Path>,
+    to: impl AsRef<Path>,
+) -> Result<(), std::io::Error> {
+    let from = from.as_ref();
+    let to = to.as_ref();
+
+    fs::create_dir_all(to)?;
+
+    for dir_entry in fs::read_dir(from)? {
+        let dir_entry = dir_entry?;
+        let file_type = dir_entry.file_type()?;
+        let target_path = to.join(dir_entry.file_name());
+
+        if file_type.is_dir() {
+            copy_dir_recursively(dir_entry.path(), &target_path)?;
+        } else if file_type.is_symlink() {
+            symlink(
+                fs::read_link(dir_entry.path())?,
+                &target_path,
+                dir_entry.path().is_dir(),
+            )?;
+        } else {
+            fs::copy(dir_entry.path(), &target_path)?;
+        }
+    }
+
+    fs::set_permissions(to, fs::metadata(from)?.permissions())
+}
+
+/// Creates a symbolic link at `link` that points to the directory at `original`.
+pub(crate) fn symlink_dir(
+    original: impl AsRef<Path>,
+    link: impl AsRef<Path>,
+) -> Result<(), std::io::Error> {
+    symlink(original, link, true)
+}
+
+#[cfg(target_family = "unix")]
+fn symlink(
+    original: impl AsRef<Path>,
+    link: impl AsRef<Path>,
+    _is_dir: bool,
+) -> Result<(), std::io::Error> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+// Windows distinguishes between symbolic links to files and to directories.
+#[cfg(target_family = "windows")]
+fn symlink(
+    original: impl AsRef<Path>,
+    link: impl AsRef<Path>,
+    is_dir: bool,
+) -> Result<(), std::io::Error> {
+    if is_dir {
+        std::os::windows::fs::symlink_dir(original, link)
+    } else {
+        std::os::windows::fs::symlink_file(original, link)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::util::default_on_not_found;
+    use crate::util::{copy_dir_recursively, default_on_not_found, symlink_dir};
+    use std::fs;
     use std::io::ErrorKind;
+    use std::path::PathBuf;
 
     #[test]
     fn default_on_not_found_with_notfound() {
@@ -38,4 +105,30 @@ mod tests {
     fn default_on_not_found_with_ok() {
         assert_eq!(default_on_not_found(Ok("Hello!")).unwrap(), "Hello!");
     }
+
+    #[test]
+    fn copy_dir_recursively_copies_files_and_symlinks() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let from = temp_dir.path().join("from");
+        let to = temp_dir.path().join("to");
+
+        fs::create_dir_all(from.join("nested")).unwrap();
+        fs::write(from.join("nested").join("file"), "contents").unwrap();
+        symlink_dir("nested", from.join("link")).unwrap();
+
+        copy_dir_recursively(&from, &to).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(to.join("nested").join("file")).unwrap(),
+            "contents"
+        );
+        assert_eq!(
+            fs::read_link(to.join("link")).unwrap(),
+            PathBuf::from("nested")
+        );
+        assert_eq!(
+            fs::read_to_string(to.join("link").join("file")).unwrap(),
+            "contents"
+        );
+    }
 }